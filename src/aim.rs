@@ -0,0 +1,320 @@
+use crate::{
+    judgement::{self, HitResult, Judgements},
+    map::Beatmap,
+    osu_data::GameMode,
+    replay::{Mods, Replay},
+};
+
+/// Where the cursor was relative to a circle or slider head when it was hit.
+///
+/// Offsets are normalised by the circle radius, so an offset of length 1 is on the edge of the circle.
+#[derive(Debug, Clone, Copy)]
+pub struct AimHit {
+    object_index: usize,
+    time: f64,
+    result: HitResult,
+    offset: (f64, f64),
+    jump: Option<Jump>,
+}
+
+/// The movement from the end of the previous object to an object.
+#[derive(Debug, Clone, Copy)]
+struct Jump {
+    /// The normalised direction of the movement
+    direction: (f64, f64),
+    /// The length of the movement in osu!pixels
+    distance: f64,
+}
+
+impl AimHit {
+    /// The index of the object in the beatmap's hit objects.
+    pub fn object_index(&self) -> usize {
+        self.object_index
+    }
+
+    /// The time of the click.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn result(&self) -> HitResult {
+        self.result
+    }
+
+    /// The offset from the object's centre on the playfield (after mods like Hard Rock have been applied).
+    pub fn offset(&self) -> (f64, f64) {
+        self.offset
+    }
+
+    /// The offset rotated relative to the movement from the previous object: the first component is
+    /// positive for overshooting and negative for undershooting the object, the second component
+    /// is positive if the cursor was to the right of the movement (on the playfield) and negative if it was to the left.
+    ///
+    /// Not present for the first object or objects stacked on the end of the previous one, which have no direction.
+    pub fn relative_offset(&self) -> Option<(f64, f64)> {
+        let (x, y) = self.jump?.direction;
+        let (dx, dy) = self.offset;
+        Some((dx * x + dy * y, dy * x - dx * y))
+    }
+
+    /// The distance from the end of the previous object in osu!pixels.
+    pub fn jump_distance(&self) -> Option<f64> {
+        self.jump.map(|jump| jump.distance)
+    }
+
+    /// The distance from the object's centre relative to the radius.
+    pub fn distance(&self) -> f64 {
+        self.offset.0.hypot(self.offset.1)
+    }
+}
+
+/// The accuracy of the hits on objects whose jump distance is in a certain range.
+#[derive(Debug, Clone, Copy)]
+pub struct JumpAccuracy {
+    min_distance: f64,
+    max_distance: f64,
+    count: usize,
+    bias: (f64, f64),
+    mean_distance: f64,
+}
+
+impl JumpAccuracy {
+    /// The shortest jump distance (inclusive) in osu!pixels.
+    pub fn min_distance(&self) -> f64 {
+        self.min_distance
+    }
+
+    /// The longest jump distance (exclusive) in osu!pixels.
+    pub fn max_distance(&self) -> f64 {
+        self.max_distance
+    }
+
+    /// The number of hits in this range.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// The average offset relative to the movement, see [`AimHit::relative_offset`].
+    pub fn bias(&self) -> (f64, f64) {
+        self.bias
+    }
+
+    /// The average distance from the centre of the objects relative to the radius.
+    pub fn mean_distance(&self) -> f64 {
+        self.mean_distance
+    }
+}
+
+/// Where the cursor was on every circle and slider head hit in an osu!standard play,
+/// which shows whether the player aims off centre or systematically over- or undershoots jumps.
+#[derive(Debug, Clone, Default)]
+pub struct AimAnalysis {
+    hits: Vec<AimHit>,
+}
+
+impl AimAnalysis {
+    /// Collects the cursor offsets from the judgements of a play of the (unmodified) beatmap with the given mods.
+    pub fn new(map: &Beatmap, judgements: &Judgements, mods: Mods) -> Self {
+        let radius = map.difficulty().with_mods(mods).circle_radius();
+        let map = map.apply_mods_in_song_time(mods);
+        let objects = map.hit_objects();
+
+        let hits = judgements
+            .iter()
+            .filter_map(|judgement| {
+                let (dx, dy) = judgement.cursor_offset()?;
+                let index = judgement.object_index();
+                let position = objects[index].stacked_position(radius);
+                let jump = index
+                    .checked_sub(1)
+                    .map(|previous| objects[previous].stacked_end_position(radius))
+                    .and_then(|from| {
+                        let (x, y) = (position.0 - from.0, position.1 - from.1);
+                        let distance = x.hypot(y) as f64;
+                        (distance > f32::EPSILON as f64).then(|| Jump {
+                            direction: (x as f64 / distance, y as f64 / distance),
+                            distance,
+                        })
+                    });
+                Some(AimHit {
+                    object_index: index,
+                    time: objects[index].timestamp() as f64 + judgement.hit_error()?,
+                    result: judgement.result(),
+                    offset: (dx as f64 / radius as f64, dy as f64 / radius as f64),
+                    jump,
+                })
+            })
+            .collect();
+        Self { hits }
+    }
+
+    /// Judges the replay on the given (unmodified) beatmap and collects the cursor offsets.
+    pub fn for_replay(map: &Beatmap, replay: &Replay) -> Result<Self, &'static str> {
+        if !matches!(replay.mode, GameMode::Standard) {
+            return Err("Aim can only be analysed in osu!standard replays");
+        }
+        let judgements = judgement::standard::judge(map, replay)?;
+        Ok(Self::new(map, &judgements, replay.mods))
+    }
+
+    /// Every object which was clicked, ordered by object.
+    pub fn hits(&self) -> &[AimHit] {
+        &self.hits
+    }
+
+    /// The average offset relative to the movement. A positive first component means the player
+    /// tends to overshoot, a negative one that they undershoot.
+    pub fn bias(&self) -> Option<(f64, f64)> {
+        mean_offset(self.hits.iter().filter_map(AimHit::relative_offset))
+    }
+
+    /// The average offset on the playfield, which shows whether the player tends to hit e.g. below the centre.
+    pub fn playfield_bias(&self) -> Option<(f64, f64)> {
+        mean_offset(self.hits.iter().map(AimHit::offset))
+    }
+
+    /// The average distance from the centre of the objects relative to the radius.
+    pub fn mean_distance(&self) -> Option<f64> {
+        if self.hits.is_empty() {
+            return None;
+        }
+        Some(self.hits.iter().map(AimHit::distance).sum::<f64>() / self.hits.len() as f64)
+    }
+
+    /// The accuracy of the hits grouped by jump distance into ranges of the given width in osu!pixels,
+    /// ordered by distance. Ranges without any hits are left out.
+    pub fn by_jump_distance(&self, bucket_width: f64) -> Vec<JumpAccuracy> {
+        let mut buckets: Vec<Vec<&AimHit>> = vec![];
+        for hit in &self.hits {
+            let Some(distance) = hit.jump_distance() else {
+                continue;
+            };
+            let bucket = (distance / bucket_width) as usize;
+            if bucket >= buckets.len() {
+                buckets.resize(bucket + 1, vec![]);
+            }
+            buckets[bucket].push(hit);
+        }
+
+        buckets
+            .into_iter()
+            .enumerate()
+            .filter(|(_, hits)| !hits.is_empty())
+            .map(|(i, hits)| JumpAccuracy {
+                min_distance: i as f64 * bucket_width,
+                max_distance: (i + 1) as f64 * bucket_width,
+                count: hits.len(),
+                bias: mean_offset(hits.iter().filter_map(|hit| hit.relative_offset()))
+                    .unwrap_or_default(),
+                mean_distance: hits.iter().map(|hit| hit.distance()).sum::<f64>()
+                    / hits.len() as f64,
+            })
+            .collect()
+    }
+}
+
+fn mean_offset(offsets: impl IntoIterator<Item = (f64, f64)>) -> Option<(f64, f64)> {
+    let (sum, count) = offsets
+        .into_iter()
+        .fold(((0.0, 0.0), 0), |((x, y), count), (dx, dy)| {
+            ((x + dx, y + dy), count + 1)
+        });
+    (count > 0).then(|| (sum.0 / count as f64, sum.1 / count as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        replay::InputKeys,
+        test_data::{self, TestMap},
+    };
+
+    use super::*;
+
+    fn assert_offset(actual: Option<(f64, f64)>, expected: (f64, f64)) {
+        let (x, y) = actual.unwrap();
+        assert!(
+            (x - expected.0).abs() < 1e-6 && (y - expected.1).abs() < 1e-6,
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    /// Jumps right and then up, clicked half a radius right of the first two circles and left of the third.
+    fn analysis() -> AimAnalysis {
+        let map =
+            TestMap::new(&["100,192,1000,1,0", "300,192,1500,1,0", "300,100,2000,1,0"]).build();
+        let half_radius = map.difficulty().circle_radius() / 2.0;
+        let frames = [
+            (1000, 100.0 + half_radius, 192.0),
+            (1500, 300.0 + half_radius, 192.0),
+            (2000, 300.0 - half_radius, 100.0),
+        ]
+        .iter()
+        .flat_map(|&(time, x, y)| {
+            [
+                (time, x, y, InputKeys::K1),
+                (time + 50, x, y, InputKeys::empty()),
+            ]
+        })
+        .collect::<Vec<_>>();
+        let replay = test_data::replay(GameMode::Standard, Mods::empty(), &frames);
+        AimAnalysis::for_replay(&map, &replay).unwrap()
+    }
+
+    #[test]
+    fn offsets_relative_to_the_movement() {
+        let analysis = analysis();
+        let hits = analysis.hits();
+        assert_eq!(hits.len(), 3);
+        assert!(hits.iter().all(|hit| hit.result() == HitResult::Hit300));
+        assert_eq!(
+            hits.iter()
+                .map(|hit| (hit.object_index(), hit.time()))
+                .collect::<Vec<_>>(),
+            [(0, 1000.0), (1, 1500.0), (2, 2000.0)]
+        );
+        assert_offset(Some(hits[0].offset()), (0.5, 0.0));
+        assert_eq!(hits[0].relative_offset(), None);
+        // Overshooting the jump to the right
+        assert_offset(hits[1].relative_offset(), (0.5, 0.0));
+        assert_eq!(hits[1].jump_distance(), Some(200.0));
+        // Left of the jump upwards
+        assert_offset(hits[2].relative_offset(), (0.0, -0.5));
+        assert_eq!(hits[2].jump_distance(), Some(92.0));
+    }
+
+    #[test]
+    fn biases() {
+        let analysis = analysis();
+        assert_offset(analysis.bias(), (0.25, -0.25));
+        assert_offset(analysis.playfield_bias(), (1.0 / 6.0, 0.0));
+        assert!((analysis.mean_distance().unwrap() - 0.5).abs() < 1e-6);
+
+        let by_distance = analysis.by_jump_distance(100.0);
+        assert_eq!(
+            by_distance
+                .iter()
+                .map(|range| (range.min_distance(), range.max_distance(), range.count()))
+                .collect::<Vec<_>>(),
+            [(0.0, 100.0, 1), (200.0, 300.0, 1)]
+        );
+        assert_offset(Some(by_distance[1].bias()), (0.5, 0.0));
+    }
+
+    #[test]
+    fn aim_of_tiferet() {
+        let (_, replay, map) = test_data::replays_with_maps().remove(0);
+        let analysis = AimAnalysis::for_replay(&map, &replay).unwrap();
+        // Every circle and slider head which was hit, as the slider ends are not clicked
+        assert!(analysis.hits().len() > 800);
+        assert!(analysis.hits().iter().all(|hit| hit.distance() <= 1.0));
+        assert!(analysis.mean_distance().unwrap() < 0.5);
+    }
+
+    #[test]
+    fn other_modes_cannot_be_analysed() {
+        let map = TestMap::new(&["256,192,1000,1,0"]).build();
+        let replay = test_data::replay(GameMode::Taiko, Mods::empty(), &[]);
+        assert!(AimAnalysis::for_replay(&map, &replay).is_err());
+    }
+}
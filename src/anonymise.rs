@@ -0,0 +1,151 @@
+use crate::{
+    cursor::CursorTimeline,
+    osu_data::GameMode,
+    random::LegacyRandom,
+    replay::{Replay, ReplayFrame},
+};
+
+/// Removes what identifies the player from replays, so they can be shared publicly, e.g. in bug reports or datasets.
+///
+/// The player name is replaced, the online score ID is removed and the replay hash is replaced, since it
+/// identifies the score online. The time of day is removed from the time stamp unless a time stamp is given.
+/// The cursor movement can identify players as well, so it can optionally be jittered and resampled
+/// (except in osu!mania, which has no cursor).
+///
+/// The hit counts and score are kept, so jittered or resampled replays may no longer match them exactly.
+/// Use [`Replay::recomputed`] to update them.
+#[derive(Debug, Clone)]
+pub struct Anonymiser {
+    player_name: String,
+    time_stamp: Option<u64>,
+    jitter: Option<f32>,
+    frame_rate: Option<f64>,
+    seed: i32,
+}
+
+impl Default for Anonymiser {
+    fn default() -> Self {
+        Self {
+            player_name: "Anonymous".to_owned(),
+            time_stamp: None,
+            jitter: None,
+            frame_rate: None,
+            seed: 0,
+        }
+    }
+}
+
+impl Anonymiser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The name the player is replaced with, "Anonymous" by default.
+    pub fn with_player_name(mut self, player_name: impl Into<String>) -> Self {
+        self.player_name = player_name.into();
+        self
+    }
+
+    /// The time stamp (in .NET ticks) the replay's time stamp is replaced with.
+    pub fn with_time_stamp(mut self, time_stamp: u64) -> Self {
+        self.time_stamp = Some(time_stamp);
+        self
+    }
+
+    /// Moves every cursor position by a random offset of up to the given distance in osu!pixels along each axis.
+    pub fn with_jitter(mut self, jitter: f32) -> Self {
+        self.jitter = Some(jitter);
+        self
+    }
+
+    /// Resamples the cursor at the given number of frames per second, which removes the frame timing of the player's client.
+    /// Key presses and releases keep their times.
+    pub fn with_resampling(mut self, frame_rate: f64) -> Self {
+        self.frame_rate = Some(frame_rate);
+        self
+    }
+
+    /// The seed of the jitter, so the same replay is always anonymised the same way.
+    pub fn with_seed(mut self, seed: i32) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Anonymises the replay, which fails if the frame rate to resample at is not positive and finite.
+    pub fn anonymise(&self, replay: &Replay) -> Result<Replay, &'static str> {
+        let mut replay = replay.clone();
+        replay.player_name = self.player_name.clone();
+        replay.online_score_id = 0;
+        replay.time_stamp = self
+            .time_stamp
+            .unwrap_or(replay.played_at().start_of_day().ticks());
+
+        // osu!mania stores the held keys in the x coordinate, so there is no cursor to change
+        if !matches!(replay.mode, GameMode::Mania) {
+            if let Some(frame_rate) = self.frame_rate {
+                replay.replay_data = CursorTimeline::new(&replay).resample(frame_rate)?;
+            }
+            if let Some(jitter) = self.jitter {
+                let mut random = LegacyRandom::new(self.seed);
+                let mut offset = || (random.next_f64() as f32 * 2.0 - 1.0) * jitter;
+                for frame in &mut replay.replay_data {
+                    let (x, y) = (frame.x() + offset(), frame.y() + offset());
+                    *frame = ReplayFrame::new(frame.time_delta(), x, y, frame.input_keys());
+                }
+            }
+        }
+
+        replay.update_replay_hash();
+        Ok(replay)
+    }
+
+    /// Reads a replay in the .osr format, anonymises it and encodes it again.
+    pub fn anonymise_bytes<'a>(&self, bytes: &'a [u8]) -> Result<Vec<u8>, &'a str> {
+        let replay = Replay::try_from(bytes)?;
+        Ok(self.anonymise(&replay)?.to_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_data;
+
+    use super::*;
+
+    #[test]
+    fn anonymised_replay_keeps_its_judgements() {
+        let (_, replay, map) = test_data::replays_with_maps().remove(0);
+        let anonymised = Anonymiser::new()
+            .with_resampling(30.0)
+            .anonymise(&replay)
+            .unwrap();
+        assert_eq!(anonymised.player_name, "Anonymous");
+        assert_eq!(anonymised.online_score_id, 0);
+        assert_eq!(
+            anonymised.played_at().to_string(),
+            format!("{} 00:00:00", replay.played_at().format_date())
+        );
+        assert_ne!(anonymised.replay_md5_hash, replay.replay_md5_hash);
+
+        // Key presses keep their times, so coarse resampling only affects how sliders are followed
+        let recomputed = anonymised.recomputed(&map);
+        assert_eq!(recomputed.n_miss, replay.n_miss);
+
+        let counts = |r: &Replay| (r.n_300, r.n_100, r.n_50, r.n_miss, r.max_combo);
+        let fine = Anonymiser::new()
+            .with_resampling(240.0)
+            .anonymise(&replay)
+            .unwrap()
+            .recomputed(&map);
+        assert_eq!(counts(&fine), counts(&replay));
+    }
+
+    #[test]
+    fn invalid_frame_rate_is_an_error() {
+        let (_, replay) = test_data::replays().remove(0);
+        assert!(Anonymiser::new()
+            .with_resampling(0.0)
+            .anonymise(&replay)
+            .is_err());
+    }
+}
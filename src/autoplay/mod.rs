@@ -0,0 +1,560 @@
+use std::f64::consts::PI;
+
+use crate::{
+    health::HealthTimeline,
+    interpolation::interpolate_bezier,
+    map::{
+        catch::BASE_WALK_SPEED,
+        hit_object::{HitObject, HitObjectData},
+        mania::ManiaObject,
+        taiko::{TaikoHitKind, TaikoObject},
+        Beatmap,
+    },
+    osu_data::{GameMode, PLAYFIELD_HEIGHT, PLAYFIELD_WIDTH},
+    replay::{InputKeys, Mods, Replay, ReplayFrame},
+    scoring::Score,
+};
+
+use self::mover::{CursorMover, LinearMover, Movement};
+
+pub mod mover;
+
+/// The number of frames per second if none is given.
+const DEFAULT_FRAME_RATE: f64 = 60.0;
+/// How long keys are held for objects which don't need them to be held, in milliseconds.
+const KEY_UP_DELAY: f64 = 50.0;
+/// The speed at which spinners are spun in radians per millisecond (about 430 rotations per minute).
+const SPIN_RATE: f64 = 0.045;
+/// The distance of the cursor from the centre of the playfield while spinning.
+const SPIN_RADIUS: f32 = 50.0;
+/// Frames during sliders and spinners are at most this many milliseconds apart,
+/// so every slider tick is followed and no rotation is lost between frames.
+const MAX_OBJECT_FRAME_INTERVAL: f64 = 1000.0 / 60.0;
+/// The interval between the points of the generated life bar graph in milliseconds.
+const LIFE_BAR_INTERVAL: f64 = 2000.0;
+/// How far from the end of the previous and the start of the next slider the cursor is looked at
+/// to decide the direction it moves in, in milliseconds.
+const DIRECTION_LOOKAROUND: f64 = 50.0;
+/// The seed written to generated replays, which decides the columns in osu!mania with Random.
+const SEED: i32 = 0;
+
+/// Keys (or osu!mania columns) held from `start` until `end`.
+#[derive(Debug, Clone, Copy)]
+struct KeyPress {
+    start: f64,
+    end: f64,
+    keys: u32,
+}
+
+/// Generates replays which play a beatmap perfectly, like osu!'s Auto mod.
+///
+/// The generated frames are in song time with the mods applied (e.g. flipped for Hard Rock),
+/// just like the frames of a replay recorded in osu!.
+#[derive(Debug, Clone, Copy)]
+pub struct Autoplay {
+    mode: GameMode,
+    mods: Mods,
+    frame_rate: f64,
+}
+
+impl Autoplay {
+    /// An autoplay of the given mode and mods at 60 frames per second.
+    pub fn new(mode: GameMode, mods: Mods) -> Self {
+        Self {
+            mode,
+            mods,
+            frame_rate: DEFAULT_FRAME_RATE,
+        }
+    }
+
+    /// Sets the number of frames per second, which must be positive and finite. Additional frames are added
+    /// wherever keys change and during sliders and spinners, so a low frame rate does not make the play imperfect.
+    /// Frames are at least a millisecond apart, so frame rates above 1000 have the same effect as 1000.
+    pub fn with_frame_rate(mut self, frame_rate: f64) -> Result<Self, &'static str> {
+        if !(frame_rate.is_finite() && frame_rate > 0.0) {
+            return Err("Frame rate must be positive and finite");
+        }
+        self.frame_rate = frame_rate;
+        Ok(self)
+    }
+
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+
+    pub fn mods(&self) -> Mods {
+        self.mods
+    }
+
+    pub fn frame_rate(&self) -> f64 {
+        self.frame_rate
+    }
+
+    /// Generates a replay of the given (unmodified) beatmap.
+    ///
+    /// Hit counts, score, combo and the life bar graph are computed by judging the generated frames.
+    /// The beatmap hash is left empty, since the beatmap does not know the file it was read from.
+    pub fn generate(&self, map: &Beatmap) -> Replay {
+        self.generate_with_mover(map, &mut LinearMover)
+    }
+
+    /// Generates a replay of the given (unmodified) beatmap, where the cursor is moved between objects by the given mover.
+    /// The mover only has an effect in osu!standard.
+    pub fn generate_with_mover(&self, map: &Beatmap, mover: &mut dyn CursorMover) -> Replay {
+        let seed = Some(SEED);
+        let replay_data = match self.mode {
+            GameMode::Standard => self.standard_frames(map, mover),
+            GameMode::Taiko => self.taiko_frames(map),
+            GameMode::CatchTheBeat => self.catch_frames(map),
+            GameMode::Mania => self.mania_frames(map, seed),
+        };
+
+        let mut replay = Replay {
+            mode: self.mode,
+            game_ver: 0,
+            map_md5_hash: String::new(),
+            player_name: "osu!".to_owned(),
+            replay_md5_hash: String::new(),
+            n_300: 0,
+            n_100: 0,
+            n_50: 0,
+            n_geki: 0,
+            n_katu: 0,
+            n_miss: 0,
+            total_score: 0,
+            max_combo: 0,
+            perfect_combo: false,
+            mods: self.mods,
+            life_bar_graph: vec![],
+            time_stamp: 0,
+            compressed_data_length: 0,
+            replay_data,
+            seed,
+            online_score_id: 0,
+            total_hit_accuracy: 0.0,
+        };
+
+        let score = Score::for_replay(map, &replay);
+        let count = |count: usize| u16::try_from(count).unwrap_or(u16::MAX);
+        replay.n_300 = count(score.n_300());
+        replay.n_100 = count(score.n_100());
+        replay.n_50 = count(score.n_50());
+        replay.n_geki = count(score.n_geki());
+        replay.n_katu = count(score.n_katu());
+        replay.n_miss = count(score.n_miss());
+        replay.total_score = u32::try_from(score.total_score()).unwrap_or(u32::MAX);
+        replay.max_combo = count(score.max_combo());
+        replay.perfect_combo = score.perfect_combo();
+        if let Ok(health) = HealthTimeline::for_replay(map, &replay) {
+            let end = map.hit_objects().last().map_or(0.0, |obj| obj.end_time());
+            replay.life_bar_graph = (0..)
+                .map(|i| i as f64 * LIFE_BAR_INTERVAL)
+                .take_while(|&time| time <= end + LIFE_BAR_INTERVAL)
+                .map(|time| (time as i64, health.health_at(time)))
+                .collect();
+        }
+        replay
+    }
+
+    /// Regularly spaced times according to the frame rate, covering the given range.
+    fn frame_times(&self, start: f64, end: f64) -> impl Iterator<Item = f64> {
+        let interval = (1000.0 / self.frame_rate).max(1.0);
+        (0..)
+            .map(move |i| start + i as f64 * interval)
+            .take_while(move |&time| time <= end)
+    }
+
+    /// Follows every object with the cursor and alternates between K1 and K2 for each object.
+    /// Between objects the cursor follows the curves given by the mover.
+    fn standard_frames(&self, map: &Beatmap, mover: &mut dyn CursorMover) -> Vec<ReplayFrame> {
+        let radius = map.difficulty().with_mods(self.mods).circle_radius();
+        let map = map.apply_mods_in_song_time(self.mods);
+        let objects = map.hit_objects();
+        let curves = (1..objects.len())
+            .map(|i| mover.control_points(&movement(objects, radius, i)))
+            .collect::<Vec<_>>();
+
+        let mut presses = objects
+            .iter()
+            .enumerate()
+            .map(|(i, obj)| {
+                // K1 also sets M1 in osu!stable replays, likewise K2 sets M2
+                let keys = if i % 2 == 0 {
+                    InputKeys::K1 | InputKeys::M1
+                } else {
+                    InputKeys::K2 | InputKeys::M2
+                };
+                KeyPress {
+                    start: obj.timestamp() as f64,
+                    end: obj.end_time() + KEY_UP_DELAY,
+                    keys: keys.bits(),
+                }
+            })
+            .collect::<Vec<_>>();
+        release_before_repress(&mut presses);
+
+        let (start, end) = time_range(&presses);
+        let mut times = self.frame_times(start, end).collect::<Vec<_>>();
+        for obj in objects.iter().filter(|obj| !obj.is_circle()) {
+            let (object_start, object_end) = (obj.timestamp() as f64, obj.end_time());
+            let steps = ((object_end - object_start) / MAX_OBJECT_FRAME_INTERVAL).ceil() as usize;
+            times.extend((0..=steps).map(|i| {
+                object_start + (object_end - object_start) * i as f64 / steps.max(1) as f64
+            }));
+        }
+
+        frames(times, &presses, |time, keys| {
+            let (x, y) = cursor_position(objects, &curves, radius, time);
+            (x, y, InputKeys::from_bits_truncate(keys))
+        })
+    }
+
+    /// Hits every note with the matching colour, alternating between left and right.
+    /// Big notes are hit with both keys, drum roll ticks are hit one by one and swells are hit alternating colours.
+    fn taiko_frames(&self, map: &Beatmap) -> Vec<ReplayFrame> {
+        let keys = |kind: TaikoHitKind, right: bool| match (kind, right) {
+            (TaikoHitKind::Don, false) => InputKeys::M1,
+            (TaikoHitKind::Don, true) => InputKeys::K1,
+            (TaikoHitKind::Kat, false) => InputKeys::M2,
+            (TaikoHitKind::Kat, true) => InputKeys::K2,
+        };
+
+        let mut presses = vec![];
+        let mut right = false;
+        let mut press = |time: f64, keys: InputKeys| {
+            presses.push(KeyPress {
+                start: time,
+                end: time + KEY_UP_DELAY,
+                keys: keys.bits(),
+            })
+        };
+        for object in map.taiko_objects() {
+            match object {
+                TaikoObject::Hit { time, kind, big } => {
+                    if big {
+                        press(time, keys(kind, false) | keys(kind, true));
+                    } else {
+                        press(time, keys(kind, right));
+                        right = !right;
+                    }
+                }
+                TaikoObject::DrumRoll { .. } => {
+                    for tick in object.ticks() {
+                        press(tick, keys(TaikoHitKind::Don, right));
+                        right = !right;
+                    }
+                }
+                TaikoObject::Swell {
+                    start,
+                    end,
+                    required_hits,
+                } => {
+                    let spacing = (end - start) / required_hits as f64;
+                    for i in 0..required_hits {
+                        let kind = if i % 2 == 0 {
+                            TaikoHitKind::Don
+                        } else {
+                            TaikoHitKind::Kat
+                        };
+                        press(start + i as f64 * spacing, keys(kind, right));
+                        right = !right;
+                    }
+                }
+            }
+        }
+        release_before_repress(&mut presses);
+
+        let (start, end) = time_range(&presses);
+        let center = (PLAYFIELD_WIDTH / 2.0, PLAYFIELD_HEIGHT / 2.0);
+        frames(
+            self.frame_times(start, end).collect(),
+            &presses,
+            |_, keys| (center.0, center.1, InputKeys::from_bits_truncate(keys)),
+        )
+    }
+
+    /// Moves the catcher in a straight line from object to object, dashing whenever walking would be too slow.
+    fn catch_frames(&self, map: &Beatmap) -> Vec<ReplayFrame> {
+        let targets = map
+            .catch_objects(self.mods)
+            .iter()
+            .map(|obj| (obj.time(), obj.x()))
+            .collect::<Vec<_>>();
+        let (Some(first), Some(last)) = (targets.first(), targets.last()) else {
+            return vec![];
+        };
+
+        let mut times = self
+            .frame_times(0.0f64.min(first.0 - 1000.0), last.0 + 1000.0)
+            .collect::<Vec<_>>();
+        times.extend(targets.iter().map(|(time, _)| *time));
+        frames(times, &[], |time, _| {
+            let next = targets.partition_point(|(target_time, _)| *target_time < time);
+            match (next.checked_sub(1).map(|i| targets[i]), targets.get(next)) {
+                (Some((prev_time, prev_x)), Some(&(next_time, next_x)))
+                    if next_time > prev_time =>
+                {
+                    let lambda = ((time - prev_time) / (next_time - prev_time)) as f32;
+                    let speed = (next_x - prev_x).abs() as f64 / (next_time - prev_time);
+                    let keys = if speed > BASE_WALK_SPEED {
+                        InputKeys::M1
+                    } else {
+                        InputKeys::empty()
+                    };
+                    (prev_x + (next_x - prev_x) * lambda, 0.0, keys)
+                }
+                (_, Some(&(_, x))) | (Some((_, x)), None) => (x, 0.0, InputKeys::empty()),
+                (None, None) => (PLAYFIELD_WIDTH / 2.0, 0.0, InputKeys::empty()),
+            }
+        })
+    }
+
+    /// Presses the column of every note and holds hold notes until their end.
+    /// The pressed columns are stored as a bitmask in the x coordinate.
+    fn mania_frames(&self, map: &Beatmap, seed: Option<i32>) -> Vec<ReplayFrame> {
+        let mut presses = map
+            .mania_objects(self.mods, seed)
+            .iter()
+            .map(|object| KeyPress {
+                start: object.start_time(),
+                end: match object {
+                    ManiaObject::Note { time, .. } => time + KEY_UP_DELAY,
+                    ManiaObject::Hold { end, .. } => *end,
+                },
+                keys: 1 << object.column(),
+            })
+            .collect::<Vec<_>>();
+        release_before_repress(&mut presses);
+
+        let (start, end) = time_range(&presses);
+        frames(
+            self.frame_times(start, end).collect(),
+            &presses,
+            |_, keys| (keys as f32, 0.0, InputKeys::empty()),
+        )
+    }
+}
+
+/// The range of times frames are generated for, starting at 0 or a second before the first press.
+fn time_range(presses: &[KeyPress]) -> (f64, f64) {
+    let first = presses
+        .iter()
+        .map(|p| p.start)
+        .fold(f64::INFINITY, f64::min);
+    let last = presses.iter().map(|p| p.end).fold(0.0, f64::max);
+    if first.is_infinite() {
+        return (0.0, 0.0);
+    }
+    (0.0f64.min(first - 1000.0), last + 1000.0)
+}
+
+/// Shortens presses so every key is let go at least a millisecond before it is pressed again.
+fn release_before_repress(presses: &mut [KeyPress]) {
+    presses.sort_by(|a, b| a.start.total_cmp(&b.start));
+    for i in 0..presses.len() {
+        let next_start = presses[i + 1..]
+            .iter()
+            .find(|next| next.keys & presses[i].keys != 0)
+            .map(|next| next.start);
+        if let Some(next_start) = next_start {
+            let press = &mut presses[i];
+            press.end = press.end.min(next_start - 1.0).max(press.start);
+        }
+    }
+}
+
+/// Builds frames at the given times (rounded to whole milliseconds) as well as whenever a press starts or ends.
+/// `state` gives the position and keys at a time, given the bits of all keys held at that time.
+fn frames(
+    times: Vec<f64>,
+    presses: &[KeyPress],
+    state: impl Fn(f64, u32) -> (f32, f32, InputKeys),
+) -> Vec<ReplayFrame> {
+    let presses = presses
+        .iter()
+        .map(|press| {
+            (
+                press.start.round() as i64,
+                press.end.round() as i64,
+                press.keys,
+            )
+        })
+        .collect::<Vec<_>>();
+    let mut times = times
+        .into_iter()
+        .map(|time| time.round() as i64)
+        .chain(presses.iter().flat_map(|&(start, end, _)| [start, end]))
+        .collect::<Vec<_>>();
+    times.sort_unstable();
+    times.dedup();
+
+    let mut previous = 0;
+    times
+        .into_iter()
+        .map(|time| {
+            let held = presses
+                .iter()
+                .filter(|&&(start, end, _)| start <= time && time < end)
+                .fold(0, |held, &(_, _, keys)| held | keys);
+            let (x, y, keys) = state(time as f64, held);
+            let frame = ReplayFrame::new(time - previous, x, y, keys);
+            previous = time;
+            frame
+        })
+        .collect()
+}
+
+/// The movement from the object before the one with the given index to that object.
+fn movement(objects: &[HitObject], radius: f32, index: usize) -> Movement {
+    let (previous, next) = (&objects[index - 1], &objects[index]);
+    let (start_time, end_time) = (previous.end_time(), next.timestamp() as f64);
+
+    let before = if previous.is_circle() {
+        index
+            .checked_sub(2)
+            .map(|i| position_during(&objects[i], radius, objects[i].end_time()))
+    } else {
+        let time = (start_time - DIRECTION_LOOKAROUND).max(previous.timestamp() as f64);
+        Some(position_during(previous, radius, time))
+    };
+    let after = if next.is_circle() {
+        objects
+            .get(index + 1)
+            .map(|obj| position_during(obj, radius, obj.timestamp() as f64))
+    } else {
+        let time = (end_time + DIRECTION_LOOKAROUND).min(next.end_time());
+        Some(position_during(next, radius, time))
+    };
+
+    Movement::new(
+        position_during(previous, radius, start_time),
+        position_during(next, radius, end_time),
+        before,
+        after,
+    )
+}
+
+/// The position of the cursor at the given time: on the object that is currently active,
+/// otherwise on the curve leading from the end of the previous object to the start of the next one.
+/// `curves` holds the control points of the curve leading to each object but the first.
+fn cursor_position(
+    objects: &[HitObject],
+    curves: &[Vec<(f32, f32)>],
+    radius: f32,
+    time: f64,
+) -> (f32, f32) {
+    let next = objects.partition_point(|obj| obj.timestamp() as f64 <= time);
+    let previous = next.checked_sub(1).map(|i| &objects[i]);
+    if let Some(obj) = previous.filter(|obj| time <= obj.end_time()) {
+        return position_during(obj, radius, time);
+    }
+
+    match (previous, objects.get(next)) {
+        (Some(previous), Some(next_obj)) => {
+            let duration = next_obj.timestamp() as f64 - previous.end_time();
+            let lambda = if duration > 0.0 {
+                ((time - previous.end_time()) / duration) as f32
+            } else {
+                1.0
+            };
+            interpolate_bezier(&curves[next - 1], lambda)
+        }
+        (Some(obj), None) => position_during(obj, radius, obj.end_time()),
+        (None, Some(obj)) => position_during(obj, radius, obj.timestamp() as f64),
+        (None, None) => (PLAYFIELD_WIDTH / 2.0, PLAYFIELD_HEIGHT / 2.0),
+    }
+}
+
+/// Where the cursor has to be while the object is active: on the circle, following the slider ball
+/// or circling around the centre of a spinner.
+fn position_during(obj: &HitObject, radius: f32, time: f64) -> (f32, f32) {
+    let start = obj.timestamp() as f64;
+    let (offset_x, offset_y) = obj.stack_offset(radius);
+    match obj.object_data() {
+        HitObjectData::Circle | HitObjectData::Hold(_) => obj.stacked_position(radius),
+        HitObjectData::Slider(slider) => {
+            let (x, y) = slider.position_at(time.min(obj.end_time()) - start);
+            (x + offset_x, y + offset_y)
+        }
+        HitObjectData::Spinner(_) => {
+            let angle = ((time - start) * SPIN_RATE) % (2.0 * PI);
+            (
+                PLAYFIELD_WIDTH / 2.0 + SPIN_RADIUS * angle.cos() as f32,
+                PLAYFIELD_HEIGHT / 2.0 + SPIN_RADIUS * angle.sin() as f32,
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        judgement::{self, HitResult},
+        test_data::{self, TestMap},
+    };
+
+    use super::*;
+
+    #[test]
+    fn standard_autoplay_is_perfect() {
+        let hand_built = TestMap::new(&[
+            "100,192,1000,1,0",
+            "100,192,1500,2,0,L|380:192,2,280",
+            "256,192,4000,12,0,6000",
+            "380,100,6500,2,0,B|300:50|200:150|100:100,1,300",
+            "380,100,8000,1,0",
+        ])
+        .build();
+        let maps = test_data::replays_with_maps()
+            .into_iter()
+            .map(|(path, _, map)| (path.display().to_string(), map))
+            .chain([("hand-built".to_owned(), hand_built)]);
+
+        for (name, map) in maps {
+            for mods in [Mods::empty(), Mods::HARD_ROCK, Mods::DOUBLE_TIME] {
+                let replay = Autoplay::new(GameMode::Standard, mods).generate(&map);
+                let judgements = judgement::standard::judge(&map, &replay).unwrap();
+                assert!(
+                    judgements.iter().all(|j| j.result() == HitResult::Hit300),
+                    "{name} {mods:?}"
+                );
+                assert_eq!(replay.n_300 as usize, map.hit_objects().len());
+                assert!(replay.perfect_combo, "{name} {mods:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn autoplay_of_other_modes_is_perfect() {
+        for (path, _, map) in test_data::replays_with_maps() {
+            for mode in [GameMode::Taiko, GameMode::CatchTheBeat, GameMode::Mania] {
+                let replay = Autoplay::new(mode, Mods::empty()).generate(&map);
+                assert_eq!(replay.n_miss, 0, "{} {mode:?}", path.display());
+                assert!(replay.perfect_combo, "{} {mode:?}", path.display());
+            }
+        }
+    }
+
+    #[test]
+    fn low_frame_rate_is_still_perfect() {
+        let (_, _, map) = test_data::replays_with_maps().remove(0);
+        let replay = Autoplay::new(GameMode::Standard, Mods::empty())
+            .with_frame_rate(10.0)
+            .unwrap()
+            .generate(&map);
+        assert_eq!(replay.n_300 as usize, map.hit_objects().len());
+        assert!(replay.perfect_combo);
+    }
+
+    #[test]
+    fn frame_rate_must_be_positive_and_finite() {
+        let autoplay = Autoplay::new(GameMode::Standard, Mods::empty());
+        for frame_rate in [0.0, -60.0, f64::NAN, f64::INFINITY] {
+            assert!(
+                autoplay.with_frame_rate(frame_rate).is_err(),
+                "{frame_rate}"
+            );
+        }
+        assert_eq!(autoplay.with_frame_rate(240.0).unwrap().frame_rate(), 240.0);
+    }
+}
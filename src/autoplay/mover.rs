@@ -0,0 +1,223 @@
+/// A movement of the cursor from the end of one object to the start of the next one.
+/// Positions are in osu!pixels.
+#[derive(Debug, Clone, Copy)]
+pub struct Movement {
+    from: (f32, f32),
+    to: (f32, f32),
+    before: Option<(f32, f32)>,
+    after: Option<(f32, f32)>,
+}
+
+impl Movement {
+    pub(crate) fn new(
+        from: (f32, f32),
+        to: (f32, f32),
+        before: Option<(f32, f32)>,
+        after: Option<(f32, f32)>,
+    ) -> Self {
+        Self {
+            from,
+            to,
+            before,
+            after,
+        }
+    }
+
+    /// Where the movement starts, which is the end of the previous object.
+    pub fn from(&self) -> (f32, f32) {
+        self.from
+    }
+
+    /// Where the movement ends, which is the start of the next object.
+    pub fn to(&self) -> (f32, f32) {
+        self.to
+    }
+
+    /// A point the cursor passed shortly before the movement, i.e. on the previous slider or the object before it.
+    /// The cursor arrives at `from` coming from this point.
+    pub fn before(&self) -> Option<(f32, f32)> {
+        self.before
+    }
+
+    /// A point the cursor will pass shortly after the movement, i.e. on the next slider or the object after it.
+    /// The cursor leaves `to` heading towards this point.
+    pub fn after(&self) -> Option<(f32, f32)> {
+        self.after
+    }
+
+    pub fn distance(&self) -> f32 {
+        length(sub(self.to, self.from))
+    }
+}
+
+/// A strategy for moving the cursor between objects in generated replays.
+pub trait CursorMover {
+    /// The control points of the Bézier curve the cursor follows during the movement, evenly in time.
+    /// The first point has to be the start of the movement and the last one its end, otherwise objects are missed.
+    ///
+    /// Movers are asked for the movements of a beatmap in order, so they may carry state from one to the next.
+    fn control_points(&mut self, movement: &Movement) -> Vec<(f32, f32)>;
+}
+
+/// Moves the cursor in a straight line at constant speed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LinearMover;
+
+impl CursorMover for LinearMover {
+    fn control_points(&mut self, movement: &Movement) -> Vec<(f32, f32)> {
+        vec![movement.from(), movement.to()]
+    }
+}
+
+/// Moves the cursor in smooth curves, where every movement continues in the direction the previous one ended in.
+#[derive(Debug, Clone, Copy)]
+pub struct BezierMover {
+    aggressiveness: f32,
+    /// The end of the previous movement and its last control point before the end
+    previous: Option<((f32, f32), (f32, f32))>,
+}
+
+impl BezierMover {
+    /// `aggressiveness` is how far the curves bulge out, relative to the length of the movement.
+    pub fn new(aggressiveness: f32) -> Self {
+        Self {
+            aggressiveness,
+            previous: None,
+        }
+    }
+}
+
+impl Default for BezierMover {
+    fn default() -> Self {
+        Self::new(0.6)
+    }
+}
+
+impl CursorMover for BezierMover {
+    fn control_points(&mut self, movement: &Movement) -> Vec<(f32, f32)> {
+        let (from, to) = (movement.from(), movement.to());
+        let reach = movement.distance() * self.aggressiveness;
+
+        // Mirror the last control point to leave in the direction the cursor arrived in.
+        // After a slider the cursor arrives from the slider body instead.
+        let incoming = match (self.previous, movement.before()) {
+            (Some((end, control)), _) if length(sub(end, from)) < 1.0 => Some(sub(from, control)),
+            (_, Some(before)) => Some(sub(from, before)),
+            _ => None,
+        };
+        let first = add(
+            from,
+            scale(normalize(incoming.unwrap_or(sub(to, from))), reach),
+        );
+        let second = add(to, scale(normalize(sub(first, to)), reach));
+
+        self.previous = Some((to, second));
+        vec![from, first, second, to]
+    }
+}
+
+/// Moves the cursor like it has momentum: it keeps going in the direction it arrived in
+/// and swings into the direction of the following movement before reaching its target.
+#[derive(Debug, Clone, Copy)]
+pub struct MomentumMover {
+    strength: f32,
+}
+
+impl MomentumMover {
+    /// `strength` is how far the cursor carries on in its old direction, relative to the length of the movement.
+    pub fn new(strength: f32) -> Self {
+        Self { strength }
+    }
+}
+
+impl Default for MomentumMover {
+    fn default() -> Self {
+        Self::new(0.5)
+    }
+}
+
+impl CursorMover for MomentumMover {
+    fn control_points(&mut self, movement: &Movement) -> Vec<(f32, f32)> {
+        let (from, to) = (movement.from(), movement.to());
+        let reach = movement.distance() * self.strength;
+        let incoming = movement
+            .before()
+            .map_or(sub(to, from), |before| sub(from, before));
+        let outgoing = movement
+            .after()
+            .map_or(sub(to, from), |after| sub(after, to));
+
+        vec![
+            from,
+            add(from, scale(normalize(incoming), reach)),
+            sub(to, scale(normalize(outgoing), reach)),
+            to,
+        ]
+    }
+}
+
+/// Moves the cursor in arcs which alternate between bending left and right, drawing petals around the objects.
+#[derive(Debug, Clone, Copy)]
+pub struct FlowerMover {
+    petal_size: f32,
+    clockwise: bool,
+}
+
+impl FlowerMover {
+    /// `petal_size` is how far the arcs bend away from the straight line, relative to the length of the movement.
+    pub fn new(petal_size: f32) -> Self {
+        Self {
+            petal_size,
+            clockwise: false,
+        }
+    }
+}
+
+impl Default for FlowerMover {
+    fn default() -> Self {
+        Self::new(2.0 / 3.0)
+    }
+}
+
+impl CursorMover for FlowerMover {
+    fn control_points(&mut self, movement: &Movement) -> Vec<(f32, f32)> {
+        let (from, to) = (movement.from(), movement.to());
+        let direction = sub(to, from);
+        let (x, y) = scale(normalize(direction), movement.distance() * self.petal_size);
+        let bend = if self.clockwise { (y, -x) } else { (-y, x) };
+        self.clockwise = !self.clockwise;
+
+        vec![
+            from,
+            add(add(from, scale(direction, 1.0 / 3.0)), bend),
+            add(sub(to, scale(direction, 1.0 / 3.0)), bend),
+            to,
+        ]
+    }
+}
+
+fn add(a: (f32, f32), b: (f32, f32)) -> (f32, f32) {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+fn sub(a: (f32, f32), b: (f32, f32)) -> (f32, f32) {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn scale(a: (f32, f32), factor: f32) -> (f32, f32) {
+    (a.0 * factor, a.1 * factor)
+}
+
+fn length(a: (f32, f32)) -> f32 {
+    a.0.hypot(a.1)
+}
+
+/// The vector scaled to a length of 1, or the zero vector if it has no direction.
+fn normalize(a: (f32, f32)) -> (f32, f32) {
+    let length = length(a);
+    if length > f32::EPSILON {
+        scale(a, 1.0 / length)
+    } else {
+        (0.0, 0.0)
+    }
+}
@@ -0,0 +1,381 @@
+use crate::{
+    cursor::CursorTimeline,
+    input::{Key, KeyInputs},
+    judgement::{
+        self,
+        standard::{FOLLOW_CIRCLE_SCALE, HITTABLE_RANGE},
+        HitResult, JudgementDetails, ObjectJudgement, SliderPart,
+    },
+    map::{
+        hit_object::{HitObject, HitObjectData},
+        slider_path::distance,
+        Beatmap,
+    },
+    osu_data::GameMode,
+    replay::Replay,
+};
+
+/// How long after the hit window a click on a missed object still counts as a late press of it (in milliseconds).
+const LATE_PRESS_RANGE: f64 = 200.0;
+
+/// Why an object was missed or a combo was broken.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BreakCause {
+    /// The object was clicked before its 50 hit window, by the given hit error in milliseconds.
+    TooEarly { hit_error: f64 },
+    /// The object was clicked after its 50 hit window, by the given hit error in milliseconds.
+    TooLate { hit_error: f64 },
+    /// A key was pressed during the hit window, but the cursor was not on the object.
+    /// The distance of the closest click from the object's centre is relative to the circle radius.
+    CursorOutside { distance: f64 },
+    /// The object was clicked in time, but the click was ignored because the given earlier object could still be hit.
+    NoteLock { blocked_by: usize },
+    /// No key was pressed anywhere near the object.
+    NotClicked,
+    /// A slider tick or repeat was missed because no key was held.
+    SliderReleased { part: SliderPart },
+    /// A slider tick or repeat was missed because the cursor was outside the follow circle.
+    /// The distance from the slider ball is relative to the circle radius.
+    LeftFollowCircle { part: SliderPart, distance: f64 },
+    /// A slider tick or repeat was missed although a key was held and the cursor was in the follow circle,
+    /// because the slider was no longer tracked and the cursor didn't return onto the ball itself.
+    MissedTick { part: SliderPart },
+    /// The spinner was not spun often enough for a 50.
+    SpinnerIncomplete {
+        rotations: f64,
+        required_rotations: f64,
+    },
+}
+
+/// A miss or combo break in an osu!standard play.
+#[derive(Debug, Clone, Copy)]
+pub struct ComboBreak {
+    object_index: usize,
+    time: f64,
+    cause: BreakCause,
+}
+
+impl ComboBreak {
+    /// The index of the object in the beatmap's hit objects.
+    pub fn object_index(&self) -> usize {
+        self.object_index
+    }
+
+    /// The time of the object for misses, of the slider part for slider breaks and of the spinner's end for spinners.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn cause(&self) -> BreakCause {
+        self.cause
+    }
+}
+
+/// Every miss and combo break of an osu!standard replay of the given (unmodified) beatmap together with its cause,
+/// ordered by time.
+///
+/// Combo breaks are missed circles, slider heads, ticks and repeats as well as spinners which were missed.
+/// A missed slider end does not break combo, so it isn't included.
+pub fn combo_breaks(map: &Beatmap, replay: &Replay) -> Result<Vec<ComboBreak>, &'static str> {
+    if !matches!(replay.mode, GameMode::Standard) {
+        return Err("Combo breaks can only be analysed in osu!standard replays");
+    }
+
+    let judgements = judgement::standard::judge(map, replay)?;
+    let difficulty = map.difficulty().for_replay(replay);
+    let map = map.apply_mods_in_song_time(replay.mods);
+    let analysis = Analysis::new(
+        map.hit_objects(),
+        judgements.objects(),
+        replay,
+        difficulty.circle_radius(),
+        difficulty.hit_windows().window_50().floor(),
+    );
+
+    let mut breaks = vec![];
+    for judgement in judgements.iter() {
+        let index = judgement.object_index();
+        let obj = &map.hit_objects()[index];
+        let mut push = |time, cause| {
+            breaks.push(ComboBreak {
+                object_index: index,
+                time,
+                cause,
+            })
+        };
+
+        match judgement.details() {
+            &JudgementDetails::Spinner {
+                rotations,
+                required_rotations,
+            } => {
+                if judgement.result() == HitResult::Miss {
+                    push(
+                        obj.end_time(),
+                        BreakCause::SpinnerIncomplete {
+                            rotations,
+                            required_rotations,
+                        },
+                    );
+                }
+            }
+            JudgementDetails::Circle => {
+                if judgement.result() == HitResult::Miss {
+                    push(
+                        obj.timestamp() as f64,
+                        analysis.head_cause(index, judgement),
+                    );
+                }
+            }
+            JudgementDetails::Slider(parts) => {
+                if parts.first().is_some_and(|head| !head.hit()) {
+                    push(
+                        obj.timestamp() as f64,
+                        analysis.head_cause(index, judgement),
+                    );
+                }
+                for part in parts.iter().filter(|part| !part.hit()) {
+                    if matches!(part.kind(), SliderPart::Tick | SliderPart::Repeat) {
+                        push(
+                            part.time(),
+                            analysis.slider_cause(obj, part.kind(), part.time()),
+                        );
+                    }
+                }
+            }
+        }
+    }
+    breaks.sort_by(|a, b| a.time.total_cmp(&b.time));
+    Ok(breaks)
+}
+
+struct Analysis<'a> {
+    objects: &'a [HitObject],
+    /// The time each circle or slider head was judged at, which for hits is the time of the click
+    head_times: Vec<Option<f64>>,
+    /// The time and cursor position of every press which didn't hit an object
+    unused_clicks: Vec<(f64, (f32, f32))>,
+    timeline: CursorTimeline,
+    radius: f32,
+    window_50: f64,
+}
+
+impl<'a> Analysis<'a> {
+    fn new(
+        objects: &'a [HitObject],
+        judgements: &[ObjectJudgement],
+        replay: &Replay,
+        radius: f32,
+        window_50: f64,
+    ) -> Self {
+        let head_times = judgements
+            .iter()
+            .map(|judgement| match judgement.details() {
+                JudgementDetails::Circle => Some(judgement.time()),
+                JudgementDetails::Slider(parts) => parts.first().map(|head| head.time()),
+                JudgementDetails::Spinner { .. } => None,
+            })
+            .collect::<Vec<_>>();
+        let used_clicks = judgements
+            .iter()
+            .zip(&head_times)
+            .filter(|(judgement, _)| judgement.hit_error().is_some())
+            .filter_map(|(_, &time)| time)
+            .collect::<Vec<_>>();
+
+        let timeline = CursorTimeline::new(replay);
+        let unused_clicks = KeyInputs::new(replay)
+            .presses()
+            .iter()
+            .filter(|press| press.key() != Key::Smoke && !used_clicks.contains(&press.start()))
+            .filter_map(|press| Some((press.start(), timeline.position_at(press.start())?)))
+            .collect();
+
+        Self {
+            objects,
+            head_times,
+            unused_clicks,
+            timeline,
+            radius,
+            window_50,
+        }
+    }
+
+    /// The clicks which didn't hit an object in the given time range.
+    fn clicks_between(&self, start: f64, end: f64) -> &[(f64, (f32, f32))] {
+        let first = self
+            .unused_clicks
+            .partition_point(|&(time, _)| time < start);
+        let last = self.unused_clicks.partition_point(|&(time, _)| time < end);
+        &self.unused_clicks[first..last.max(first)]
+    }
+
+    fn head_cause(&self, index: usize, judgement: &ObjectJudgement) -> BreakCause {
+        if let Some(hit_error) = judgement.hit_error() {
+            return if hit_error < 0.0 {
+                BreakCause::TooEarly { hit_error }
+            } else {
+                BreakCause::TooLate { hit_error }
+            };
+        }
+
+        let obj = &self.objects[index];
+        let time = obj.timestamp() as f64;
+        let position = obj.stacked_position(self.radius);
+        let relative_distance = |click: (f32, f32)| distance(click, position) / self.radius;
+
+        let in_window = self.clicks_between(time - self.window_50, time + self.window_50);
+        for &(click_time, click) in in_window {
+            if relative_distance(click) > 1.0 {
+                continue;
+            }
+            // The earliest object which could still be hit at the time of the click
+            let blocked_by = (0..index).find(|&i| {
+                self.head_times[i].is_some_and(|head_time| head_time > click_time)
+                    && self.objects[i].timestamp() as f64 - HITTABLE_RANGE <= click_time
+            });
+            if let Some(blocked_by) = blocked_by {
+                return BreakCause::NoteLock { blocked_by };
+            }
+        }
+        if let Some(distance) = in_window
+            .iter()
+            .map(|&(_, click)| relative_distance(click) as f64)
+            .min_by(f64::total_cmp)
+        {
+            return BreakCause::CursorOutside { distance };
+        }
+
+        let nearby = self.clicks_between(
+            time - HITTABLE_RANGE,
+            time + self.window_50 + LATE_PRESS_RANGE,
+        );
+        nearby
+            .iter()
+            .filter(|&&(_, click)| relative_distance(click) <= 1.0)
+            .map(|&(click_time, _)| click_time - time)
+            .min_by(|a, b| a.abs().total_cmp(&b.abs()))
+            .map_or(BreakCause::NotClicked, |hit_error| {
+                if hit_error < 0.0 {
+                    BreakCause::TooEarly { hit_error }
+                } else {
+                    BreakCause::TooLate { hit_error }
+                }
+            })
+    }
+
+    fn slider_cause(&self, obj: &HitObject, part: SliderPart, time: f64) -> BreakCause {
+        let keys = self.timeline.keys_at(time);
+        if !keys.left() && !keys.right() {
+            return BreakCause::SliderReleased { part };
+        }
+
+        let HitObjectData::Slider(slider) = obj.object_data() else {
+            return BreakCause::MissedTick { part };
+        };
+        let (x, y) = slider.position_at(time.min(obj.end_time()) - obj.timestamp() as f64);
+        let (offset_x, offset_y) = obj.stack_offset(self.radius);
+        let cursor = self.timeline.position_at(time).unwrap_or_default();
+        let distance = distance(cursor, (x + offset_x, y + offset_y)) / self.radius;
+        if distance > FOLLOW_CIRCLE_SCALE {
+            BreakCause::LeftFollowCircle {
+                part,
+                distance: distance as f64,
+            }
+        } else {
+            BreakCause::MissedTick { part }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        replay::{InputKeys, Mods},
+        test_data::{self, TestMap},
+    };
+
+    use super::*;
+
+    #[test]
+    fn combo_breaks_of_tiferet() {
+        let (_, replay, map) = test_data::replays_with_maps().remove(0);
+        let breaks = combo_breaks(&map, &replay).unwrap();
+        assert!(matches!(
+            breaks[0],
+            ComboBreak {
+                object_index: 528,
+                cause: BreakCause::CursorOutside { distance },
+                ..
+            } if distance > 1.0
+        ));
+    }
+
+    #[test]
+    fn causes() {
+        let map = TestMap::new(&[
+            "100,192,1000,1,0",
+            "400,192,1100,1,0",
+            "256,192,2000,2,0,L|456:192,1,200",
+            "256,192,3500,2,0,L|456:192,1,200",
+            "256,192,5000,12,0,6000",
+            "100,100,7000,1,0",
+            "300,300,8000,1,0",
+        ])
+        .build();
+        let frames = [
+            // Clicks the second circle while the first can still be hit
+            (0, 400.0, 192.0, InputKeys::empty()),
+            (1100, 400.0, 192.0, InputKeys::K1),
+            (1150, 400.0, 192.0, InputKeys::empty()),
+            // Lets go of the first slider before its tick
+            (2000, 256.0, 192.0, InputKeys::K1),
+            (2100, 256.0, 192.0, InputKeys::empty()),
+            // Holds the second slider without following it, then doesn't spin the spinner or click the next circle
+            (3500, 256.0, 192.0, InputKeys::K1),
+            (4300, 256.0, 192.0, InputKeys::empty()),
+            // Clicks the last circle far too early
+            (7700, 300.0, 300.0, InputKeys::K1),
+            (7750, 300.0, 300.0, InputKeys::empty()),
+            (9000, 300.0, 300.0, InputKeys::empty()),
+        ];
+        let replay = test_data::replay(GameMode::Standard, Mods::empty(), &frames);
+        let breaks = combo_breaks(&map, &replay)
+            .unwrap()
+            .iter()
+            .map(|combo_break| {
+                (
+                    combo_break.object_index(),
+                    combo_break.time(),
+                    combo_break.cause(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert!(
+            matches!(
+                breaks[..],
+                [
+                    (0, 1000.0, BreakCause::CursorOutside { distance }),
+                    (1, 1100.0, BreakCause::NoteLock { blocked_by: 0 }),
+                    (2, 2500.0, BreakCause::SliderReleased { part: SliderPart::Tick }),
+                    (3, 4000.0, BreakCause::LeftFollowCircle { part: SliderPart::Tick, distance: follow_distance }),
+                    (4, 6000.0, BreakCause::SpinnerIncomplete { rotations, .. }),
+                    (5, 7000.0, BreakCause::NotClicked),
+                    (6, 8000.0, BreakCause::TooEarly { hit_error }),
+                ] if distance > 8.0
+                    && follow_distance > FOLLOW_CIRCLE_SCALE as f64
+                    && rotations == 0.0
+                    && hit_error == -300.0
+            ),
+            "{breaks:?}"
+        );
+    }
+
+    #[test]
+    fn other_modes_cannot_be_analysed() {
+        let map = TestMap::new(&["256,192,1000,1,0"]).build();
+        let replay = test_data::replay(GameMode::Mania, Mods::empty(), &[]);
+        assert!(combo_breaks(&map, &replay).is_err());
+    }
+}
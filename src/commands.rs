@@ -0,0 +1,724 @@
+use std::{fs, path::Path, str::FromStr};
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::{
+    aim::AimAnalysis,
+    anonymise::Anonymiser,
+    autoplay::{
+        mover::{BezierMover, CursorMover, FlowerMover, LinearMover, MomentumMover},
+        Autoplay,
+    },
+    combo_break::combo_breaks,
+    file_reading,
+    frame_timing::FrameTiming,
+    graph::DifficultyGraph,
+    health::HealthTimeline,
+    hit_error::HitErrors,
+    input::{Key, KeyInputs},
+    judgement::{self, mania::ManiaHitWindows, JudgementDetails},
+    map::{mania::ManiaObject, Beatmap},
+    osu_data::GameMode,
+    performance::{HitCounts, Performance},
+    replay::{Mods, Replay},
+    scoring::Score,
+    signals::SignalReport,
+    similarity::compare_directory,
+    star_rating::DifficultyAttributes,
+    time_stamp::{read_replays_played_between, TimeStamp},
+};
+
+pub const USAGE: &str = "Usage:
+    sabi                                   choose a replay from res/replays and analyse it
+    sabi report <replay>                   analyse a replay
+    sabi judgements <replay>               list the judgement of every object of a replay
+    sabi strains <beatmap> [<mode> <mods>] list the strain of every skill over the course of a beatmap
+    sabi graph <replay> <output> [<width> <height>]
+                                           draw the difficulty of the beatmap with the misses of a replay as a PNG
+    sabi errors <replay>                   show the distribution of the hit errors of a replay
+    sabi aim <replay>                      list where the cursor was on every hit of an osu!standard replay
+    sabi keys <replay>                     list every key press and release of a replay
+    sabi compare <replay> <directory>      compare the cursor of a replay with the replays of the same beatmap in a directory
+    sabi autoplay <beatmap> <output> [<mode> <mods>] [--frame-rate <frame rate>]
+        [--mover linear|bezier|momentum|flower]
+                                           generate a replay which plays the beatmap perfectly
+    sabi anonymise <replay> <output> [--name <name>] [--played-at <time>]
+        [--jitter <osu!pixels>] [--seed <seed>] [--resample <frame rate>]
+                                           remove what identifies the player from a replay
+    sabi list <directory> [<first date> <last date>]
+                                           list the replays in a directory by play date
+
+Modes are given as their number, 0 for osu!standard to 3 for osu!mania, and mods as the sum of their numbers, e.g. 72 for HDDT.
+The beatmaps of replays are looked up by their hash in res/maps.
+Dates are given as 2021-10-20 and times in UTC as 2021-10-20, 2021-10-20 21:37:11 or milliseconds since 1970.";
+
+/// Runs the subcommand given by the command line arguments (without the program name).
+pub fn run(args: &[String]) -> Result<(), String> {
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    match args[..] {
+        ["report", replay] => {
+            let replay = read_replay(replay)?;
+            report(&find_map(&replay)?, &replay);
+            Ok(())
+        }
+        ["judgements", replay] => {
+            let replay = read_replay(replay)?;
+            judgements(&find_map(&replay)?, &replay)
+        }
+        ["strains", map] => strains(&read_map(map)?, GameMode::Standard, Mods::empty()),
+        ["strains", map, mode, mods] => {
+            strains(&read_map(map)?, parse_mode(mode)?, parse_mods(mods)?)
+        }
+        ["graph", replay, output] => graph(&read_replay(replay)?, output, 1200, 400),
+        ["graph", replay, output, width, height] => {
+            graph(&read_replay(replay)?, output, parse(width)?, parse(height)?)
+        }
+        ["errors", replay] => {
+            let replay = read_replay(replay)?;
+            errors(&HitErrors::for_replay(&find_map(&replay)?, &replay));
+            Ok(())
+        }
+        ["aim", replay] => {
+            let replay = read_replay(replay)?;
+            aim(&find_map(&replay)?, &replay)
+        }
+        ["keys", replay] => {
+            keys(&read_replay(replay)?);
+            Ok(())
+        }
+        ["compare", replay, directory] => compare(&read_replay(replay)?, directory),
+        ["autoplay", map, output, ref options @ ..] => autoplay(map, output, options),
+        ["anonymise", replay, output, ref options @ ..] => anonymise(replay, output, options),
+        ["list", directory] => list(directory, NaiveDate::MIN, NaiveDate::MAX),
+        ["list", directory, first, last] => list(directory, parse(first)?, parse(last)?),
+        _ => Err(USAGE.to_owned()),
+    }
+}
+
+/// Prints the analysis of a play of the (unmodified) beatmap.
+pub fn report(map: &Beatmap, replay: &Replay) {
+    println!(
+        "{} played {:?} with {:?} at {}",
+        replay.player_name,
+        replay.mode,
+        replay.mods,
+        replay.played_at()
+    );
+
+    let score = Score::for_replay(map, replay);
+    println!(
+        "\n{} points ({} with ScoreV1, {} with ScoreV2), {:?} with {:.2}% accuracy and a{} combo of {}",
+        score.total_score(),
+        score.score_v1(),
+        score.score_v2(),
+        score.grade(),
+        100.0 * score.accuracy(),
+        if score.perfect_combo() { " perfect" } else { "" },
+        score.max_combo()
+    );
+    println!(
+        "300: {}, 100: {}, 50: {}, geki: {}, katu: {}, miss: {}",
+        score.n_300(),
+        score.n_100(),
+        score.n_50(),
+        score.n_geki(),
+        score.n_katu(),
+        score.n_miss()
+    );
+    // The score is computed slightly differently from osu!stable, so it only has to be close
+    for mismatch in score.validate(replay, 0.005) {
+        println!(
+            "The replay's {} is {}, but {} was computed",
+            mismatch.field(),
+            mismatch.recorded(),
+            mismatch.computed()
+        );
+    }
+
+    if let Ok(health) = HealthTimeline::for_replay(map, replay) {
+        match health.fail() {
+            Some(fail) => println!("\nFailed at {:.0} ms ({:?})", fail.time(), fail.reason()),
+            None => println!("\nPassed"),
+        }
+        let lowest = health.points().iter().min_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some(&(time, lowest)) = lowest {
+            println!(
+                "Health was lowest at {time:.0} ms with {:.0}%, draining {:.1}% per second",
+                100.0 * lowest,
+                100.0 * 1000.0 * health.drain_rate()
+            );
+        }
+        let deviations = health.compare(&replay.life_bar_graph);
+        if let Some(deviation) = deviations.first() {
+            println!(
+                "The life bar deviates from the simulation {} times, first at {} ms with {:.0}% instead of {:.0}%",
+                deviations.len(),
+                deviation.time(),
+                100.0 * deviation.recorded(),
+                100.0 * deviation.simulated()
+            );
+        }
+    }
+
+    let attributes = DifficultyAttributes::calculate(map, replay.mode, replay.mods);
+    print!(
+        "\n{:.2} stars with a max combo of {}",
+        attributes.stars(),
+        attributes.max_combo()
+    );
+    match &attributes {
+        DifficultyAttributes::Standard(difficulty) => println!(
+            " (aim {:.2}, speed {:.2}, flashlight {:.2}), AR {:.2}, OD {:.2}, HP {:.2}",
+            difficulty.aim(),
+            difficulty.speed(),
+            difficulty.flashlight(),
+            difficulty.approach_rate(),
+            difficulty.overall_difficulty(),
+            difficulty.hp_drain_rate()
+        ),
+        DifficultyAttributes::Taiko(difficulty) => println!(
+            " (stamina {:.2}, rhythm {:.2}, colour {:.2})",
+            difficulty.stamina(),
+            difficulty.rhythm(),
+            difficulty.colour()
+        ),
+        DifficultyAttributes::Catch(difficulty) => {
+            println!(", AR {:.2}", difficulty.approach_rate())
+        }
+        DifficultyAttributes::Mania(_) => println!(),
+    }
+    let performance = Performance::for_replay(map, replay);
+    let if_fc = Performance::if_fc(&attributes, HitCounts::from_replay(replay), replay.mods);
+    let if_ss = Performance::if_ss(&attributes, replay.mods);
+    println!(
+        "{:.2} pp ({:.2} pp if FC, {:.2} pp if SS)",
+        performance.pp(),
+        if_fc.pp(),
+        if_ss.pp()
+    );
+    match replay.mode {
+        GameMode::Standard => println!(
+            "Aim {:.2} pp, speed {:.2} pp, accuracy {:.2} pp, flashlight {:.2} pp",
+            performance.aim(),
+            performance.speed(),
+            performance.accuracy(),
+            performance.flashlight()
+        ),
+        GameMode::Taiko => println!(
+            "Difficulty {:.2} pp, accuracy {:.2} pp",
+            performance.difficulty(),
+            performance.accuracy()
+        ),
+        GameMode::CatchTheBeat | GameMode::Mania => {}
+    }
+
+    let hit_errors = HitErrors::for_replay(map, replay);
+    if !hit_errors.is_empty() {
+        println!(
+            "\nUnstable rate {:.2}, hit errors of {:.2} ms on average with a median of {:.2} ms",
+            hit_errors.unstable_rate().unwrap_or_default(),
+            hit_errors.mean().unwrap_or_default(),
+            hit_errors.median().unwrap_or_default()
+        );
+        println!(
+            "{} hits early by {:.2} ms and {} late by {:.2} ms on average",
+            hit_errors.n_early(),
+            hit_errors.mean_early().map_or(0.0, f64::abs),
+            hit_errors.n_late(),
+            hit_errors.mean_late().unwrap_or_default()
+        );
+    }
+
+    if let Ok(aim) = AimAnalysis::for_replay(map, replay) {
+        if let (Some(distance), Some(bias), Some(playfield_bias)) =
+            (aim.mean_distance(), aim.bias(), aim.playfield_bias())
+        {
+            println!(
+                "\nHit {distance:.2} radii from the centre on average, overshooting by {:.2} and {:.2} to the right \
+                 of the movement, {:.2} to the right and {:.2} below the centre",
+                bias.0, bias.1, playfield_bias.0, playfield_bias.1
+            );
+        }
+        for range in aim.by_jump_distance(100.0) {
+            println!(
+                "Jumps of {:.0} to {:.0} px: {} hits {:.2} radii from the centre, overshooting by {:.2}",
+                range.min_distance(),
+                range.max_distance(),
+                range.count(),
+                range.mean_distance(),
+                range.bias().0
+            );
+        }
+    }
+
+    let inputs = KeyInputs::new(replay);
+    let taps = Key::ALL
+        .into_iter()
+        .filter(|&key| inputs.tap_count(key) > 0)
+        .map(|key| {
+            format!(
+                "{key:?} {} times (held for {:.0} ms)",
+                inputs.tap_count(key),
+                inputs.mean_hold_duration(key).unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>();
+    if !taps.is_empty() {
+        println!("\nPressed {}", taps.join(", "));
+    }
+    if let Some(ratio) = inputs.alternation_ratio() {
+        let max_keys_per_second = inputs
+            .keys_per_second(1000.0)
+            .into_iter()
+            .map(|(_, keys)| keys)
+            .fold(0.0, f64::max);
+        println!(
+            "Alternated {:.0}% of the time, at most {max_keys_per_second:.1} keys per second",
+            100.0 * ratio
+        );
+    }
+
+    let timing = FrameTiming::new(replay);
+    if let (Some(interval), Some(frame_rate)) = (timing.frame_interval(), timing.frame_rate()) {
+        println!("\nRecorded every {interval:.1} ms ({frame_rate:.1} frames per second)");
+    }
+    let suspicious = timing.suspicious(0.5).collect::<Vec<_>>();
+    for anomaly in &suspicious {
+        println!(
+            "{:?} from {:.0} to {:.0} ms ({:.0}% certain)",
+            anomaly.kind(),
+            anomaly.start(),
+            anomaly.end(),
+            100.0 * anomaly.confidence()
+        );
+    }
+    if timing.anomalies().len() > suspicious.len() {
+        println!(
+            "{} less certain timing anomalies",
+            timing.anomalies().len() - suspicious.len()
+        );
+    }
+
+    let signals = SignalReport::new(map, replay);
+    if !signals.signals().is_empty() {
+        println!();
+    }
+    for signal in signals.signals() {
+        println!(
+            "{:?}: {:.3} (suspicion {:.0}%)",
+            signal.kind(),
+            signal.value(),
+            100.0 * signal.score()
+        );
+    }
+
+    if let Ok(breaks) = combo_breaks(map, replay) {
+        if !breaks.is_empty() {
+            println!();
+        }
+        for combo_break in breaks {
+            println!(
+                "Combo break on object {} at {:.0} ms: {:?}",
+                combo_break.object_index(),
+                combo_break.time(),
+                combo_break.cause()
+            );
+        }
+    }
+}
+
+/// Prints the judgement of every object, with the details the mode judges it by.
+fn judgements(map: &Beatmap, replay: &Replay) -> Result<(), String> {
+    match replay.mode {
+        GameMode::Standard => {
+            for judgement in judgement::standard::judge(map, replay)?.objects() {
+                print!(
+                    "Object {} at {:.0} ms: {:?}",
+                    judgement.object_index(),
+                    judgement.time(),
+                    judgement.result()
+                );
+                if let Some(hit_error) = judgement.hit_error() {
+                    print!(", hit error {hit_error:.0} ms");
+                }
+                match judgement.details() {
+                    JudgementDetails::Circle => {}
+                    JudgementDetails::Slider(parts) => print!(
+                        ", {} of {} slider parts",
+                        parts.iter().filter(|part| part.hit()).count(),
+                        parts.len()
+                    ),
+                    JudgementDetails::Spinner {
+                        rotations,
+                        required_rotations,
+                    } => print!(", {rotations:.1} of {required_rotations:.1} rotations"),
+                }
+                println!();
+            }
+        }
+        GameMode::Taiko => {
+            let judgements = judgement::taiko::judge(map, replay);
+            for hit in judgements.hits() {
+                print!(
+                    "{}{:?} at {:.0} ms: {:?}",
+                    if hit.big() { "Big " } else { "" },
+                    hit.kind(),
+                    hit.time(),
+                    hit.result()
+                );
+                if let Some(hit_error) = hit.hit_error() {
+                    print!(", hit error {hit_error:.0} ms");
+                }
+                println!("{}", if hit.strong() { ", strong" } else { "" });
+            }
+            for drum_roll in judgements.drum_rolls() {
+                println!(
+                    "{}Drum roll from {:.0} to {:.0} ms: {} of {} ticks",
+                    if drum_roll.big() { "Big " } else { "" },
+                    drum_roll.start(),
+                    drum_roll.end(),
+                    drum_roll.ticks_hit(),
+                    drum_roll.ticks().len()
+                );
+            }
+            for swell in judgements.swells() {
+                println!(
+                    "Swell from {:.0} to {:.0} ms: {} of {} hits{}",
+                    swell.start(),
+                    swell.end(),
+                    swell.hits().len(),
+                    swell.required_hits(),
+                    if swell.completed() { ", completed" } else { "" }
+                );
+            }
+        }
+        GameMode::CatchTheBeat => {
+            let judgements = judgement::catch::judge(map, replay);
+            for judgement in judgements.judgements() {
+                println!(
+                    "{:?} at {:.0} ms and x {:.0}: {} with the catcher at x {:.0}{}{}",
+                    judgement.kind(),
+                    judgement.time(),
+                    judgement.x(),
+                    if judgement.caught() {
+                        "caught"
+                    } else {
+                        "missed"
+                    },
+                    judgement.catcher_x(),
+                    if judgement.dashing() { ", dashing" } else { "" },
+                    if judgement.hyper_dash() {
+                        ", hyperdash"
+                    } else {
+                        ""
+                    }
+                );
+            }
+            println!("{} bananas caught", judgements.bananas_caught());
+        }
+        GameMode::Mania => {
+            let windows = ManiaHitWindows::new(
+                map.difficulty().overall_difficulty(),
+                !map.is_mania_map(),
+                replay.mods,
+            );
+            println!(
+                "Hit windows: MAX {} ms, 300 {} ms, 200 {} ms, 100 {} ms, 50 {} ms, miss {} ms",
+                windows.max(),
+                windows.great(),
+                windows.good(),
+                windows.ok(),
+                windows.meh(),
+                windows.miss()
+            );
+            for judgement in judgement::mania::judge(map, replay).judgements() {
+                match judgement.object() {
+                    ManiaObject::Note { time, column } => {
+                        print!("Note in column {column} at {time:.0} ms")
+                    }
+                    ManiaObject::Hold { start, end, column } => {
+                        print!("Hold note in column {column} from {start:.0} to {end:.0} ms")
+                    }
+                }
+                print!(": {:?}", judgement.result());
+                if let Some(hit_error) = judgement.hit_error() {
+                    print!(", hit error {hit_error:.0} ms");
+                }
+                if let Some(release_error) = judgement.release_error() {
+                    print!(", release error {release_error:.0} ms");
+                }
+                println!();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints the peak strain of every skill in every section as a table, given by the end of the section.
+fn strains(map: &Beatmap, mode: GameMode, mods: Mods) -> Result<(), String> {
+    let strains = DifficultyAttributes::strains(map, mode, mods);
+    let Some(first) = strains.first() else {
+        return Ok(());
+    };
+    println!(
+        "Sections of {:.0} ms from {:.0} ms",
+        first.section_length(),
+        first.start_time()
+    );
+    let skills = strains
+        .iter()
+        .map(|series| format!(" {:>11}", series.skill()))
+        .collect::<String>();
+    println!("   time{skills}");
+    for (time, _) in first.points() {
+        let peaks = strains
+            .iter()
+            .map(|series| format!(" {:>11.2}", series.peak_at(time).unwrap_or_default()))
+            .collect::<String>();
+        println!("{time:>7.0}{peaks}");
+    }
+    Ok(())
+}
+
+fn graph(replay: &Replay, output: &str, width: u32, height: u32) -> Result<(), String> {
+    let graph = DifficultyGraph::for_replay(&find_map(replay)?, replay);
+    fs::write(output, graph.to_png(width, height)?).map_err(|e| e.to_string())?;
+
+    let skills = graph
+        .strains()
+        .iter()
+        .map(|series| series.skill())
+        .collect::<Vec<_>>();
+    let max_density = graph
+        .density()
+        .iter()
+        .map(|&(_, density)| density)
+        .fold(0.0, f64::max);
+    println!(
+        "Drew the strains of {} with up to {max_density:.0} objects per second, misses: {}",
+        skills.join(", "),
+        graph.misses().len()
+    );
+    Ok(())
+}
+
+/// Prints a histogram of the hit errors in 5 ms buckets and the unstable rate of every 30 seconds.
+fn errors(hit_errors: &HitErrors) {
+    let by_error = |a: &&(f64, f64), b: &&(f64, f64)| a.1.total_cmp(&b.1);
+    let earliest = hit_errors.errors().iter().min_by(by_error);
+    let latest = hit_errors.errors().iter().max_by(by_error);
+    if let (Some(earliest), Some(latest)) = (earliest, latest) {
+        println!(
+            "Earliest hit {:.0} ms before the object at {:.0} ms, latest {:.0} ms after the object at {:.0} ms",
+            -earliest.1, earliest.0, latest.1, latest.0
+        );
+    }
+
+    let histogram = hit_errors.histogram(5.0);
+    let max_count = histogram.iter().map(|&(_, count)| count).max().unwrap_or(0);
+    for (error, count) in histogram {
+        let bar = "#".repeat((60 * count).div_ceil(max_count));
+        println!("{error:>5.0} ms {count:>4} {bar}");
+    }
+
+    println!();
+    for (start, std_dev) in hit_errors.section_std_devs(30000.0) {
+        match std_dev {
+            Some(std_dev) => println!("From {start:.0} ms: unstable rate {:.2}", 10.0 * std_dev),
+            None => println!("From {start:.0} ms: no hits"),
+        }
+    }
+}
+
+/// Offsets are relative to the circle radius, see [`AimHit`](crate::aim::AimHit).
+fn aim(map: &Beatmap, replay: &Replay) -> Result<(), String> {
+    for hit in AimAnalysis::for_replay(map, replay)?.hits() {
+        print!(
+            "Object {} clicked at {:.0} ms ({:?}): offset ({:.2}, {:.2})",
+            hit.object_index(),
+            hit.time(),
+            hit.result(),
+            hit.offset().0,
+            hit.offset().1
+        );
+        if let (Some(offset), Some(distance)) = (hit.relative_offset(), hit.jump_distance()) {
+            print!(
+                ", ({:.2}, {:.2}) relative to the jump of {distance:.0} px",
+                offset.0, offset.1
+            );
+        }
+        println!();
+    }
+    Ok(())
+}
+
+fn keys(replay: &Replay) {
+    for event in KeyInputs::new(replay).events() {
+        println!(
+            "{:.0} ms: {:?} {:?}",
+            event.time(),
+            event.kind(),
+            event.key()
+        );
+    }
+}
+
+/// Prints how close the cursor of every other replay in the directory was to the replay's, most similar first,
+/// together with the sections of 10 seconds in which they were especially close.
+fn compare(replay: &Replay, directory: &str) -> Result<(), String> {
+    let similarities = compare_directory(replay, directory).map_err(|e| e.to_string())?;
+    if similarities.is_empty() {
+        println!("No other replays of the beatmap in {directory}");
+    }
+    for (path, similarity) in similarities {
+        let max_distance = similarity
+            .distances()
+            .map(|(_, distance)| distance)
+            .fold(0.0, f64::max);
+        println!(
+            "{}: {:.1} px apart on average and at most {:.1} px, {:.0}% of the time within 10 px, \
+             correlation {:.3}, offset {} ms",
+            path.display(),
+            similarity.mean_distance(),
+            max_distance,
+            100.0 * similarity.fraction_within(10.0),
+            similarity.correlation().unwrap_or_default(),
+            similarity.time_offset()
+        );
+        for section in similarity.sections(10000.0) {
+            if section.mean_distance() < 10.0 {
+                println!(
+                    "    from {:.0} ms: {:.1} px apart on average, correlation {:.3}",
+                    section.start(),
+                    section.mean_distance(),
+                    section.correlation().unwrap_or_default()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The mode and mods are osu!standard without mods unless given, followed by pairs of a flag and its value.
+fn autoplay(map: &str, output: &str, options: &[&str]) -> Result<(), String> {
+    let (mut autoplay, flags) = match options {
+        [mode, mods, flags @ ..] if !mode.starts_with("--") => {
+            (Autoplay::new(parse_mode(mode)?, parse_mods(mods)?), flags)
+        }
+        flags => (Autoplay::new(GameMode::Standard, Mods::empty()), flags),
+    };
+    let mut mover: Option<Box<dyn CursorMover>> = None;
+    for flag in flags.chunks(2) {
+        match *flag {
+            ["--frame-rate", frame_rate] => {
+                autoplay = autoplay.with_frame_rate(parse(frame_rate)?)?
+            }
+            ["--mover", "linear"] => mover = Some(Box::new(LinearMover)),
+            ["--mover", "bezier"] => mover = Some(Box::new(BezierMover::default())),
+            ["--mover", "momentum"] => mover = Some(Box::new(MomentumMover::default())),
+            ["--mover", "flower"] => mover = Some(Box::new(FlowerMover::default())),
+            _ => return Err(USAGE.to_owned()),
+        }
+    }
+
+    let map = fs::read_to_string(map).map_err(|e| e.to_string())?;
+    let beatmap = Beatmap::from_str(&map)?;
+    let mut replay = match &mut mover {
+        Some(mover) => autoplay.generate_with_mover(&beatmap, mover.as_mut()),
+        None => autoplay.generate(&beatmap),
+    };
+    replay.map_md5_hash = file_reading::map_hash(&map);
+    replay.update_replay_hash();
+    fs::write(output, replay.to_bytes()).map_err(|e| e.to_string())?;
+    println!(
+        "Generated a {:?} replay with {:?} at {} frames per second",
+        autoplay.mode(),
+        autoplay.mods(),
+        autoplay.frame_rate()
+    );
+    Ok(())
+}
+
+/// Options are given as pairs of a flag and its value, see [`USAGE`].
+fn anonymise(replay: &str, output: &str, options: &[&str]) -> Result<(), String> {
+    let mut anonymiser = Anonymiser::new();
+    for option in options.chunks(2) {
+        anonymiser = match *option {
+            ["--name", name] => anonymiser.with_player_name(name),
+            ["--played-at", time] => anonymiser.with_time_stamp(parse_time_stamp(time)?.ticks()),
+            ["--jitter", jitter] => anonymiser.with_jitter(parse(jitter)?),
+            ["--seed", seed] => anonymiser.with_seed(parse(seed)?),
+            ["--resample", frame_rate] => anonymiser.with_resampling(parse(frame_rate)?),
+            _ => return Err(USAGE.to_owned()),
+        };
+    }
+    let bytes = fs::read(replay).map_err(|e| e.to_string())?;
+    let anonymised = anonymiser.anonymise_bytes(&bytes)?;
+    fs::write(output, anonymised).map_err(|e| e.to_string())
+}
+
+/// Lists the replays played on or between the dates from the oldest to the newest play, grouped by day.
+fn list(directory: &str, first: NaiveDate, last: NaiveDate) -> Result<(), String> {
+    let replays = read_replays_played_between(directory, first, last).map_err(|e| e.to_string())?;
+    let mut day = None;
+    for (path, replay) in replays {
+        let played_at = replay.played_at();
+        if day != Some(played_at.date()) {
+            day = Some(played_at.date());
+            println!("{}", played_at.format_date());
+        }
+        println!(
+            "    {} ({})  {}",
+            played_at.date_time().format("%H:%M:%S"),
+            played_at.unix_millis(),
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+fn read_replay(path: impl AsRef<Path>) -> Result<Replay, String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    Ok(Replay::try_from(&bytes[..])?)
+}
+
+/// Finds the beatmap of the replay in res/maps by its hash.
+fn find_map(replay: &Replay) -> Result<Beatmap, String> {
+    let maps = file_reading::get_available_maps().map_err(|e| e.to_string())?;
+    let entry = maps.get(&replay.map_md5_hash).ok_or(format!(
+        "Map for this replay is unavailable (MD5 Hash: {})",
+        replay.map_md5_hash
+    ))?;
+    read_map(entry.path())
+}
+
+fn read_map(path: impl AsRef<Path>) -> Result<Beatmap, String> {
+    let map = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    Ok(Beatmap::from_str(&map)?)
+}
+
+fn parse<T: FromStr>(value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("Invalid value {value}"))
+}
+
+fn parse_mode(value: &str) -> Result<GameMode, String> {
+    Ok(GameMode::try_from(parse::<u8>(value)?)?)
+}
+
+fn parse_mods(value: &str) -> Result<Mods, String> {
+    Mods::from_bits(parse(value)?).ok_or(format!("Invalid mods {value}"))
+}
+
+/// Parses a date, a date and time or milliseconds since 1970, all in UTC.
+fn parse_time_stamp(value: &str) -> Result<TimeStamp, String> {
+    let time_stamp = if let Ok(millis) = value.parse() {
+        TimeStamp::from_unix_millis(millis)
+    } else if let Ok(date) = value.parse::<NaiveDate>() {
+        TimeStamp::from_date_time(date.and_time(Default::default()).and_utc())
+    } else {
+        let date_time = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+            .map_err(|_| format!("Invalid time {value}"))?;
+        TimeStamp::from_date_time(date_time.and_utc())
+    };
+    time_stamp.ok_or(format!("{value} is before 0001-01-01"))
+}
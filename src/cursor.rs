@@ -0,0 +1,270 @@
+use crate::{
+    interpolation::interpolate_linear,
+    replay::{InputKeys, Replay, ReplayFrame},
+};
+
+/// The time step (in milliseconds) over which velocity and acceleration are measured, one frame at 60 fps.
+const DERIVATIVE_STEP: f64 = 1000.0 / 60.0;
+
+/// A cursor position and the keys held at a certain time.
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    time: f64,
+    position: (f32, f32),
+    keys: InputKeys,
+}
+
+/// The cursor of a replay over time, so its position can be looked up at any time rather than only at frames.
+///
+/// Positions between two frames are interpolated linearly. Before the first frame the cursor stays
+/// at its first position and after the last frame at its last one. All times are in song time.
+#[derive(Debug, Clone)]
+pub struct CursorTimeline {
+    samples: Vec<Sample>,
+}
+
+impl CursorTimeline {
+    /// Frames which go back in time are left out, and of several frames at the same time only the last one is kept.
+    pub fn new(replay: &Replay) -> Self {
+        let mut samples: Vec<Sample> = vec![];
+        for (time, frame) in replay.timed_frames() {
+            let sample = Sample {
+                time: time as f64,
+                position: frame.position(),
+                keys: frame.input_keys(),
+            };
+            match samples.last_mut() {
+                Some(last) if last.time > sample.time => {}
+                Some(last) if last.time == sample.time => *last = sample,
+                _ => samples.push(sample),
+            }
+        }
+        Self { samples }
+    }
+
+    /// The time of the first frame.
+    pub fn start_time(&self) -> Option<f64> {
+        self.samples.first().map(|sample| sample.time)
+    }
+
+    /// The time of the last frame.
+    pub fn end_time(&self) -> Option<f64> {
+        self.samples.last().map(|sample| sample.time)
+    }
+
+    /// The position of the cursor at the given time, or `None` if the replay has no frames.
+    pub fn position_at(&self, time: f64) -> Option<(f32, f32)> {
+        let next = self.samples.partition_point(|sample| sample.time <= time);
+        let Some(previous) = next.checked_sub(1).map(|previous| &self.samples[previous]) else {
+            return self.samples.first().map(|first| first.position);
+        };
+        let Some(next) = self.samples.get(next) else {
+            return Some(previous.position);
+        };
+        let lambda = (time - previous.time) / (next.time - previous.time);
+        Some(interpolate_linear(previous.position, next.position, lambda))
+    }
+
+    /// The velocity of the cursor at the given time in osu!pixels per millisecond.
+    pub fn velocity_at(&self, time: f64) -> Option<(f64, f64)> {
+        let half_step = DERIVATIVE_STEP / 2.0;
+        let before = self.position_at(time - half_step)?;
+        let after = self.position_at(time + half_step)?;
+        Some((
+            (after.0 - before.0) as f64 / DERIVATIVE_STEP,
+            (after.1 - before.1) as f64 / DERIVATIVE_STEP,
+        ))
+    }
+
+    /// The acceleration of the cursor at the given time in osu!pixels per millisecond squared.
+    pub fn acceleration_at(&self, time: f64) -> Option<(f64, f64)> {
+        let half_step = DERIVATIVE_STEP / 2.0;
+        let before = self.velocity_at(time - half_step)?;
+        let after = self.velocity_at(time + half_step)?;
+        Some((
+            (after.0 - before.0) / DERIVATIVE_STEP,
+            (after.1 - before.1) / DERIVATIVE_STEP,
+        ))
+    }
+
+    /// The keys held at the given time, which are the keys of the last frame at or before it.
+    pub fn keys_at(&self, time: f64) -> InputKeys {
+        let next = self.samples.partition_point(|sample| sample.time <= time);
+        next.checked_sub(1)
+            .map_or(InputKeys::empty(), |previous| self.samples[previous].keys)
+    }
+
+    /// Samples the cursor at a fixed number of frames per second from the first to the last frame,
+    /// giving replay frames which can replace the frames of the replay.
+    ///
+    /// Frames are always kept where keys are pressed or released, so key presses don't change, and only the cursor
+    /// is sampled in between. Frame times are rounded to whole milliseconds, since that is how replays store them,
+    /// so frame rates above 1000 are sampled every millisecond. The frame rate must be positive and finite.
+    pub fn resample(&self, frame_rate: f64) -> Result<Vec<ReplayFrame>, &'static str> {
+        if !(frame_rate.is_finite() && frame_rate > 0.0) {
+            return Err("Frame rate must be positive and finite");
+        }
+        let (Some(start), Some(end)) = (self.start_time(), self.end_time()) else {
+            return Ok(vec![]);
+        };
+        let interval = (1000.0 / frame_rate).max(1.0);
+        let frame_count = ((end - start) / interval).floor() as usize + 1;
+
+        let key_changes = self
+            .samples
+            .windows(2)
+            .filter(|pair| pair[0].keys != pair[1].keys)
+            .map(|pair| pair[1].time);
+        let mut times = (0..frame_count)
+            .map(|i| start + i as f64 * interval)
+            .chain(key_changes)
+            .chain([end])
+            .map(|time| time.round() as i64)
+            .collect::<Vec<_>>();
+        times.sort_unstable();
+        times.dedup();
+
+        let mut previous_time = 0;
+        Ok(times
+            .into_iter()
+            .map(|time| {
+                let (x, y) = self.position_at(time as f64).unwrap_or_default();
+                let frame = ReplayFrame::new(time - previous_time, x, y, self.keys_at(time as f64));
+                previous_time = time;
+                frame
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{input::KeyInputs, osu_data::GameMode, replay::Mods, test_data};
+
+    use super::*;
+
+    /// The replay without the frames which go back in time, which the timeline leaves out as well.
+    fn without_rewinds(replay: &Replay) -> Replay {
+        let mut frames = vec![];
+        let mut last_time = None;
+        for (time, frame) in replay.timed_frames() {
+            if last_time.is_none_or(|last_time| time >= last_time) {
+                let delta = time - last_time.unwrap_or(0);
+                frames.push(ReplayFrame::new(
+                    delta,
+                    frame.x(),
+                    frame.y(),
+                    frame.input_keys(),
+                ));
+                last_time = Some(time);
+            }
+        }
+        let mut replay = replay.clone();
+        replay.replay_data = frames;
+        replay
+    }
+
+    fn presses(replay: &Replay) -> Vec<(f64, f64)> {
+        KeyInputs::new(replay)
+            .presses()
+            .iter()
+            .map(|press| (press.start(), press.duration()))
+            .collect()
+    }
+
+    /// Moves right at one osu!pixel per millisecond from 1000 to 2000 while K1 is held, then stops.
+    fn moving_right() -> CursorTimeline {
+        CursorTimeline::new(&test_data::replay(
+            GameMode::Standard,
+            Mods::empty(),
+            &[
+                (1000, 0.0, 100.0, InputKeys::K1),
+                (1500, 500.0, 100.0, InputKeys::K1),
+                (2000, 1000.0, 100.0, InputKeys::empty()),
+            ],
+        ))
+    }
+
+    #[test]
+    fn positions_are_interpolated() {
+        let timeline = moving_right();
+        assert_eq!(
+            (timeline.start_time(), timeline.end_time()),
+            (Some(1000.0), Some(2000.0))
+        );
+        assert_eq!(timeline.position_at(0.0), Some((0.0, 100.0)));
+        assert_eq!(timeline.position_at(1250.0), Some((250.0, 100.0)));
+        assert_eq!(timeline.position_at(1500.0), Some((500.0, 100.0)));
+        assert_eq!(timeline.position_at(3000.0), Some((1000.0, 100.0)));
+
+        assert_eq!(timeline.keys_at(0.0), InputKeys::empty());
+        assert_eq!(timeline.keys_at(1999.0), InputKeys::K1);
+        assert_eq!(timeline.keys_at(2000.0), InputKeys::empty());
+    }
+
+    #[test]
+    fn velocity_and_acceleration() {
+        let timeline = moving_right();
+        let (vx, vy) = timeline.velocity_at(1250.0).unwrap();
+        assert!((vx - 1.0).abs() < 1e-3 && vy == 0.0, "{vx}, {vy}");
+        let (ax, _) = timeline.acceleration_at(1250.0).unwrap();
+        assert!(ax.abs() < 1e-3, "{ax}");
+
+        // Stopping at the last frame is a deceleration
+        assert_eq!(timeline.velocity_at(2500.0), Some((0.0, 0.0)));
+        let (ax, _) = timeline.acceleration_at(2000.0).unwrap();
+        assert!(ax < 0.0, "{ax}");
+    }
+
+    #[test]
+    fn frames_going_back_in_time_are_left_out() {
+        let timeline = CursorTimeline::new(&test_data::replay(
+            GameMode::Standard,
+            Mods::empty(),
+            &[
+                (1000, 0.0, 0.0, InputKeys::empty()),
+                (2000, 100.0, 0.0, InputKeys::empty()),
+                (1500, 500.0, 500.0, InputKeys::empty()),
+                (2000, 200.0, 0.0, InputKeys::K2),
+            ],
+        ));
+        // The last of the frames at 2000 is kept
+        assert_eq!(timeline.position_at(1500.0), Some((100.0, 0.0)));
+        assert_eq!(timeline.keys_at(2000.0), InputKeys::K2);
+
+        let empty = CursorTimeline::new(&test_data::replay(GameMode::Standard, Mods::empty(), &[]));
+        assert_eq!(empty.position_at(0.0), None);
+        assert_eq!(empty.velocity_at(0.0), None);
+        assert_eq!(empty.resample(60.0), Ok(vec![]));
+    }
+
+    #[test]
+    fn resampling_keeps_key_presses() {
+        for (path, replay) in test_data::replays() {
+            let timeline = CursorTimeline::new(&replay);
+            for frame_rate in [10.0, 60.0, 2000.0] {
+                let mut resampled = replay.clone();
+                resampled.replay_data = timeline.resample(frame_rate).unwrap();
+                assert_eq!(
+                    presses(&resampled),
+                    presses(&without_rewinds(&replay)),
+                    "{}",
+                    path.display()
+                );
+                assert_eq!(
+                    CursorTimeline::new(&resampled).end_time(),
+                    timeline.end_time()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn resampling_requires_valid_frame_rate() {
+        let (_, replay) = test_data::replays().remove(0);
+        let timeline = CursorTimeline::new(&replay);
+        for frame_rate in [0.0, -60.0, f64::NAN, f64::INFINITY] {
+            assert!(timeline.resample(frame_rate).is_err());
+        }
+    }
+}
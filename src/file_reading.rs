@@ -25,13 +25,17 @@ pub fn get_available_maps() -> std::io::Result<HashMap<String, DirEntry>> {
         })
         .map(|entry| {
             (
-                format!(
-                    "{:x}",
-                    md5::compute(std::fs::read_to_string(entry.path()).unwrap())
-                ),
+                map_hash(&std::fs::read_to_string(entry.path()).unwrap()),
                 entry,
             )
         })
         .collect::<HashMap<_, _>>();
     Ok(files)
 }
+
+/// The MD5 hash replays identify the beatmap by. osu! hashes the beatmaps with CRLF line endings,
+/// which a checkout may have converted.
+pub fn map_hash(map: &str) -> String {
+    let crlf = map.replace("\r\n", "\n").replace('\n', "\r\n");
+    format!("{:x}", md5::compute(crlf))
+}
@@ -0,0 +1,345 @@
+use crate::{
+    input::{Key, KeyInputs},
+    replay::Replay,
+};
+
+/// The interval osu! records frames at while the keys don't change, in real time (milliseconds).
+const RECORDING_INTERVAL: f64 = 1000.0 / 60.0;
+/// The first frames are written before the play starts and may jump around in time.
+const LEAD_IN_FRAMES: usize = 3;
+/// The length of the windows the frame interval is measured in, in real time (milliseconds).
+const WINDOW_LENGTH: f64 = 1000.0;
+/// Windows with fewer frames are too short to measure the frame interval in.
+const MIN_WINDOW_FRAMES: usize = 20;
+/// Windows whose clock runs slower than this (relative to real time) are considered timewarped.
+const TIMEWARP_THRESHOLD: f64 = 0.9;
+/// Frames further apart than this (in real time) are considered a gap.
+const FRAME_GAP: f64 = 100.0;
+/// The shortest time (in real time) a human can hold a key for.
+const MIN_HOLD_DURATION: f64 = 10.0;
+/// The shortest time (in real time) in which a human can press the same key twice.
+const MIN_REPEAT_INTERVAL: f64 = 40.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnomalyKind {
+    /// Frames were recorded faster than osu! records them, so the game clock ran at the given rate
+    /// relative to real time, e.g. 0.75 if the game was slowed down by a quarter.
+    Timewarp { rate: f64 },
+    /// No frames were recorded for a long time.
+    FrameGap,
+    /// The key was held for a shorter time than a human can.
+    ShortHold { key: Key },
+    /// The key was pressed again quicker than a human can.
+    FastRepeat { key: Key },
+}
+
+/// A time range (in song time) in which the frames or key presses of a replay look manipulated.
+#[derive(Debug, Clone, Copy)]
+pub struct TimingAnomaly {
+    kind: AnomalyKind,
+    start: f64,
+    end: f64,
+    confidence: f64,
+}
+
+impl TimingAnomaly {
+    pub fn kind(&self) -> AnomalyKind {
+        self.kind
+    }
+
+    pub fn start(&self) -> f64 {
+        self.start
+    }
+
+    pub fn end(&self) -> f64 {
+        self.end
+    }
+
+    /// How certain it is that this isn't a normal play, between 0 and 1.
+    pub fn confidence(&self) -> f64 {
+        self.confidence
+    }
+}
+
+/// The timing of the frames of a replay, which shows the rate osu! recorded it at and sections which look manipulated,
+/// e.g. by slowing the game down (timewarp) or by inputs no human could make.
+///
+/// While no key changes, osu! records a frame about every 1/60th of a second in real time.
+/// A timewarped game clock runs slower than real time, so these frames are closer together in song time than the mods allow.
+/// Frames which change the keys are recorded immediately and are not used to measure the frame interval.
+///
+/// The thresholds are heuristics, so anomalies are meant to be reviewed by a person rather than be taken as proof.
+#[derive(Debug, Clone)]
+pub struct FrameTiming {
+    frame_interval: Option<f64>,
+    anomalies: Vec<TimingAnomaly>,
+}
+
+impl FrameTiming {
+    pub fn new(replay: &Replay) -> Self {
+        let clock_rate = replay.mods.clock_rate();
+
+        // The time and real time delta of every frame which was recorded because time passed, not because a key changed
+        let mut recorded = vec![];
+        let mut anomalies = vec![];
+        let mut previous_keys = None;
+        for (time, frame) in replay.timed_frames().skip(LEAD_IN_FRAMES) {
+            let delta = frame.time_delta() as f64 / clock_rate;
+            if delta > FRAME_GAP {
+                anomalies.push(TimingAnomaly {
+                    kind: AnomalyKind::FrameGap,
+                    start: time as f64 - frame.time_delta() as f64,
+                    end: time as f64,
+                    confidence: 1.0 - FRAME_GAP / delta,
+                });
+            }
+            if delta > 0.0 && previous_keys == Some(frame.input_keys()) {
+                recorded.push((time as f64, delta));
+            }
+            previous_keys = Some(frame.input_keys());
+        }
+
+        let frame_interval = recording_interval(recorded.iter().map(|&(_, delta)| delta));
+        anomalies.extend(timewarps(&recorded, clock_rate));
+        anomalies.extend(impossible_presses(&KeyInputs::new(replay), clock_rate));
+        anomalies.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+        Self {
+            frame_interval,
+            anomalies,
+        }
+    }
+
+    /// The usual time between two recorded frames in real time (milliseconds).
+    pub fn frame_interval(&self) -> Option<f64> {
+        self.frame_interval
+    }
+
+    /// The number of frames recorded per second of real time.
+    pub fn frame_rate(&self) -> Option<f64> {
+        self.frame_interval.map(|interval| 1000.0 / interval)
+    }
+
+    /// Every anomaly, ordered by start time.
+    pub fn anomalies(&self) -> &[TimingAnomaly] {
+        &self.anomalies
+    }
+
+    /// The anomalies which are at least as certain as the given confidence.
+    pub fn suspicious(&self, min_confidence: f64) -> impl Iterator<Item = &TimingAnomaly> + '_ {
+        self.anomalies
+            .iter()
+            .filter(move |anomaly| anomaly.confidence >= min_confidence)
+    }
+}
+
+/// Measures the frame interval in windows of one second (in real time) and merges consecutive windows in which the clock ran too slowly.
+fn timewarps(recorded: &[(f64, f64)], clock_rate: f64) -> Vec<TimingAnomaly> {
+    let window = WINDOW_LENGTH * clock_rate;
+    let mut windows: Vec<Vec<f64>> = vec![];
+    for &(time, delta) in recorded {
+        let index = (time / window).floor().max(0.0) as usize;
+        if index >= windows.len() {
+            windows.resize(index + 1, vec![]);
+        }
+        windows[index].push(delta);
+    }
+
+    let mut timewarps: Vec<(TimingAnomaly, usize)> = vec![];
+    for (i, deltas) in windows.into_iter().enumerate() {
+        if deltas.len() < MIN_WINDOW_FRAMES {
+            continue;
+        }
+        let Some(rate) = recording_interval(deltas).map(|interval| interval / RECORDING_INTERVAL)
+        else {
+            continue;
+        };
+        if rate >= TIMEWARP_THRESHOLD {
+            continue;
+        }
+
+        let (start, end) = (i as f64 * window, (i + 1) as f64 * window);
+        let confidence = ((0.95 - rate) / 0.2).clamp(0.0, 1.0);
+        match timewarps.last_mut() {
+            Some((anomaly, count)) if anomaly.end == start => {
+                let AnomalyKind::Timewarp { rate: mean_rate } = &mut anomaly.kind else {
+                    unreachable!("Only timewarps are collected here");
+                };
+                *mean_rate = (*mean_rate * *count as f64 + rate) / (*count + 1) as f64;
+                anomaly.end = end;
+                anomaly.confidence = anomaly.confidence.max(confidence);
+                *count += 1;
+            }
+            _ => timewarps.push((
+                TimingAnomaly {
+                    kind: AnomalyKind::Timewarp { rate },
+                    start,
+                    end,
+                    confidence,
+                },
+                1,
+            )),
+        }
+    }
+    timewarps.into_iter().map(|(anomaly, _)| anomaly).collect()
+}
+
+/// Finds key presses which were held too briefly or followed too quickly by another press of the same key.
+fn impossible_presses(inputs: &KeyInputs, clock_rate: f64) -> Vec<TimingAnomaly> {
+    let mut anomalies = vec![];
+    for key in Key::ALL {
+        let presses = inputs.presses_of(key).collect::<Vec<_>>();
+        for press in &presses {
+            let duration = press.duration() / clock_rate;
+            if duration < MIN_HOLD_DURATION {
+                anomalies.push(TimingAnomaly {
+                    kind: AnomalyKind::ShortHold { key },
+                    start: press.start(),
+                    end: press.end(),
+                    confidence: 1.0 - duration / MIN_HOLD_DURATION,
+                });
+            }
+        }
+        for pair in presses.windows(2) {
+            let interval = (pair[1].start() - pair[0].start()) / clock_rate;
+            if interval < MIN_REPEAT_INTERVAL {
+                anomalies.push(TimingAnomaly {
+                    kind: AnomalyKind::FastRepeat { key },
+                    start: pair[0].start(),
+                    end: pair[1].start(),
+                    confidence: 1.0 - interval / MIN_REPEAT_INTERVAL,
+                });
+            }
+        }
+    }
+    anomalies
+}
+
+/// The upper quartile of the frame deltas. Frames which are recorded early, e.g. because the game
+/// ran at a higher frame rate, only make deltas shorter, so the interval osu! recorded at is near the upper end.
+fn recording_interval(deltas: impl IntoIterator<Item = f64>) -> Option<f64> {
+    let mut deltas = deltas.into_iter().collect::<Vec<_>>();
+    deltas.sort_by(f64::total_cmp);
+    deltas.get(deltas.len() * 3 / 4).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        osu_data::GameMode,
+        replay::{InputKeys, Mods},
+        test_data,
+    };
+
+    use super::*;
+
+    /// A replay with a frame every given number of milliseconds (in song time) until the end, without any keys.
+    fn recorded_every(interval: i64, end: i64, mods: Mods) -> Replay {
+        let frames = (0..=end / interval)
+            .map(|i| (i * interval, 256.0, 192.0, InputKeys::empty()))
+            .collect::<Vec<_>>();
+        test_data::replay(GameMode::Standard, mods, &frames)
+    }
+
+    #[test]
+    fn frame_rate_of_tiferet() {
+        let (_, replay, _) = test_data::replays_with_maps().remove(0);
+        let timing = FrameTiming::new(&replay);
+        assert!((timing.frame_rate().unwrap() - 58.8).abs() < 0.1);
+        assert!(timing.anomalies().is_empty(), "{:?}", timing.anomalies());
+    }
+
+    #[test]
+    fn frames_in_real_time() {
+        for (interval, mods) in [(17, Mods::empty()), (24, Mods::DOUBLE_TIME)] {
+            let timing = FrameTiming::new(&recorded_every(interval, 10000, mods));
+            let real_interval = interval as f64 / mods.clock_rate();
+            assert!((timing.frame_interval().unwrap() - real_interval).abs() < 1e-9);
+            assert!(timing.anomalies().is_empty(), "{mods:?}");
+        }
+    }
+
+    #[test]
+    fn slowed_down_clock_is_a_timewarp() {
+        // 60 fps in real time while the game runs at 72% speed
+        let timing = FrameTiming::new(&recorded_every(12, 10000, Mods::empty()));
+        let [anomaly] = timing.anomalies() else {
+            panic!("{:?}", timing.anomalies());
+        };
+        let AnomalyKind::Timewarp { rate } = anomaly.kind() else {
+            panic!("{anomaly:?}");
+        };
+        assert!((rate - 0.72).abs() < 0.01, "{rate}");
+        assert_eq!((anomaly.start(), anomaly.end()), (0.0, 10000.0));
+        assert_eq!(anomaly.confidence(), 1.0);
+    }
+
+    #[test]
+    fn gaps_between_frames() {
+        let mut frames = (0..100)
+            .map(|i| (i * 17, 256.0, 192.0, InputKeys::empty()))
+            .collect::<Vec<_>>();
+        frames.push((1683 + 400, 256.0, 192.0, InputKeys::empty()));
+        let timing = FrameTiming::new(&test_data::replay(
+            GameMode::Standard,
+            Mods::empty(),
+            &frames,
+        ));
+        let [anomaly] = timing.anomalies() else {
+            panic!("{:?}", timing.anomalies());
+        };
+        assert_eq!(anomaly.kind(), AnomalyKind::FrameGap);
+        assert_eq!((anomaly.start(), anomaly.end()), (1683.0, 2083.0));
+        assert_eq!(anomaly.confidence(), 0.75);
+    }
+
+    #[test]
+    fn inhuman_key_presses() {
+        let frames = test_data::key_frames(
+            (256.0, 192.0),
+            &[
+                (1000, 1005, InputKeys::K1),
+                (1020, 1100, InputKeys::K1),
+                (1200, 1300, InputKeys::K2),
+            ],
+        );
+        let timing = FrameTiming::new(&test_data::replay(
+            GameMode::Standard,
+            Mods::empty(),
+            &frames,
+        ));
+        let anomalies = timing
+            .anomalies()
+            .iter()
+            .map(|anomaly| {
+                (
+                    anomaly.kind(),
+                    anomaly.start(),
+                    anomaly.end(),
+                    anomaly.confidence(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            anomalies,
+            [
+                (AnomalyKind::ShortHold { key: Key::K1 }, 1000.0, 1005.0, 0.5),
+                (
+                    AnomalyKind::FastRepeat { key: Key::K1 },
+                    1000.0,
+                    1020.0,
+                    0.5
+                ),
+            ]
+        );
+        assert_eq!(timing.suspicious(0.6).count(), 0);
+
+        // Presses are measured in real time, so the same presses are even shorter with Double Time
+        let timing = FrameTiming::new(&test_data::replay(
+            GameMode::Standard,
+            Mods::DOUBLE_TIME,
+            &frames,
+        ));
+        assert_eq!(timing.suspicious(0.6).count(), 2);
+    }
+}
@@ -0,0 +1,347 @@
+use png::{BitDepth, ColorType, Encoder};
+
+use crate::{
+    judgement::{self, mania::ManiaHitResult, HitResult},
+    map::Beatmap,
+    osu_data::GameMode,
+    replay::{Mods, Replay},
+    star_rating::{DifficultyAttributes, StrainSeries},
+};
+
+/// The length of the windows the notes per second are counted in, in real time (milliseconds).
+const DENSITY_WINDOW: f64 = 1000.0;
+
+const BACKGROUND: [u8; 3] = [30, 30, 36];
+const GRID: [u8; 3] = [60, 60, 70];
+const DENSITY: [u8; 3] = [70, 90, 80];
+const MISS: [u8; 3] = [220, 60, 60];
+/// The colours of the strain lines, in the order of the skills
+const STRAIN_COLOURS: [[u8; 3]; 3] = [[90, 160, 230], [240, 150, 60], [230, 210, 90]];
+
+/// The difficulty of a beatmap over time: the strain of every skill and the number of notes per second,
+/// together with the times of the misses of a play, so spikes in difficulty can be lined up with misses.
+/// All times are in song time.
+#[derive(Debug, Clone)]
+pub struct DifficultyGraph {
+    strains: Vec<StrainSeries>,
+    density: Vec<(f64, f64)>,
+    misses: Vec<f64>,
+}
+
+impl DifficultyGraph {
+    /// The graph of the (unmodified) beatmap played in the given mode with the given mods, without any misses.
+    pub fn new(map: &Beatmap, mode: GameMode, mods: Mods) -> Self {
+        Self {
+            strains: DifficultyAttributes::strains(map, mode, mods),
+            density: note_density(map, mods),
+            misses: vec![],
+        }
+    }
+
+    /// The graph of the (unmodified) beatmap as played in the replay, with the misses of the play.
+    pub fn for_replay(map: &Beatmap, replay: &Replay) -> Self {
+        Self {
+            misses: miss_times(map, replay),
+            ..Self::new(map, replay.mode, replay.mods)
+        }
+    }
+
+    /// The strain of every skill of the mode.
+    pub fn strains(&self) -> &[StrainSeries] {
+        &self.strains
+    }
+
+    /// The end time of every one second window (in real time) together with the number of objects starting in it.
+    pub fn density(&self) -> &[(f64, f64)] {
+        &self.density
+    }
+
+    /// The times of the missed objects, ordered by time.
+    pub fn misses(&self) -> &[f64] {
+        &self.misses
+    }
+
+    /// Renders the graph as a PNG image of the given size.
+    ///
+    /// The note density is drawn as a filled area in the background, the strains as lines on top of it
+    /// and misses as red vertical lines. The strains share a scale, the density has its own.
+    pub fn to_png(&self, width: u32, height: u32) -> Result<Vec<u8>, &'static str> {
+        if width == 0 || height == 0 {
+            return Err("Graph dimensions must be positive");
+        }
+
+        let end_time = self
+            .strains
+            .iter()
+            .flat_map(|series| series.points().last())
+            .chain(self.density.last().copied())
+            .map(|(time, _)| time)
+            .chain(self.misses.last().copied())
+            .fold(0.0, f64::max);
+        let max_strain = self
+            .strains
+            .iter()
+            .flat_map(|series| series.peaks().iter().copied())
+            .fold(0.0, f64::max);
+        let max_density = self
+            .density
+            .iter()
+            .map(|&(_, notes)| notes)
+            .fold(0.0, f64::max);
+
+        let mut canvas = Canvas::new(width, height, end_time);
+        for i in 1..4 {
+            let y = canvas.height as f64 * i as f64 / 4.0;
+            canvas.horizontal_line(y, GRID);
+        }
+        let mut window_start = 0.0;
+        for &(window_end, notes) in &self.density {
+            canvas.fill_below(window_start, window_end, notes / max_density, DENSITY);
+            window_start = window_end;
+        }
+        for (i, series) in self.strains.iter().enumerate() {
+            let colour = STRAIN_COLOURS[i % STRAIN_COLOURS.len()];
+            let points = series
+                .points()
+                .map(|(time, peak)| (time, peak / max_strain))
+                .collect::<Vec<_>>();
+            for pair in points.windows(2) {
+                canvas.line(pair[0], pair[1], colour);
+            }
+        }
+        for &time in &self.misses {
+            canvas.vertical_line(time, MISS);
+        }
+        canvas.to_png()
+    }
+}
+
+/// The number of objects starting in every one second window (in real time) of the beatmap played with the given mods,
+/// given by the end of each window in song time.
+pub fn note_density(map: &Beatmap, mods: Mods) -> Vec<(f64, f64)> {
+    let window = DENSITY_WINDOW * map.difficulty().with_mods(mods).clock_rate();
+    let mut density = vec![];
+    for obj in map.hit_objects() {
+        let index = (obj.timestamp() as f64 / window) as usize;
+        if index >= density.len() {
+            density.resize(index + 1, 0.0);
+        }
+        density[index] += 1.0;
+    }
+    density
+        .into_iter()
+        .enumerate()
+        .map(|(i, notes)| ((i + 1) as f64 * window, notes))
+        .collect()
+}
+
+/// The times of the objects missed in the replay, ordered by time. Objects which can't be missed,
+/// like the ticks of sliders, tiny droplets or osu!taiko drum rolls, are not included.
+pub fn miss_times(map: &Beatmap, replay: &Replay) -> Vec<f64> {
+    let mut misses = match replay.mode {
+        GameMode::Standard => judgement::standard::judge(map, replay)
+            .expect("The replay is an osu!standard replay")
+            .iter()
+            .filter(|judgement| judgement.result() == HitResult::Miss)
+            .map(|judgement| map.hit_objects()[judgement.object_index()].timestamp() as f64)
+            .collect::<Vec<_>>(),
+        GameMode::Taiko => judgement::taiko::judge(map, replay)
+            .hits()
+            .iter()
+            .filter(|hit| hit.result() == HitResult::Miss)
+            .map(|hit| hit.time())
+            .collect(),
+        GameMode::CatchTheBeat => judgement::catch::judge(map, replay)
+            .judgements()
+            .iter()
+            .filter(|judgement| judgement.kind().affects_combo() && !judgement.caught())
+            .map(|judgement| judgement.time())
+            .collect(),
+        GameMode::Mania => judgement::mania::judge(map, replay)
+            .judgements()
+            .iter()
+            .filter(|judgement| judgement.result() == ManiaHitResult::Miss)
+            .map(|judgement| judgement.object().start_time())
+            .collect(),
+    };
+    misses.sort_by(f64::total_cmp);
+    misses
+}
+
+/// An RGB image the graph is drawn on, whose x axis goes from 0 to the given end time.
+struct Canvas {
+    width: u32,
+    height: u32,
+    end_time: f64,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32, end_time: f64) -> Self {
+        Self {
+            width,
+            height,
+            end_time,
+            pixels: BACKGROUND.repeat((width * height) as usize),
+        }
+    }
+
+    fn x_of(&self, time: f64) -> f64 {
+        if self.end_time > 0.0 {
+            time / self.end_time * (self.width - 1) as f64
+        } else {
+            0.0
+        }
+    }
+
+    /// The y coordinate of a value between 0 (bottom) and 1 (top), leaving some space above the highest value.
+    fn y_of(&self, value: f64) -> f64 {
+        let value = if value.is_finite() { value } else { 0.0 };
+        (self.height - 1) as f64 * (1.0 - 0.95 * value)
+    }
+
+    fn set(&mut self, x: i64, y: i64, colour: [u8; 3]) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+        let index = 3 * (y as usize * self.width as usize + x as usize);
+        self.pixels[index..index + 3].copy_from_slice(&colour);
+    }
+
+    fn horizontal_line(&mut self, y: f64, colour: [u8; 3]) {
+        for x in 0..self.width as i64 {
+            self.set(x, y as i64, colour);
+        }
+    }
+
+    fn vertical_line(&mut self, time: f64, colour: [u8; 3]) {
+        let x = self.x_of(time).round() as i64;
+        for y in 0..self.height as i64 {
+            self.set(x, y, colour);
+        }
+    }
+
+    /// Fills the area between the start and end time from the bottom up to the given value.
+    fn fill_below(&mut self, start_time: f64, end_time: f64, value: f64, colour: [u8; 3]) {
+        let top = self.y_of(value).round() as i64;
+        let (start, end) = (self.x_of(start_time).round(), self.x_of(end_time).round());
+        for x in start as i64..end as i64 {
+            for y in top..self.height as i64 {
+                self.set(x, y, colour);
+            }
+        }
+    }
+
+    /// Draws a line two pixels thick between two points given as time and value.
+    fn line(&mut self, (from_time, from): (f64, f64), (to_time, to): (f64, f64), colour: [u8; 3]) {
+        let (x0, y0) = (self.x_of(from_time), self.y_of(from));
+        let (x1, y1) = (self.x_of(to_time), self.y_of(to));
+        let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1.0) as usize;
+        for step in 0..=steps {
+            let t = step as f64 / steps as f64;
+            let x = (x0 + (x1 - x0) * t).round() as i64;
+            let y = (y0 + (y1 - y0) * t).round() as i64;
+            self.set(x, y, colour);
+            self.set(x, y + 1, colour);
+        }
+    }
+
+    fn to_png(&self) -> Result<Vec<u8>, &'static str> {
+        let mut bytes = vec![];
+        let mut encoder = Encoder::new(&mut bytes, self.width, self.height);
+        encoder.set_color(ColorType::Rgb);
+        encoder.set_depth(BitDepth::Eight);
+        let mut writer = encoder
+            .write_header()
+            .map_err(|_| "Error writing PNG header")?;
+        writer
+            .write_image_data(&self.pixels)
+            .map_err(|_| "Error writing PNG image data")?;
+        writer.finish().map_err(|_| "Error finishing PNG image")?;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        autoplay::Autoplay,
+        test_data::{self, TestMap},
+    };
+
+    use super::*;
+
+    const MODES: [GameMode; 4] = [
+        GameMode::Standard,
+        GameMode::Taiko,
+        GameMode::CatchTheBeat,
+        GameMode::Mania,
+    ];
+
+    #[test]
+    fn strains_cover_the_beatmap() {
+        let map = test_data::map_named("Another");
+        let end = map.hit_objects().last().unwrap().end_time();
+        for mode in MODES {
+            let graph = DifficultyGraph::new(&map, mode, Mods::empty());
+            assert!(!graph.strains().is_empty());
+            for series in graph.strains() {
+                let (last_time, _) = series.points().last().unwrap();
+                assert!(
+                    (last_time - end).abs() <= series.section_length(),
+                    "{mode:?} {}",
+                    series.skill()
+                );
+                assert!(series.peaks().iter().all(|&peak| peak >= 0.0));
+            }
+        }
+    }
+
+    #[test]
+    fn note_density_counts_objects_per_second() {
+        let map = TestMap::new(&[
+            "256,192,100,1,0",
+            "256,192,400,1,0",
+            "256,192,900,1,0",
+            "256,192,2500,1,0",
+        ])
+        .build();
+        assert_eq!(
+            note_density(&map, Mods::empty()),
+            [(1000.0, 3.0), (2000.0, 0.0), (3000.0, 1.0)]
+        );
+        // A second of real time is one and a half seconds of song time with Double Time
+        assert_eq!(
+            note_density(&map, Mods::DOUBLE_TIME),
+            [(1500.0, 3.0), (3000.0, 1.0)]
+        );
+    }
+
+    #[test]
+    fn misses_of_tiferet() {
+        let (_, replay, map) = test_data::replays_with_maps().remove(0);
+        let graph = DifficultyGraph::for_replay(&map, &replay);
+        assert_eq!(graph.misses().len(), replay.n_miss as usize);
+    }
+
+    #[test]
+    fn autoplay_has_no_misses() {
+        let map = test_data::map_named("Insane");
+        for mode in MODES {
+            let replay = Autoplay::new(mode, Mods::empty()).generate(&map);
+            assert_eq!(miss_times(&map, &replay), [] as [f64; 0], "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn png_has_the_given_size() {
+        let (_, replay, map) = test_data::replays_with_maps().remove(0);
+        let graph = DifficultyGraph::for_replay(&map, &replay);
+        let png = graph.to_png(320, 120).unwrap();
+        let reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+        assert_eq!((reader.info().width, reader.info().height), (320, 120));
+
+        assert!(graph.to_png(0, 120).is_err());
+        assert!(graph.to_png(320, 0).is_err());
+    }
+}
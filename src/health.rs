@@ -0,0 +1,486 @@
+use crate::{
+    judgement::{
+        standard, HitResult, JudgementDetails, JudgementEvent, Judgements, SectionBonus,
+        SliderPart, SliderPartJudgement,
+    },
+    map::{break_period::BreakPeriod, difficulty::difficulty_range, Beatmap},
+    replay::{Mods, Replay},
+};
+
+/// Health gained for a 300, 100 and 50 respectively.
+const HEALTH_300: f64 = 0.03;
+const HEALTH_100: f64 = 0.011;
+const HEALTH_50: f64 = 0.002;
+/// Health gained for the last object of a combo section instead of its usual amount.
+const HEALTH_GEKI: f64 = 0.07;
+const HEALTH_KATU: f64 = 0.05;
+/// Health gained for the head, repeats and end of a slider.
+const HEALTH_SLIDER_PART: f64 = 0.02;
+const HEALTH_SLIDER_TICK: f64 = 0.015;
+/// Health gained for every spinner rotation, and additionally for every rotation past the required amount.
+const HEALTH_SPIN: f64 = 0.0085;
+const HEALTH_BONUS_SPIN: f64 = 0.01;
+
+/// The initial guess for the drain rate in health per millisecond.
+const INITIAL_DRAIN_RATE: f64 = 0.00025;
+/// Recorded health values which differ from the simulation by more than this are reported.
+const LIFE_BAR_TOLERANCE: f64 = 0.1;
+
+/// Why a play was failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailReason {
+    /// The health reached zero without No Fail
+    HealthDepleted,
+    /// The combo broke with Sudden Death
+    SuddenDeath,
+    /// Something other than a 300 was hit with Perfect
+    Perfect,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Fail {
+    time: f64,
+    reason: FailReason,
+}
+
+impl Fail {
+    /// The time of the fail in milliseconds.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn reason(&self) -> FailReason {
+        self.reason
+    }
+}
+
+/// A point of a recorded life bar which deviates from the simulated health.
+#[derive(Debug, Clone, Copy)]
+pub struct HealthDeviation {
+    time: i64,
+    recorded: f64,
+    simulated: f64,
+}
+
+impl HealthDeviation {
+    pub fn time(&self) -> i64 {
+        self.time
+    }
+
+    pub fn recorded(&self) -> f64 {
+        self.recorded
+    }
+
+    pub fn simulated(&self) -> f64 {
+        self.simulated
+    }
+}
+
+/// The health over the course of a play, between 0 and 1.
+#[derive(Debug, Clone, Default)]
+pub struct HealthTimeline {
+    points: Vec<(f64, f64)>,
+    drain_rate: f64,
+    fail: Option<Fail>,
+}
+
+impl HealthTimeline {
+    /// Simulates the health of an osu!standard play from its judgements.
+    ///
+    /// Health drains linearly from the first object to the end of the last one, except during breaks and spinners.
+    /// The drain rate and a multiplier for health gains are derived like osu!stable does,
+    /// so that a perfect play never drops too low.
+    /// The extra lives of Easy are not taken into account.
+    pub fn simulate(map: &Beatmap, judgements: &Judgements, mods: Mods) -> Self {
+        let hp = map.difficulty().with_mods(mods).hp_drain_rate() as f64;
+        let objects = map.hit_objects();
+        let breaks = map.break_periods();
+        let (drain_rate, multiplier) = drain_rate(map, mods, hp);
+        let bonuses = judgements.section_bonuses(objects);
+
+        let mut timeline = Self {
+            points: vec![],
+            drain_rate,
+            fail: None,
+        };
+        let (drain_start, drain_end) = match (objects.first(), objects.last()) {
+            (Some(first), Some(last)) => (first.timestamp() as f64, last.end_time()),
+            _ => return timeline,
+        };
+
+        let mut health = 1.0;
+        let mut time = drain_start;
+        timeline.points.push((time, health));
+        for (event_time, event) in judgements.events() {
+            let event_time = event_time.clamp(drain_start, drain_end).max(time);
+
+            // Health does not drain while a spinner is active, and spinning continuously restores it
+            let mut gain = 0.0;
+            if let JudgementEvent::Object(i) = event {
+                let details = judgements.objects()[i].details();
+                if matches!(details, JudgementDetails::Spinner { .. }) {
+                    let start = (objects[i].timestamp() as f64).clamp(time, event_time);
+                    health = timeline.drain(health, time, start, 0.0, breaks);
+                    timeline.points.push((start, health));
+                    time = start;
+                    let spin_health = scale_gain(spin_health(details), multiplier);
+                    if event_time > start {
+                        gain = spin_health / (event_time - start) + drain_rate;
+                    } else {
+                        health = (health + spin_health).min(1.0);
+                    }
+                }
+            }
+
+            health = timeline.drain(health, time, event_time, gain, breaks);
+            time = event_time;
+            if health <= 0.0 && !mods.contains(Mods::NO_FAIL) {
+                timeline.fail_at(time, FailReason::HealthDepleted);
+                return timeline;
+            }
+            timeline.points.push((time, health));
+
+            let (change, combo_break) = match event {
+                JudgementEvent::SliderPart(part) => (
+                    part_health(&part, hp),
+                    !part.hit() && part.kind() != SliderPart::End,
+                ),
+                JudgementEvent::Object(i) => {
+                    let judgement = &judgements.objects()[i];
+                    (
+                        object_health(judgement.result(), bonuses[i], hp),
+                        !judgement.result().is_hit()
+                            && !matches!(judgement.details(), JudgementDetails::Slider(_)),
+                    )
+                }
+            };
+            health = (health + scale_gain(change, multiplier)).clamp(0.0, 1.0);
+            timeline.points.push((time, health));
+
+            let imperfect = match event {
+                JudgementEvent::SliderPart(_) => combo_break,
+                JudgementEvent::Object(i) => judgements.objects()[i].result() != HitResult::Hit300,
+            };
+            if combo_break && mods.contains(Mods::SUDDEN_DEATH) {
+                timeline.fail_at(time, FailReason::SuddenDeath);
+                return timeline;
+            } else if imperfect && mods.contains(Mods::PERFECT) {
+                timeline.fail_at(time, FailReason::Perfect);
+                return timeline;
+            } else if health <= 0.0 && !mods.contains(Mods::NO_FAIL) {
+                timeline.fail_at(time, FailReason::HealthDepleted);
+                return timeline;
+            }
+        }
+
+        if time < drain_end {
+            health = timeline.drain(health, time, drain_end, 0.0, breaks);
+            timeline.points.push((drain_end, health));
+        }
+        timeline
+    }
+
+    /// Judges the replay on the given (unmodified) beatmap and simulates its health.
+    pub fn for_replay(map: &Beatmap, replay: &Replay) -> Result<Self, &'static str> {
+        let judgements = standard::judge(map, replay)?;
+        Ok(Self::simulate(map, &judgements, replay.mods))
+    }
+
+    /// The health after every change as `(time, health)` pairs, ordered by time.
+    /// The health changes linearly between consecutive points.
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points
+    }
+
+    /// The health drained per millisecond.
+    pub fn drain_rate(&self) -> f64 {
+        self.drain_rate
+    }
+
+    /// When and why the play was failed, if it was.
+    pub fn fail(&self) -> Option<&Fail> {
+        self.fail.as_ref()
+    }
+
+    /// The health at the given time in milliseconds.
+    pub fn health_at(&self, time: f64) -> f64 {
+        let i = self.points.partition_point(|&(t, _)| t <= time);
+        match (i.checked_sub(1).map(|i| self.points[i]), self.points.get(i)) {
+            (None, _) => 1.0,
+            (Some((_, health)), None) => health,
+            (Some((start, from)), Some(&(end, to))) => {
+                from + (to - from) * (time - start) / (end - start)
+            }
+        }
+    }
+
+    /// Compares the simulated health with a recorded life bar graph (e.g. `Replay::life_bar_graph`)
+    /// and returns all points which deviate noticeably, which hints at a desync.
+    pub fn compare(&self, life_bar_graph: &[(i64, f64)]) -> Vec<HealthDeviation> {
+        life_bar_graph
+            .iter()
+            .filter(|&&(time, _)| self.fail.is_none_or(|fail| (time as f64) < fail.time))
+            .map(|&(time, recorded)| HealthDeviation {
+                time,
+                recorded,
+                simulated: self.health_at(time as f64),
+            })
+            .filter(|deviation| {
+                (deviation.recorded - deviation.simulated).abs() > LIFE_BAR_TOLERANCE
+            })
+            .collect()
+    }
+
+    /// Drains health from `from` to `to`, pausing during breaks, and returns the remaining health.
+    /// `gain` is the health restored per millisecond at the same time, e.g. by spinning.
+    fn drain(
+        &mut self,
+        mut health: f64,
+        from: f64,
+        to: f64,
+        gain: f64,
+        breaks: &[BreakPeriod],
+    ) -> f64 {
+        let mut time = from;
+        // Add points around breaks, so the timeline can be interpolated linearly
+        for b in breaks {
+            if b.start() as f64 <= time || b.end() as f64 >= to {
+                continue;
+            }
+            health = self.drain_linear(health, time, b.start() as f64, gain);
+            self.points.push((b.start() as f64, health));
+            self.points.push((b.end() as f64, health));
+            time = b.end() as f64;
+        }
+        self.drain_linear(health, time, to, gain)
+    }
+
+    /// Changes the health linearly from `from` to `to`, adding a point where it reaches 0 or 1.
+    fn drain_linear(&mut self, health: f64, from: f64, to: f64, gain: f64) -> f64 {
+        let rate = gain - self.drain_rate;
+        let target = health + rate * (to - from);
+        if target > 1.0 && rate > 0.0 {
+            self.points.push((from + (1.0 - health) / rate, 1.0));
+            1.0
+        } else if target < 0.0 && rate < 0.0 {
+            self.points.push((from + health / -rate, 0.0));
+            0.0
+        } else {
+            target.clamp(0.0, 1.0)
+        }
+    }
+
+    fn fail_at(&mut self, time: f64, reason: FailReason) {
+        if reason == FailReason::HealthDepleted {
+            self.points.push((time, 0.0));
+        }
+        self.fail = Some(Fail { time, reason });
+    }
+}
+
+fn part_health(part: &SliderPartJudgement, hp: f64) -> f64 {
+    match (part.kind(), part.hit()) {
+        (SliderPart::Tick, true) => HEALTH_SLIDER_TICK,
+        (_, true) => HEALTH_SLIDER_PART,
+        // Missing the end of a slider is only punished by a worse judgement of the slider
+        (SliderPart::End, false) => 0.0,
+        (_, false) => difficulty_range(hp, -0.02, -0.075, -0.14),
+    }
+}
+
+fn object_health(result: HitResult, bonus: Option<SectionBonus>, hp: f64) -> f64 {
+    match (result, bonus) {
+        (HitResult::Miss, _) => difficulty_range(hp, -0.03, -0.125, -0.2),
+        (_, Some(SectionBonus::Geki)) => HEALTH_GEKI,
+        (_, Some(SectionBonus::Katu)) => HEALTH_KATU,
+        (HitResult::Hit300, None) => HEALTH_300,
+        (HitResult::Hit100, None) => HEALTH_100,
+        (HitResult::Hit50, None) => HEALTH_50,
+    }
+}
+
+/// The health restored by the rotations of a spinner.
+fn spin_health(details: &JudgementDetails) -> f64 {
+    match details {
+        JudgementDetails::Spinner {
+            rotations,
+            required_rotations,
+        } => {
+            let spins = rotations.floor();
+            let bonus_spins = (spins - required_rotations).max(0.0);
+            spins * HEALTH_SPIN + bonus_spins * HEALTH_BONUS_SPIN
+        }
+        _ => 0.0,
+    }
+}
+
+/// The time between `from` and `to` in which health drains, i.e. which is not part of a break.
+fn drain_time(from: f64, to: f64, breaks: &[BreakPeriod]) -> f64 {
+    let break_time = breaks
+        .iter()
+        .map(|b| (to.min(b.end() as f64) - from.max(b.start() as f64)).max(0.0))
+        .sum::<f64>();
+    (to - from - break_time).max(0.0)
+}
+
+/// Finds the drain rate osu!stable uses for a beatmap, by lowering it until a perfect play
+/// stays above a minimum health and ends with enough health left.
+///
+/// Like osu!stable, the health gains are raised whenever a perfect play doesn't end with enough health or doesn't
+/// recover enough health on average, since lowering the drain rate alone can't fix the latter.
+/// Returns the drain rate and the multiplier for health gains.
+pub(crate) fn drain_rate(map: &Beatmap, mods: Mods, hp: f64) -> (f64, f64) {
+    let lowest_health = difficulty_range(hp, 0.975, 0.8, 0.3);
+    let lowest_health_end = difficulty_range(hp, 0.99, 0.9, 0.4);
+    let recovery_needed = difficulty_range(hp, 0.04, 0.02, 0.0);
+
+    let objects = map.hit_objects();
+    let breaks = map.break_periods();
+    let judgements = standard::perfect(map, mods);
+    let bonuses = judgements.section_bonuses(objects);
+    let Some(first) = objects.first() else {
+        return (0.0, 1.0);
+    };
+
+    let mut rate = INITIAL_DRAIN_RATE;
+    let mut multiplier = 1.0;
+    'search: loop {
+        let mut health: f64 = 1.0;
+        let mut uncapped_health = 1.0;
+        let mut last_time = first.timestamp() as f64;
+
+        for (i, (obj, judgement)) in objects.iter().zip(judgements.iter()).enumerate() {
+            let start = obj.timestamp() as f64;
+            let drained = rate * drain_time(last_time, start, breaks);
+            health -= drained;
+            uncapped_health -= drained;
+            last_time = obj.end_time();
+            if health <= lowest_health {
+                rate *= 0.96;
+                continue 'search;
+            }
+
+            // Health drains while holding sliders and spinners as well
+            let drained = rate * (obj.end_time() - start);
+            let overkill = (drained - health).max(0.0);
+            health -= drained;
+            uncapped_health -= drained;
+            let changes = match judgement.details() {
+                JudgementDetails::Slider(parts) => {
+                    parts.iter().map(|part| part_health(part, hp)).collect()
+                }
+                details => vec![spin_health(details)],
+            };
+            for change in changes {
+                let change = scale_gain(change, multiplier);
+                health = (health + change).min(1.0);
+                uncapped_health += change;
+            }
+            if overkill > 0.0 && health - overkill <= lowest_health {
+                rate *= 0.96;
+                continue 'search;
+            }
+
+            let change = scale_gain(
+                object_health(judgement.result(), bonuses[i], hp),
+                multiplier,
+            );
+            health = (health + change).min(1.0);
+            uncapped_health += change;
+        }
+
+        if health < lowest_health_end {
+            rate *= 0.94;
+            multiplier *= 1.01;
+            continue;
+        }
+        if (uncapped_health - 1.0) / (objects.len() as f64) < recovery_needed {
+            rate *= 0.96;
+            multiplier *= 1.01;
+            continue;
+        }
+        return (rate, multiplier);
+    }
+}
+
+/// Applies the health multiplier found by [`drain_rate`] to a change of health, which only affects gains.
+fn scale_gain(change: f64, multiplier: f64) -> f64 {
+    if change > 0.0 {
+        change * multiplier
+    } else {
+        change
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    const TIFERET_MAP: &str =
+        "res/maps/714225 xi feat Sta - Tiferet/xi feat. Sta - Tiferet (ktgster) [Another].osu";
+    const TIFERET_REPLAY: &str =
+        "res/replays/Mitalun - xi feat. Sta - Tiferet [Another] (2021-10-20) Osu.osr";
+
+    /// A beatmap of circles a beat apart in a single combo.
+    fn circles(count: usize, hp: f32) -> Beatmap {
+        let hit_objects = (0..count)
+            .map(|i| format!("256,192,{},1,0", 1000 + i * 500))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let s = format!(
+            "osu file format v14\n\n\
+             [General]\nStackLeniency: 0.7\nMode: 0\n\n\
+             [Difficulty]\nHPDrainRate:{hp}\nCircleSize:4\nOverallDifficulty:5\nApproachRate:5\n\
+             SliderMultiplier:1.4\nSliderTickRate:1\n\n\
+             [TimingPoints]\n0,500,4,2,0,100,1,0\n\n\
+             [HitObjects]\n{hit_objects}\n"
+        );
+        Beatmap::from_str(&s).unwrap()
+    }
+
+    #[test]
+    fn drain_rate_search_raises_health_gains() {
+        // A perfect play gains less health per object on average than needed at HP 2
+        let map = circles(50, 2.0);
+        let (rate, multiplier) = drain_rate(&map, Mods::NONE, 2.0);
+        assert!(rate > 0.0 && rate <= INITIAL_DRAIN_RATE);
+        assert!(multiplier > 1.0);
+
+        let timeline =
+            HealthTimeline::simulate(&map, &standard::perfect(&map, Mods::NONE), Mods::NONE);
+        assert!(timeline.fail().is_none());
+        assert_eq!(timeline.drain_rate(), rate);
+    }
+
+    #[test]
+    fn drain_rate_of_empty_map() {
+        let map = circles(0, 5.0);
+        assert_eq!(drain_rate(&map, Mods::NONE, 5.0), (0.0, 1.0));
+    }
+
+    #[test]
+    fn perfect_play_does_not_fail() {
+        let map = Beatmap::from_str(&std::fs::read_to_string(TIFERET_MAP).unwrap()).unwrap();
+        for mods in [
+            Mods::NONE,
+            Mods::HARD_ROCK,
+            Mods::SUDDEN_DEATH | Mods::PERFECT,
+        ] {
+            let timeline = HealthTimeline::simulate(&map, &standard::perfect(&map, mods), mods);
+            assert!(timeline.fail().is_none());
+            assert!(timeline.points().iter().all(|&(_, health)| health > 0.0));
+        }
+    }
+
+    #[test]
+    fn replay_health_matches_life_bar() {
+        let map = Beatmap::from_str(&std::fs::read_to_string(TIFERET_MAP).unwrap()).unwrap();
+        let replay = Replay::try_from(&std::fs::read(TIFERET_REPLAY).unwrap()[..]).unwrap();
+        let timeline = HealthTimeline::for_replay(&map, &replay).unwrap();
+        assert!(timeline.fail().is_none());
+        assert!(!replay.life_bar_graph.is_empty());
+        assert!(timeline.compare(&replay.life_bar_graph).is_empty());
+    }
+}
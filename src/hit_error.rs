@@ -0,0 +1,271 @@
+use crate::{
+    judgement::{self, HitResult},
+    map::Beatmap,
+    osu_data::GameMode,
+    replay::Replay,
+};
+
+/// The timing of the hits of a play, from which the unstable rate and other statistics are computed.
+///
+/// Hit errors are in real time (milliseconds), so they are comparable between plays with and without
+/// Double Time or Half Time. The times of the hit objects stay in song time.
+#[derive(Debug, Clone, Default)]
+pub struct HitErrors {
+    errors: Vec<(f64, f64)>,
+}
+
+impl HitErrors {
+    /// Creates the statistics from the time of every hit object and the hit error of the hit on it in song time,
+    /// both in milliseconds. The errors are divided by the clock rate to convert them to real time.
+    pub fn new(errors: impl IntoIterator<Item = (f64, f64)>, clock_rate: f64) -> Self {
+        let mut errors = errors
+            .into_iter()
+            .map(|(time, error)| (time, error / clock_rate))
+            .collect::<Vec<_>>();
+        errors.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { errors }
+    }
+
+    /// Judges the replay on the given (unmodified) beatmap and collects the hit errors of all objects which were hit.
+    ///
+    /// Only presses count in osu!mania, not the release of hold notes.
+    /// osu!catch has no hit errors, so its statistics are empty.
+    pub fn for_replay(map: &Beatmap, replay: &Replay) -> Self {
+        let errors = match replay.mode {
+            GameMode::Standard => judgement::standard::judge(map, replay)
+                .expect("The replay is an osu!standard replay")
+                .iter()
+                .filter(|judgement| judgement.result() != HitResult::Miss)
+                .filter_map(|judgement| {
+                    let time = map.hit_objects()[judgement.object_index()].timestamp() as f64;
+                    Some((time, judgement.hit_error()?))
+                })
+                .collect::<Vec<_>>(),
+            GameMode::Taiko => judgement::taiko::judge(map, replay)
+                .hits()
+                .iter()
+                .filter(|hit| hit.result() != HitResult::Miss)
+                .filter_map(|hit| Some((hit.time(), hit.hit_error()?)))
+                .collect(),
+            GameMode::CatchTheBeat => vec![],
+            GameMode::Mania => judgement::mania::judge(map, replay)
+                .judgements()
+                .iter()
+                .filter(|judgement| judgement.result().is_hit())
+                .filter_map(|judgement| {
+                    Some((judgement.object().start_time(), judgement.hit_error()?))
+                })
+                .collect(),
+        };
+        Self::new(errors, replay.mods.clock_rate())
+    }
+
+    /// The time of every hit object together with its hit error, ordered by time.
+    pub fn errors(&self) -> &[(f64, f64)] {
+        &self.errors
+    }
+
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// The unstable rate, which is ten times the standard deviation of the hit errors.
+    pub fn unstable_rate(&self) -> Option<f64> {
+        self.std_dev().map(|std_dev| 10.0 * std_dev)
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        mean(self.errors.iter().map(|&(_, error)| error))
+    }
+
+    pub fn median(&self) -> Option<f64> {
+        let mut errors = self
+            .errors
+            .iter()
+            .map(|&(_, error)| error)
+            .collect::<Vec<_>>();
+        errors.sort_by(f64::total_cmp);
+        let middle = errors.len() / 2;
+        match errors.len() {
+            0 => None,
+            len if len % 2 == 0 => Some((errors[middle - 1] + errors[middle]) / 2.0),
+            _ => Some(errors[middle]),
+        }
+    }
+
+    /// The standard deviation of the hit errors, as osu! computes it for the unstable rate.
+    pub fn std_dev(&self) -> Option<f64> {
+        std_dev(self.errors.iter().map(|&(_, error)| error))
+    }
+
+    /// The number of hits which were early.
+    pub fn n_early(&self) -> usize {
+        self.errors
+            .iter()
+            .filter(|&&(_, error)| error < 0.0)
+            .count()
+    }
+
+    /// The number of hits which were late or exactly on time.
+    pub fn n_late(&self) -> usize {
+        self.len() - self.n_early()
+    }
+
+    /// The average error of the early hits, as shown in osu!'s results screen.
+    pub fn mean_early(&self) -> Option<f64> {
+        mean(
+            self.errors
+                .iter()
+                .map(|&(_, error)| error)
+                .filter(|&error| error < 0.0),
+        )
+    }
+
+    /// The average error of the late hits, as shown in osu!'s results screen.
+    pub fn mean_late(&self) -> Option<f64> {
+        mean(
+            self.errors
+                .iter()
+                .map(|&(_, error)| error)
+                .filter(|&error| error >= 0.0),
+        )
+    }
+
+    /// The standard deviation of the hit errors in consecutive sections of the given length (in song time),
+    /// given by the start of each section. Sections without any hits have no deviation.
+    pub fn section_std_devs(&self, section_length: f64) -> Vec<(f64, Option<f64>)> {
+        let Some(&(last_time, _)) = self.errors.last() else {
+            return vec![];
+        };
+        let section_count = (last_time / section_length).floor().max(0.0) as usize + 1;
+        let mut sections = vec![vec![]; section_count];
+        for &(time, error) in &self.errors {
+            let section = (time / section_length).floor().max(0.0) as usize;
+            sections[section].push(error);
+        }
+        sections
+            .into_iter()
+            .enumerate()
+            .map(|(i, errors)| (i as f64 * section_length, std_dev(errors)))
+            .collect()
+    }
+
+    /// Counts the hit errors in buckets of the given width (in milliseconds), given by the lower end of each bucket
+    /// and ordered from the earliest to the latest. Buckets between the earliest and latest hit are included even if empty.
+    pub fn histogram(&self, bucket_width: f64) -> Vec<(f64, usize)> {
+        let buckets = self
+            .errors
+            .iter()
+            .map(|&(_, error)| (error / bucket_width).floor() as i64)
+            .collect::<Vec<_>>();
+        let (Some(&min), Some(&max)) = (buckets.iter().min(), buckets.iter().max()) else {
+            return vec![];
+        };
+
+        let mut counts = vec![0; (max - min + 1) as usize];
+        for bucket in buckets {
+            counts[(bucket - min) as usize] += 1;
+        }
+        counts
+            .into_iter()
+            .enumerate()
+            .map(|(i, count)| ((min + i as i64) as f64 * bucket_width, count))
+            .collect()
+    }
+}
+
+fn mean(values: impl IntoIterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values
+        .into_iter()
+        .fold((0.0, 0), |(sum, count), value| (sum + value, count + 1));
+    (count > 0).then(|| sum / count as f64)
+}
+
+/// The population standard deviation of the values.
+fn std_dev(values: impl IntoIterator<Item = f64> + Clone) -> Option<f64> {
+    let mean_value = mean(values.clone())?;
+    let variance = mean(values.into_iter().map(|value| (value - mean_value).powi(2)))?;
+    Some(variance.sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{autoplay::Autoplay, replay::Mods, test_data};
+
+    use super::*;
+
+    fn errors() -> HitErrors {
+        HitErrors::new(
+            [(300.0, -20.0), (0.0, -10.0), (100.0, 10.0), (1200.0, 20.0)],
+            1.0,
+        )
+    }
+
+    #[test]
+    fn statistics() {
+        let errors = errors();
+        assert_eq!(errors.errors()[0], (0.0, -10.0));
+        assert_eq!(errors.mean(), Some(0.0));
+        assert_eq!(errors.median(), Some(0.0));
+        assert_eq!(errors.std_dev(), Some(250f64.sqrt()));
+        assert_eq!(errors.unstable_rate(), Some(10.0 * 250f64.sqrt()));
+        assert_eq!((errors.n_early(), errors.n_late()), (2, 2));
+        assert_eq!(errors.mean_early(), Some(-15.0));
+        assert_eq!(errors.mean_late(), Some(15.0));
+
+        let empty = HitErrors::default();
+        assert!(empty.is_empty());
+        assert_eq!(empty.unstable_rate(), None);
+        assert_eq!(empty.median(), None);
+    }
+
+    #[test]
+    fn errors_are_in_real_time() {
+        let errors = HitErrors::new([(0.0, -15.0), (100.0, 30.0)], 1.5);
+        assert_eq!(errors.errors(), [(0.0, -10.0), (100.0, 20.0)]);
+    }
+
+    #[test]
+    fn sections_and_histogram() {
+        let errors = errors();
+        assert_eq!(
+            errors.section_std_devs(500.0),
+            [
+                (0.0, Some((1400f64 / 9.0).sqrt())),
+                (500.0, None),
+                (1000.0, Some(0.0))
+            ]
+        );
+        assert_eq!(
+            errors.histogram(15.0),
+            [(-30.0, 1), (-15.0, 1), (0.0, 1), (15.0, 1)]
+        );
+    }
+
+    #[test]
+    fn unstable_rate_of_tiferet() {
+        // Regression values of this implementation
+        let (_, replay, map) = test_data::replays_with_maps().remove(0);
+        let errors = HitErrors::for_replay(&map, &replay);
+        assert!((errors.unstable_rate().unwrap() - 146.19).abs() < 0.01);
+        assert!((errors.mean().unwrap() - 1.31).abs() < 0.01);
+        assert_eq!((errors.n_early(), errors.n_late()), (367, 457));
+    }
+
+    #[test]
+    fn autoplay_hits_on_time() {
+        let map = test_data::map_named("Insane");
+        let replay = Autoplay::new(GameMode::Standard, Mods::empty()).generate(&map);
+        let errors = HitErrors::for_replay(&map, &replay);
+        assert!(!errors.is_empty());
+        assert!(
+            errors.unstable_rate().unwrap() < 10.0,
+            "{:?}",
+            errors.unstable_rate()
+        );
+    }
+}
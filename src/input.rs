@@ -0,0 +1,341 @@
+use crate::{
+    osu_data::GameMode,
+    replay::{InputKeys, Replay},
+};
+
+/// A key or mouse button recorded in replays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    M1,
+    M2,
+    K1,
+    K2,
+    Smoke,
+}
+
+impl Key {
+    pub const ALL: [Key; 5] = [Key::M1, Key::M2, Key::K1, Key::K2, Key::Smoke];
+
+    fn flag(&self) -> InputKeys {
+        match self {
+            Self::M1 => InputKeys::M1,
+            Self::M2 => InputKeys::M2,
+            Self::K1 => InputKeys::K1,
+            Self::K2 => InputKeys::K2,
+            Self::Smoke => InputKeys::SMOKE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyEventKind {
+    Press,
+    Release,
+}
+
+/// A key going down or up at a certain time (in milliseconds).
+#[derive(Debug, Clone, Copy)]
+pub struct KeyEvent {
+    time: f64,
+    key: Key,
+    kind: KeyEventKind,
+}
+
+impl KeyEvent {
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn key(&self) -> Key {
+        self.key
+    }
+
+    pub fn kind(&self) -> KeyEventKind {
+        self.kind
+    }
+}
+
+/// A key being held from the time it was pressed until it was released (in milliseconds).
+#[derive(Debug, Clone, Copy)]
+pub struct KeyPress {
+    key: Key,
+    start: f64,
+    end: f64,
+}
+
+impl KeyPress {
+    pub fn key(&self) -> Key {
+        self.key
+    }
+
+    pub fn start(&self) -> f64 {
+        self.start
+    }
+
+    /// The time the key was released, or the time of the last frame if it was never released.
+    pub fn end(&self) -> f64 {
+        self.end
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+/// The presses and releases of every key in a replay, like an input overlay shows them.
+///
+/// Frames only store which keys are held, so events are found by comparing consecutive frames.
+/// In osu!standard, K1 also sets M1 and K2 also sets M2, so the mouse buttons only count as held
+/// while the corresponding key isn't. osu!mania stores its keys differently and has no events.
+/// All times are in song time.
+#[derive(Debug, Clone)]
+pub struct KeyInputs {
+    events: Vec<KeyEvent>,
+    presses: Vec<KeyPress>,
+    clock_rate: f64,
+}
+
+impl KeyInputs {
+    pub fn new(replay: &Replay) -> Self {
+        let clock_rate = replay.mods.clock_rate();
+        if matches!(replay.mode, GameMode::Mania) {
+            return Self {
+                events: vec![],
+                presses: vec![],
+                clock_rate,
+            };
+        }
+
+        let mut events = vec![];
+        let mut presses = vec![];
+        let mut held = InputKeys::empty();
+        let mut press_starts = [0.0; Key::ALL.len()];
+        let mut last_time = 0.0;
+        for (time, frame) in replay.timed_frames() {
+            let time = time as f64;
+            let mut keys = frame.input_keys();
+            if matches!(replay.mode, GameMode::Standard) {
+                if keys.contains(InputKeys::K1) {
+                    keys.remove(InputKeys::M1);
+                }
+                if keys.contains(InputKeys::K2) {
+                    keys.remove(InputKeys::M2);
+                }
+            }
+
+            for (i, key) in Key::ALL.into_iter().enumerate() {
+                let (was_held, is_held) = (held.contains(key.flag()), keys.contains(key.flag()));
+                if !was_held && is_held {
+                    press_starts[i] = time;
+                    events.push(KeyEvent {
+                        time,
+                        key,
+                        kind: KeyEventKind::Press,
+                    });
+                } else if was_held && !is_held {
+                    presses.push(KeyPress {
+                        key,
+                        start: press_starts[i],
+                        end: time,
+                    });
+                    events.push(KeyEvent {
+                        time,
+                        key,
+                        kind: KeyEventKind::Release,
+                    });
+                }
+            }
+            held = keys;
+            last_time = time;
+        }
+
+        // Keys still held at the end of the replay are released with the last frame
+        for (i, key) in Key::ALL.into_iter().enumerate() {
+            if held.contains(key.flag()) {
+                presses.push(KeyPress {
+                    key,
+                    start: press_starts[i],
+                    end: last_time,
+                });
+            }
+        }
+        presses.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+        Self {
+            events,
+            presses,
+            clock_rate,
+        }
+    }
+
+    /// Every press and release, ordered by time.
+    pub fn events(&self) -> &[KeyEvent] {
+        &self.events
+    }
+
+    /// Every press with its duration, ordered by the time the keys went down.
+    pub fn presses(&self) -> &[KeyPress] {
+        &self.presses
+    }
+
+    /// The presses of the given key, ordered by time.
+    pub fn presses_of(&self, key: Key) -> impl Iterator<Item = &KeyPress> + '_ {
+        self.presses.iter().filter(move |press| press.key == key)
+    }
+
+    /// The number of times the given key was pressed.
+    pub fn tap_count(&self, key: Key) -> usize {
+        self.presses_of(key).count()
+    }
+
+    /// The average time the given key was held for.
+    pub fn mean_hold_duration(&self, key: Key) -> Option<f64> {
+        let count = self.tap_count(key);
+        (count > 0)
+            .then(|| self.presses_of(key).map(KeyPress::duration).sum::<f64>() / count as f64)
+    }
+
+    /// The fraction of presses (not counting smoke) which used a different key than the press before.
+    /// Players who alternate perfectly have a ratio of 1, players who single tap one of 0.
+    pub fn alternation_ratio(&self) -> Option<f64> {
+        let keys = self
+            .tapping_presses()
+            .map(|press| press.key)
+            .collect::<Vec<_>>();
+        if keys.len() < 2 {
+            return None;
+        }
+        let alternations = keys.windows(2).filter(|pair| pair[0] != pair[1]).count();
+        Some(alternations as f64 / (keys.len() - 1) as f64)
+    }
+
+    /// The number of presses (not counting smoke) per second of real time in consecutive windows
+    /// of the given length in song time, given by the end of each window.
+    pub fn keys_per_second(&self, window: f64) -> Vec<(f64, f64)> {
+        let mut counts: Vec<usize> = vec![];
+        for press in self.tapping_presses() {
+            let index = (press.start / window).floor().max(0.0) as usize;
+            if index >= counts.len() {
+                counts.resize(index + 1, 0);
+            }
+            counts[index] += 1;
+        }
+
+        let seconds = window / self.clock_rate / 1000.0;
+        counts
+            .into_iter()
+            .enumerate()
+            .map(|(i, count)| ((i + 1) as f64 * window, count as f64 / seconds))
+            .collect()
+    }
+
+    fn tapping_presses(&self) -> impl Iterator<Item = &KeyPress> + '_ {
+        self.presses.iter().filter(|press| press.key != Key::Smoke)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{replay::Mods, test_data};
+
+    use super::*;
+
+    fn inputs(mode: GameMode, mods: Mods) -> KeyInputs {
+        let k1 = InputKeys::K1 | InputKeys::M1;
+        let k2 = InputKeys::K2 | InputKeys::M2;
+        let mut frames = test_data::key_frames(
+            (256.0, 192.0),
+            &[
+                (1000, 1100, k1),
+                (1200, 1250, k2),
+                (1300, 1350, k2),
+                (1400, 1500, k1),
+                (1600, 1700, InputKeys::M1),
+                (1800, 3000, InputKeys::SMOKE),
+            ],
+        );
+        // Smoke is still held when the replay ends
+        frames.retain(|&(time, ..)| time < 3000);
+        frames.push((2000, 256.0, 192.0, InputKeys::SMOKE));
+        KeyInputs::new(&test_data::replay(mode, mods, &frames))
+    }
+
+    #[test]
+    fn keys_set_their_mouse_button_in_osu_standard() {
+        let inputs = inputs(GameMode::Standard, Mods::empty());
+        let events = inputs
+            .events()
+            .iter()
+            .map(|event| (event.time(), event.key(), event.kind()))
+            .take(4)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            [
+                (1000.0, Key::K1, KeyEventKind::Press),
+                (1100.0, Key::K1, KeyEventKind::Release),
+                (1200.0, Key::K2, KeyEventKind::Press),
+                (1250.0, Key::K2, KeyEventKind::Release),
+            ]
+        );
+        assert_eq!(inputs.tap_count(Key::K1), 2);
+        assert_eq!(inputs.tap_count(Key::K2), 2);
+        assert_eq!(inputs.tap_count(Key::M1), 1);
+        assert_eq!(inputs.tap_count(Key::M2), 0);
+
+        // Other modes don't set the mouse buttons with the keys
+        let inputs = self::inputs(GameMode::Taiko, Mods::empty());
+        assert_eq!(inputs.tap_count(Key::M1), 3);
+        assert_eq!(inputs.tap_count(Key::M2), 2);
+    }
+
+    #[test]
+    fn hold_durations() {
+        let inputs = inputs(GameMode::Standard, Mods::empty());
+        assert_eq!(inputs.mean_hold_duration(Key::K1), Some(100.0));
+        assert_eq!(inputs.mean_hold_duration(Key::K2), Some(50.0));
+        assert_eq!(inputs.mean_hold_duration(Key::M2), None);
+        let smoke = inputs.presses_of(Key::Smoke).collect::<Vec<_>>();
+        assert!(matches!(
+            smoke[..],
+            [press] if press.start() == 1800.0 && press.end() == 2000.0
+        ));
+        assert_eq!(inputs.presses().len(), 6);
+    }
+
+    #[test]
+    fn tapping_statistics() {
+        let inputs = inputs(GameMode::Standard, Mods::empty());
+        // K1, K2, K2, K1, M1
+        assert_eq!(inputs.alternation_ratio(), Some(0.75));
+        assert_eq!(
+            inputs.keys_per_second(1000.0),
+            [(1000.0, 0.0), (2000.0, 5.0)]
+        );
+
+        // A second of song time is two thirds of a second with Double Time
+        let inputs = self::inputs(GameMode::Standard, Mods::DOUBLE_TIME);
+        assert_eq!(
+            inputs.keys_per_second(1000.0),
+            [(1000.0, 0.0), (2000.0, 7.5)]
+        );
+    }
+
+    #[test]
+    fn osu_mania_has_no_events() {
+        let inputs = inputs(GameMode::Mania, Mods::empty());
+        assert!(inputs.events().is_empty());
+        assert_eq!(inputs.alternation_ratio(), None);
+    }
+
+    #[test]
+    fn inputs_of_tiferet() {
+        let (_, replay, _) = test_data::replays_with_maps().remove(0);
+        let inputs = KeyInputs::new(&replay);
+        let taps = inputs.tap_count(Key::K1) + inputs.tap_count(Key::K2);
+        // At least one tap for every circle, slider and spinner
+        assert!(taps >= 829, "{taps}");
+        let ratio = inputs.alternation_ratio().unwrap();
+        assert!(ratio > 0.0 && ratio < 1.0, "{ratio}");
+    }
+}
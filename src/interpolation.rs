@@ -113,6 +113,46 @@ where
     cast_tuple(c).unwrap()
 }
 
+/// Uniform Catmull-Rom interpolation between `p1` and `p2`, as used by osu! for catmull sliders.
+pub fn interpolate_catmull<I, F>(
+    p0: (I, I),
+    p1: (I, I),
+    p2: (I, I),
+    p3: (I, I),
+    lambda: F,
+) -> (I, I)
+where
+    I: NumCast + Copy,
+    F: Float,
+{
+    let lambda = clamp_float(lambda, F::zero(), F::one());
+    let p0 = cast_tuple::<I, F>(p0).unwrap();
+    let p1 = cast_tuple::<I, F>(p1).unwrap();
+    let p2 = cast_tuple::<I, F>(p2).unwrap();
+    let p3 = cast_tuple::<I, F>(p3).unwrap();
+
+    let t2 = lambda * lambda;
+    let t3 = t2 * lambda;
+    let two: F = cast(2).unwrap();
+    let three: F = cast(3).unwrap();
+    let four: F = cast(4).unwrap();
+    let five: F = cast(5).unwrap();
+    let half: F = cast(0.5).unwrap();
+
+    let component = |v0: F, v1: F, v2: F, v3: F| {
+        half * (two * v1
+            + (v2 - v0) * lambda
+            + (two * v0 - five * v1 + four * v2 - v3) * t2
+            + (three * v1 - v0 - three * v2 + v3) * t3)
+    };
+
+    cast_tuple((
+        component(p0.0, p1.0, p2.0, p3.0),
+        component(p0.1, p1.1, p2.1, p3.1),
+    ))
+    .unwrap()
+}
+
 pub fn interpolate_perfect_circle<I, F>(start: (I, I), end: (I, I), center: (I, I), radius: I, lambda: F) -> (I, I)
 where
     I: NumCast + Copy,
@@ -159,28 +199,29 @@ fn cast_tuple<I: NumCast, O: NumCast>(tup: (I, I)) -> Option<(O, O)> {
     Some((cast::<I, O>(tup.0)?, cast::<I, O>(tup.1)?))
 }
 
-/// https://stackoverflow.com/questions/4103405/what-is-the-algorithm-for-finding-the-center-of-a-circle-from-three-points
+/// Finds the center and radius of the circle passing through the three given points.
+///
+/// The points must not be collinear, otherwise the resulting values are not finite.
 pub fn find_circle<I: NumCast, O: NumCast>(p0: (I, I), p1: (I, I), p2: (I, I)) -> ((O, O), O) {
 
     let p0 = cast_tuple::<I, f64>(p0).unwrap();
     let p1 = cast_tuple::<I, f64>(p1).unwrap();
     let p2 = cast_tuple::<I, f64>(p2).unwrap();
 
-    let y_delta_a = p1.1 - p0.1;
-    let x_delta_a = p1.0 - p0.0;
-    let y_delta_b = p2.1 - p1.1;
-    let x_delta_b = p2.0 - p1.0;
+    // Circumcenter via the determinant formulation, which (unlike the slope based one) also works
+    // for vertical and horizontal chords
+    let d = 2.0 * (p0.0 * (p1.1 - p2.1) + p1.0 * (p2.1 - p0.1) + p2.0 * (p0.1 - p1.1));
+    let sq0 = p0.0 * p0.0 + p0.1 * p0.1;
+    let sq1 = p1.0 * p1.0 + p1.1 * p1.1;
+    let sq2 = p2.0 * p2.0 + p2.1 * p2.1;
 
-    let slope_a = y_delta_a / x_delta_a;
-    let slope_b = y_delta_b / x_delta_b;
-    let center_x = (slope_a * slope_b * (p0.1 - p2.1) + slope_b * (p0.0 + p1.0) - slope_a * (p1.0 + p2.0)) / (2.0 * (slope_b - slope_a));
-    let center_y = -1.0 * (center_x - (p0.0 + p1.0) / 2.0) / slope_a + (p0.1 + p1.1) / 2.0;
+    let center_x = (sq0 * (p1.1 - p2.1) + sq1 * (p2.1 - p0.1) + sq2 * (p0.1 - p1.1)) / d;
+    let center_y = (sq0 * (p2.0 - p1.0) + sq1 * (p0.0 - p2.0) + sq2 * (p1.0 - p0.0)) / d;
 
     let radius = ((p0.0 - center_x) * (p0.0 - center_x) + (p0.1 - center_y) * (p0.1 - center_y)).sqrt();
     (cast_tuple((center_x, center_y)).unwrap(), NumCast::from(radius).unwrap())
 }
 
-
 fn tuple_mul_scalar<N>((x, y): (N, N), scalar: N) -> (N, N)
 where
     N: Mul<Output = N> + Copy,
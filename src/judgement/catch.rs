@@ -0,0 +1,284 @@
+use crate::{
+    map::{
+        catch::{catch_width, CatchObjectKind},
+        Beatmap,
+    },
+    osu_data::PLAYFIELD_WIDTH,
+    replay::{InputKeys, Replay},
+};
+
+/// The position of the catcher as recorded in a replay frame.
+#[derive(Debug, Clone, Copy)]
+struct CatcherFrame {
+    time: f64,
+    x: f32,
+    dashing: bool,
+}
+
+/// Whether a single fruit, droplet or banana was caught.
+#[derive(Debug, Clone, Copy)]
+pub struct CatchJudgement {
+    kind: CatchObjectKind,
+    time: f64,
+    x: f32,
+    caught: bool,
+    catcher_x: f32,
+    dashing: bool,
+    hyper_dash: bool,
+}
+
+impl CatchJudgement {
+    pub fn kind(&self) -> CatchObjectKind {
+        self.kind
+    }
+
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// The position of the object.
+    pub fn x(&self) -> f32 {
+        self.x
+    }
+
+    pub fn caught(&self) -> bool {
+        self.caught
+    }
+
+    /// The position of the catcher when the object reached it.
+    pub fn catcher_x(&self) -> f32 {
+        self.catcher_x
+    }
+
+    /// Whether the catcher was dashing when the object reached it.
+    pub fn dashing(&self) -> bool {
+        self.dashing
+    }
+
+    /// Whether catching the object gave a hyperdash to the next one.
+    pub fn hyper_dash(&self) -> bool {
+        self.hyper_dash
+    }
+}
+
+/// The judgements of all objects of an osu!catch play, ordered by time.
+#[derive(Debug, Clone, Default)]
+pub struct CatchJudgements {
+    judgements: Vec<CatchJudgement>,
+}
+
+impl CatchJudgements {
+    pub fn judgements(&self) -> &[CatchJudgement] {
+        &self.judgements
+    }
+
+    fn count(&self, kind: CatchObjectKind, caught: bool) -> usize {
+        self.judgements
+            .iter()
+            .filter(|j| j.kind == kind && j.caught == caught)
+            .count()
+    }
+
+    /// The number of fruits caught, which osu!stable stores as 300s.
+    pub fn n_300(&self) -> usize {
+        self.count(CatchObjectKind::Fruit, true)
+    }
+
+    /// The number of droplets caught, which osu!stable stores as 100s.
+    pub fn n_100(&self) -> usize {
+        self.count(CatchObjectKind::Droplet, true)
+    }
+
+    /// The number of tiny droplets caught, which osu!stable stores as 50s.
+    pub fn n_50(&self) -> usize {
+        self.count(CatchObjectKind::TinyDroplet, true)
+    }
+
+    /// The number of tiny droplets missed, which osu!stable stores as katus.
+    pub fn n_katu(&self) -> usize {
+        self.count(CatchObjectKind::TinyDroplet, false)
+    }
+
+    /// The number of fruits and droplets missed.
+    pub fn n_miss(&self) -> usize {
+        self.count(CatchObjectKind::Fruit, false) + self.count(CatchObjectKind::Droplet, false)
+    }
+
+    pub fn bananas_caught(&self) -> usize {
+        self.count(CatchObjectKind::Banana, true)
+    }
+
+    /// The highest combo, which only fruits and droplets count towards.
+    pub fn max_combo(&self) -> usize {
+        let mut combo = 0;
+        let mut max_combo = 0;
+        for judgement in self.judgements.iter().filter(|j| j.kind.affects_combo()) {
+            combo = if judgement.caught { combo + 1 } else { 0 };
+            max_combo = max_combo.max(combo);
+        }
+        max_combo
+    }
+}
+
+/// Simulates the catching of every object in osu!catch given the replay played on the (unmodified) beatmap.
+///
+/// The x coordinate of a replay frame is the position of the catcher and M1 means it is dashing.
+/// Between frames the catcher is assumed to move in a straight line.
+pub fn judge(map: &Beatmap, replay: &Replay) -> CatchJudgements {
+    let difficulty = map.difficulty().for_replay(replay);
+    let half_width = catch_width(difficulty.difficulty().circle_size()) / 2.0;
+    let frames = replay
+        .timed_frames()
+        .map(|(time, frame)| CatcherFrame {
+            time: time as f64,
+            x: frame.x().clamp(0.0, PLAYFIELD_WIDTH),
+            dashing: frame.input_keys().contains(InputKeys::M1),
+        })
+        .collect::<Vec<_>>();
+
+    let judgements = map
+        .catch_objects(replay.mods)
+        .into_iter()
+        .map(|object| {
+            let catcher = catcher_at(&frames, object.time());
+            CatchJudgement {
+                kind: object.kind(),
+                time: object.time(),
+                x: object.x(),
+                caught: (object.x() - catcher.x).abs() <= half_width,
+                catcher_x: catcher.x,
+                dashing: catcher.dashing,
+                hyper_dash: object.hyper_dash(),
+            }
+        })
+        .collect();
+
+    CatchJudgements { judgements }
+}
+
+/// The state of the catcher at the given time, interpolated between the surrounding frames.
+fn catcher_at(frames: &[CatcherFrame], time: f64) -> CatcherFrame {
+    let next = frames.partition_point(|frame| frame.time <= time);
+    match (next.checked_sub(1).map(|i| frames[i]), frames.get(next)) {
+        (Some(previous), Some(&next)) if next.time > previous.time => {
+            let lambda = ((time - previous.time) / (next.time - previous.time)) as f32;
+            CatcherFrame {
+                time,
+                x: previous.x + (next.x - previous.x) * lambda,
+                dashing: previous.dashing,
+            }
+        }
+        (_, Some(&frame)) | (Some(frame), None) => frame,
+        (None, None) => CatcherFrame {
+            time,
+            x: PLAYFIELD_WIDTH / 2.0,
+            dashing: false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        autoplay::Autoplay,
+        osu_data::GameMode,
+        replay::Mods,
+        test_data::{self, TestMap},
+    };
+
+    use super::*;
+
+    fn catch_map(hit_objects: &[&str]) -> Beatmap {
+        TestMap {
+            mode: 2,
+            ..TestMap::new(hit_objects)
+        }
+        .build()
+    }
+
+    /// A replay in which the catcher is at the given positions at the given times.
+    fn replay(positions: &[(i64, f32, InputKeys)]) -> Replay {
+        let frames = positions
+            .iter()
+            .map(|&(time, x, keys)| (time, x, 0.0, keys))
+            .collect::<Vec<_>>();
+        test_data::replay(GameMode::CatchTheBeat, Mods::empty(), &frames)
+    }
+
+    #[test]
+    fn fruits_within_the_catcher_are_caught() {
+        // The catcher catches 48.7 osu!pixels to either side at circle size 4
+        let map = catch_map(&["256,192,1000,1,0", "300,192,2000,1,0", "400,192,3000,1,0"]);
+        let replay = replay(&[(0, 256.0, InputKeys::empty())]);
+
+        let judgements = judge(&map, &replay);
+        let caught = judgements
+            .judgements()
+            .iter()
+            .map(CatchJudgement::caught)
+            .collect::<Vec<_>>();
+        assert_eq!(caught, [true, true, false]);
+        assert_eq!(judgements.n_300(), 2);
+        assert_eq!(judgements.n_miss(), 1);
+        assert_eq!(judgements.max_combo(), 2);
+    }
+
+    #[test]
+    fn juice_stream_followed_by_the_catcher() {
+        let map = catch_map(&["100,192,1000,2,0,L|380:192,1,280"]);
+        // The catcher moves between frames in a straight line, just like the juice stream
+        let following = replay(&[
+            (1000, 100.0, InputKeys::empty()),
+            (2000, 380.0, InputKeys::empty()),
+        ]);
+        let judgements = judge(&map, &following);
+        assert_eq!(judgements.n_300(), 2);
+        assert_eq!(judgements.n_100(), 1);
+        assert_eq!(judgements.n_50(), 14);
+        assert_eq!(judgements.n_katu(), 0);
+        assert_eq!(judgements.max_combo(), 3);
+
+        // Staying at the head only catches the head and the tiny droplets right after it
+        let judgements = judge(&map, &replay(&[(0, 100.0, InputKeys::empty())]));
+        assert_eq!(judgements.n_300(), 1);
+        assert_eq!(judgements.n_100(), 0);
+        assert_eq!(judgements.n_miss(), 2);
+        assert!(judgements.n_katu() > 0);
+    }
+
+    #[test]
+    fn dashes_and_hyper_dashes() {
+        let map = catch_map(&["0,192,1000,1,0", "512,192,1100,1,0"]);
+        let replay = replay(&[
+            (1000, 0.0, InputKeys::M1),
+            (1100, 512.0, InputKeys::empty()),
+        ]);
+        let judgements = judge(&map, &replay).judgements().to_vec();
+        assert!(judgements.iter().all(CatchJudgement::caught));
+        assert!(judgements[0].dashing() && judgements[0].hyper_dash());
+        assert!(!judgements[1].dashing() && !judgements[1].hyper_dash());
+    }
+
+    #[test]
+    fn autoplay_catches_everything() {
+        let map = catch_map(&[
+            "100,192,1000,2,0,L|380:192,2,280",
+            "0,192,3200,1,0",
+            "512,192,3300,1,0",
+            "256,192,4000,12,0,5000",
+        ]);
+        let replay = Autoplay::new(GameMode::CatchTheBeat, Mods::empty()).generate(&map);
+        let judgements = judge(&map, &replay);
+        assert!(judgements.judgements().iter().all(CatchJudgement::caught));
+        assert_eq!(judgements.bananas_caught(), 17);
+
+        for (path, _, map) in test_data::replays_with_maps() {
+            for mods in [Mods::empty(), Mods::HARD_ROCK] {
+                let replay = Autoplay::new(GameMode::CatchTheBeat, mods).generate(&map);
+                let judgements = judge(&map, &replay);
+                assert_eq!(judgements.n_miss(), 0, "{}", path.display());
+                assert_eq!(judgements.n_katu(), 0, "{}", path.display());
+            }
+        }
+    }
+}
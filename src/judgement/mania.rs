@@ -0,0 +1,513 @@
+use crate::{
+    map::{mania::ManiaObject, Beatmap},
+    replay::{Mods, Replay},
+};
+
+/// The judgement a note receives in osu!mania.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ManiaHitResult {
+    /// The rainbow 300, stored as a geki in replays
+    Max,
+    Hit300,
+    /// Stored as a katu in replays
+    Hit200,
+    Hit100,
+    Hit50,
+    Miss,
+}
+
+impl ManiaHitResult {
+    /// The base score value of this judgement.
+    pub fn score_value(&self) -> u32 {
+        use ManiaHitResult::*;
+        match self {
+            Max => 320,
+            Hit300 => 300,
+            Hit200 => 200,
+            Hit100 => 100,
+            Hit50 => 50,
+            Miss => 0,
+        }
+    }
+
+    pub fn is_hit(&self) -> bool {
+        !matches!(self, Self::Miss)
+    }
+}
+
+/// The maximum absolute hit error in milliseconds for each judgement in osu!mania.
+#[derive(Debug, Clone, Copy)]
+pub struct ManiaHitWindows {
+    max: f64,
+    great: f64,
+    good: f64,
+    ok: f64,
+    meh: f64,
+    /// Presses which are earlier than the 50 window but within this one count as a miss
+    miss: f64,
+}
+
+impl ManiaHitWindows {
+    /// The hit windows of osu!stable, which are fixed for converted beatmaps apart from a step at OD 4.
+    /// Hard Rock makes all windows 1.4 times smaller, Easy 1.4 times larger.
+    pub fn new(overall_difficulty: f32, convert: bool, mods: Mods) -> Self {
+        let od = overall_difficulty as f64;
+        let mut windows = if convert {
+            Self {
+                max: 16.0,
+                great: if od > 4.0 { 34.0 } else { 47.0 },
+                good: if od > 4.0 { 67.0 } else { 77.0 },
+                ok: 97.0,
+                meh: 121.0,
+                miss: 158.0,
+            }
+        } else {
+            Self {
+                max: 16.0,
+                great: 64.0 - 3.0 * od,
+                good: 97.0 - 3.0 * od,
+                ok: 127.0 - 3.0 * od,
+                meh: 151.0 - 3.0 * od,
+                miss: 188.0 - 3.0 * od,
+            }
+        };
+
+        let scale = if mods.contains(Mods::HARD_ROCK) {
+            1.0 / 1.4
+        } else if mods.contains(Mods::EASY) {
+            1.4
+        } else {
+            1.0
+        };
+        // osu!stable works with whole milliseconds
+        for window in [
+            &mut windows.max,
+            &mut windows.great,
+            &mut windows.good,
+            &mut windows.ok,
+            &mut windows.meh,
+            &mut windows.miss,
+        ] {
+            *window = (*window * scale).floor();
+        }
+        windows
+    }
+
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    pub fn great(&self) -> f64 {
+        self.great
+    }
+
+    pub fn good(&self) -> f64 {
+        self.good
+    }
+
+    pub fn ok(&self) -> f64 {
+        self.ok
+    }
+
+    pub fn meh(&self) -> f64 {
+        self.meh
+    }
+
+    pub fn miss(&self) -> f64 {
+        self.miss
+    }
+
+    /// Determines the judgement of a note hit with the given error, or `None` if it is outside of the 50 window.
+    fn judge(&self, hit_error: f64) -> Option<ManiaHitResult> {
+        let hit_error = hit_error.abs();
+        [
+            (self.max, ManiaHitResult::Max),
+            (self.great, ManiaHitResult::Hit300),
+            (self.good, ManiaHitResult::Hit200),
+            (self.ok, ManiaHitResult::Hit100),
+            (self.meh, ManiaHitResult::Hit50),
+        ]
+        .into_iter()
+        .find(|&(window, _)| hit_error <= window)
+        .map(|(_, result)| result)
+    }
+
+    /// Judges a hold note from the errors of pressing its head and releasing its tail, like osu!stable does.
+    /// The release is more lenient than the press.
+    fn judge_hold(&self, head_error: f64, release_error: f64) -> ManiaHitResult {
+        let head = head_error.abs();
+        let combined = head + release_error.abs();
+        if head <= self.max * 1.2 && combined <= self.max * 2.4 {
+            ManiaHitResult::Max
+        } else if head <= self.great * 1.1 && combined <= self.great * 2.2 {
+            ManiaHitResult::Hit300
+        } else if head <= self.good && combined <= self.good * 2.0 {
+            ManiaHitResult::Hit200
+        } else if head <= self.ok && combined <= self.ok * 2.0 {
+            ManiaHitResult::Hit100
+        } else {
+            ManiaHitResult::Hit50
+        }
+    }
+}
+
+/// A key being held in a single column, where the release is `None` if it is never let go.
+#[derive(Debug, Clone, Copy)]
+struct Press {
+    time: f64,
+    release: Option<f64>,
+}
+
+/// The judgement of a single note or hold note.
+#[derive(Debug, Clone, Copy)]
+pub struct ManiaJudgement {
+    object: ManiaObject,
+    result: ManiaHitResult,
+    hit_error: Option<f64>,
+    release_error: Option<f64>,
+}
+
+impl ManiaJudgement {
+    pub fn object(&self) -> ManiaObject {
+        self.object
+    }
+
+    pub fn result(&self) -> ManiaHitResult {
+        self.result
+    }
+
+    /// The difference between the time of the press and the note in milliseconds, negative if the press was early.
+    pub fn hit_error(&self) -> Option<f64> {
+        self.hit_error
+    }
+
+    /// The difference between the release and the end of a hold note in milliseconds, negative if it was early.
+    pub fn release_error(&self) -> Option<f64> {
+        self.release_error
+    }
+}
+
+/// The judgements of all objects of an osu!mania play, ordered by time.
+#[derive(Debug, Clone, Default)]
+pub struct ManiaJudgements {
+    judgements: Vec<ManiaJudgement>,
+}
+
+impl ManiaJudgements {
+    pub fn judgements(&self) -> &[ManiaJudgement] {
+        &self.judgements
+    }
+
+    /// The number of objects which received the given judgement.
+    pub fn count(&self, result: ManiaHitResult) -> usize {
+        self.judgements
+            .iter()
+            .filter(|j| j.result == result)
+            .count()
+    }
+
+    /// The number of rainbow 300s, which osu!stable stores as gekis.
+    pub fn n_geki(&self) -> usize {
+        self.count(ManiaHitResult::Max)
+    }
+
+    pub fn n_300(&self) -> usize {
+        self.count(ManiaHitResult::Hit300)
+    }
+
+    /// The number of 200s, which osu!stable stores as katus.
+    pub fn n_katu(&self) -> usize {
+        self.count(ManiaHitResult::Hit200)
+    }
+
+    pub fn n_100(&self) -> usize {
+        self.count(ManiaHitResult::Hit100)
+    }
+
+    pub fn n_50(&self) -> usize {
+        self.count(ManiaHitResult::Hit50)
+    }
+
+    pub fn n_miss(&self) -> usize {
+        self.count(ManiaHitResult::Miss)
+    }
+
+    /// The highest combo, where every note and hold note counts once.
+    pub fn max_combo(&self) -> usize {
+        let mut combo = 0;
+        let mut max_combo = 0;
+        for judgement in &self.judgements {
+            combo = if judgement.result.is_hit() {
+                combo + 1
+            } else {
+                0
+            };
+            max_combo = max_combo.max(combo);
+        }
+        max_combo
+    }
+}
+
+/// Simulates the judgement of every object in osu!mania given the replay played on the (unmodified) beatmap.
+///
+/// The x coordinate of a replay frame holds the pressed columns as a bitmask. Every press judges the next note
+/// in its column, where pressing too early results in a miss. Hold notes released early only award a 50,
+/// holding past the 50 window of the tail counts like releasing at its edge.
+pub fn judge(map: &Beatmap, replay: &Replay) -> ManiaJudgements {
+    let windows = ManiaHitWindows::new(
+        map.difficulty().overall_difficulty(),
+        !map.is_mania_map(),
+        replay.mods,
+    );
+    let key_count = map.mania_key_count(replay.mods);
+    let presses = presses(replay, key_count);
+    let mut next_press = vec![0; key_count];
+
+    let judgements = map
+        .mania_objects(replay.mods, replay.seed)
+        .into_iter()
+        .map(|object| {
+            let column = object.column();
+            let presses = &presses[column];
+            let next = &mut next_press[column];
+            let start = object.start_time();
+
+            // Presses too early for this note did not hit anything
+            while *next < presses.len() && presses[*next].time < start - windows.miss {
+                *next += 1;
+            }
+
+            let mut judgement = ManiaJudgement {
+                object,
+                result: ManiaHitResult::Miss,
+                hit_error: None,
+                release_error: None,
+            };
+            let Some(press) = presses
+                .get(*next)
+                .filter(|press| press.time <= start + windows.meh)
+            else {
+                return judgement;
+            };
+            *next += 1;
+
+            let hit_error = press.time - start;
+            judgement.hit_error = Some(hit_error);
+            let Some(result) = windows.judge(hit_error) else {
+                return judgement;
+            };
+
+            judgement.result = match object {
+                ManiaObject::Note { .. } => result,
+                ManiaObject::Hold { end, .. } => {
+                    let release_error = press
+                        .release
+                        .map_or(windows.meh, |release| release - end)
+                        .min(windows.meh);
+                    judgement.release_error = Some(release_error);
+                    if release_error < -windows.meh {
+                        ManiaHitResult::Hit50
+                    } else {
+                        windows.judge_hold(hit_error, release_error)
+                    }
+                }
+            };
+            judgement
+        })
+        .collect();
+
+    ManiaJudgements { judgements }
+}
+
+/// Collects the presses of every column, in order.
+fn presses(replay: &Replay, key_count: usize) -> Vec<Vec<Press>> {
+    let mut presses = vec![Vec::<Press>::new(); key_count];
+    let mut previous = 0u32;
+    for (time, frame) in replay.timed_frames() {
+        let keys = frame.x().max(0.0) as u32;
+        for (column, presses) in presses.iter_mut().enumerate() {
+            let bit = 1 << column;
+            if keys & bit != 0 && previous & bit == 0 {
+                presses.push(Press {
+                    time: time as f64,
+                    release: None,
+                });
+            } else if keys & bit == 0 && previous & bit != 0 {
+                if let Some(press) = presses.last_mut() {
+                    press.release = Some(time as f64);
+                }
+            }
+        }
+        previous = keys;
+    }
+    presses
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        autoplay::Autoplay,
+        osu_data::GameMode,
+        replay::InputKeys,
+        test_data::{self, TestMap},
+    };
+
+    use super::*;
+
+    /// An osu!mania beatmap with 4 columns, where OD 5 gives the windows 16/49/82/112/136 and a miss window of 173ms.
+    fn mania_map(hit_objects: &[&str]) -> Beatmap {
+        TestMap {
+            mode: 3,
+            ..TestMap::new(hit_objects)
+        }
+        .build()
+    }
+
+    /// A replay holding the given column from the start until the end of each press.
+    fn replay(presses: &[(i64, Option<i64>, usize)]) -> Replay {
+        let mut times = presses
+            .iter()
+            .flat_map(|&(start, end, _)| [Some(start), end])
+            .flatten()
+            .collect::<Vec<_>>();
+        times.sort_unstable();
+        times.dedup();
+        let frames = times
+            .into_iter()
+            .map(|time| {
+                let columns = presses
+                    .iter()
+                    .filter(|&&(start, end, _)| start <= time && end.is_none_or(|end| time < end))
+                    .fold(0, |held, &(_, _, column)| held | 1 << column);
+                (time, columns as f32, 0.0, InputKeys::empty())
+            })
+            .collect::<Vec<_>>();
+        test_data::replay(GameMode::Mania, Mods::empty(), &frames)
+    }
+
+    fn results(judgements: &ManiaJudgements) -> Vec<ManiaHitResult> {
+        judgements
+            .judgements()
+            .iter()
+            .map(ManiaJudgement::result)
+            .collect()
+    }
+
+    #[test]
+    fn notes() {
+        let map = mania_map(&[
+            "64,192,1000,1,0",
+            "64,192,2000,1,0",
+            "64,192,3000,1,0",
+            "64,192,4000,1,0",
+            "64,192,5000,1,0",
+            "64,192,6000,1,0",
+            "64,192,7000,1,0",
+        ]);
+        let replay = replay(&[
+            (1000, Some(1050), 0),
+            (2030, Some(2080), 0),
+            (2930, Some(2980), 0),
+            (4100, Some(4150), 0),
+            (5130, Some(5180), 0),
+            // Too early, which uses up the press
+            (5850, Some(5900), 0),
+            // Too early to count at all
+            (6800, Some(6850), 0),
+        ]);
+
+        let judgements = judge(&map, &replay);
+        use ManiaHitResult::*;
+        assert_eq!(
+            results(&judgements),
+            [Max, Hit300, Hit200, Hit100, Hit50, Miss, Miss]
+        );
+        assert_eq!(judgements.judgements()[5].hit_error(), Some(-150.0));
+        assert_eq!(judgements.judgements()[6].hit_error(), None);
+        assert_eq!(judgements.max_combo(), 5);
+    }
+
+    #[test]
+    fn columns() {
+        let map = mania_map(&["64,192,1000,1,0", "192,192,1000,1,0", "448,192,1000,1,0"]);
+        // Columns 0 and 2 are pressed, so the note in column 1 and the one in column 3 are missed
+        let judgements = judge(
+            &map,
+            &replay(&[(1000, Some(1050), 0), (1000, Some(1050), 2)]),
+        );
+        let by_column = judgements
+            .judgements()
+            .iter()
+            .map(|j| (j.object().column(), j.result()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            by_column,
+            [
+                (0, ManiaHitResult::Max),
+                (1, ManiaHitResult::Miss),
+                (3, ManiaHitResult::Miss)
+            ]
+        );
+    }
+
+    #[test]
+    fn hold_notes() {
+        let map = mania_map(&[
+            "64,192,1000,128,0,2000:0:0:0:0:",
+            "192,192,1000,128,0,2000:0:0:0:0:",
+            "320,192,1000,128,0,2000:0:0:0:0:",
+            "448,192,1000,128,0,2000:0:0:0:0:",
+            "64,192,3000,128,0,4000:0:0:0:0:",
+        ]);
+        let replay = replay(&[
+            (1000, Some(2000), 0),
+            // Head and release 40ms late
+            (1040, Some(2040), 1),
+            // Released far too early
+            (1000, Some(1500), 2),
+            // Never released
+            (1000, None, 3),
+        ]);
+
+        let judgements = judge(&map, &replay);
+        use ManiaHitResult::*;
+        assert_eq!(results(&judgements), [Max, Hit300, Hit50, Hit200, Miss]);
+        let release_errors = judgements
+            .judgements()
+            .iter()
+            .map(ManiaJudgement::release_error)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            release_errors,
+            [Some(0.0), Some(40.0), Some(-500.0), Some(136.0), None]
+        );
+    }
+
+    #[test]
+    fn autoplay_is_perfect() {
+        let map = mania_map(&[
+            "64,192,1000,1,0",
+            "192,192,1000,128,0,1500:0:0:0:0:",
+            "192,192,1600,1,0",
+            "448,192,1700,128,0,2500:0:0:0:0:",
+        ]);
+        for mods in [Mods::empty(), Mods::MIRROR, Mods::RANDOM] {
+            let replay = Autoplay::new(GameMode::Mania, mods).generate(&map);
+            let judgements = judge(&map, &replay);
+            assert_eq!(judgements.n_geki(), 4, "{mods:?}");
+        }
+
+        for (path, _, map) in test_data::replays_with_maps() {
+            for mods in [Mods::empty(), Mods::KEY4 | Mods::RANDOM] {
+                let replay = Autoplay::new(GameMode::Mania, mods).generate(&map);
+                let judgements = judge(&map, &replay);
+                assert_eq!(
+                    judgements.n_geki(),
+                    judgements.judgements().len(),
+                    "{}",
+                    path.display()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use crate::map::hit_object::HitObject;
+
+pub mod catch;
+pub mod mania;
+pub mod standard;
+pub mod taiko;
+
+/// The judgement an object receives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HitResult {
+    Hit300,
+    Hit100,
+    Hit50,
+    Miss,
+}
+
+impl HitResult {
+    /// The base score value of this judgement.
+    pub fn score_value(&self) -> u32 {
+        use HitResult::*;
+        match self {
+            Hit300 => 300,
+            Hit100 => 100,
+            Hit50 => 50,
+            Miss => 0,
+        }
+    }
+
+    pub fn is_hit(&self) -> bool {
+        !matches!(self, Self::Miss)
+    }
+}
+
+/// A part of a slider which is judged on its own and contributes to the slider's judgement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SliderPart {
+    Head,
+    Tick,
+    Repeat,
+    End,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SliderPartJudgement {
+    kind: SliderPart,
+    time: f64,
+    hit: bool,
+}
+
+impl SliderPartJudgement {
+    pub fn kind(&self) -> SliderPart {
+        self.kind
+    }
+
+    /// The time at which this part was judged.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn hit(&self) -> bool {
+        self.hit
+    }
+}
+
+/// Object type specific information about how a judgement came to be.
+#[derive(Debug, Clone)]
+pub enum JudgementDetails {
+    Circle,
+    Slider(Vec<SliderPartJudgement>), // ordered by time
+    Spinner {
+        rotations: f64,
+        required_rotations: f64,
+    },
+}
+
+/// The judgement of a single hit object.
+#[derive(Debug, Clone)]
+pub struct ObjectJudgement {
+    object_index: usize,
+    time: f64,
+    result: HitResult,
+    hit_error: Option<f64>,
+    cursor_offset: Option<(f32, f32)>,
+    details: JudgementDetails,
+}
+
+impl ObjectJudgement {
+    /// The index of the judged object in the beatmap's hit objects.
+    pub fn object_index(&self) -> usize {
+        self.object_index
+    }
+
+    /// The time at which the object was judged. For sliders and spinners this is their end time.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn result(&self) -> HitResult {
+        self.result
+    }
+
+    /// The difference between the time of the click and the object's timestamp in milliseconds,
+    /// negative if the click was early. Only present for circles and slider heads which were clicked.
+    pub fn hit_error(&self) -> Option<f64> {
+        self.hit_error
+    }
+
+    /// The position of the cursor relative to the object's center when it was clicked.
+    pub fn cursor_offset(&self) -> Option<(f32, f32)> {
+        self.cursor_offset
+    }
+
+    pub fn details(&self) -> &JudgementDetails {
+        &self.details
+    }
+}
+
+/// The bonus awarded for the last object of a combo section (the objects between two new combos).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SectionBonus {
+    /// Every object in the section was a 300
+    Geki,
+    /// No object in the section was a 50 or a miss
+    Katu,
+}
+
+/// Something which was judged during a play, in the order they happen.
+#[derive(Debug, Clone, Copy)]
+pub enum JudgementEvent {
+    SliderPart(SliderPartJudgement),
+    /// The final judgement of an object, given by its index
+    Object(usize),
+}
+
+/// The judgements of all objects in a beatmap, ordered by object.
+#[derive(Debug, Clone, Default)]
+pub struct Judgements {
+    objects: Vec<ObjectJudgement>,
+}
+
+impl Judgements {
+    pub fn objects(&self) -> &[ObjectJudgement] {
+        &self.objects
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, ObjectJudgement> {
+        self.objects.iter()
+    }
+
+    /// The number of objects which received the given judgement.
+    pub fn count(&self, result: HitResult) -> usize {
+        self.objects.iter().filter(|j| j.result == result).count()
+    }
+
+    pub fn n_300(&self) -> usize {
+        self.count(HitResult::Hit300)
+    }
+
+    pub fn n_100(&self) -> usize {
+        self.count(HitResult::Hit100)
+    }
+
+    pub fn n_50(&self) -> usize {
+        self.count(HitResult::Hit50)
+    }
+
+    pub fn n_miss(&self) -> usize {
+        self.count(HitResult::Miss)
+    }
+
+    /// All judged objects and slider parts ordered by the time they were judged.
+    /// The parts of a slider always come before the slider's final judgement.
+    pub fn events(&self) -> Vec<(f64, JudgementEvent)> {
+        let mut events = vec![];
+        for (i, judgement) in self.objects.iter().enumerate() {
+            if let JudgementDetails::Slider(parts) = &judgement.details {
+                events.extend(
+                    parts
+                        .iter()
+                        .map(|&part| (part.time, JudgementEvent::SliderPart(part))),
+                );
+            }
+            events.push((judgement.time, JudgementEvent::Object(i)));
+        }
+        // A stable sort keeps the parts of a slider before its final judgement
+        events.sort_by(|a, b| a.0.total_cmp(&b.0));
+        events
+    }
+
+    /// The bonus awarded by each object, which can only be present for the last object of a combo section.
+    /// `objects` are the hit objects of the beatmap these judgements belong to.
+    pub fn section_bonuses(&self, objects: &[HitObject]) -> Vec<Option<SectionBonus>> {
+        let mut bonuses = vec![None; self.objects.len()];
+        let mut section_start = 0;
+        for i in 0..self.objects.len() {
+            // Spinners always form a combo section on their own
+            let ends_section = objects.get(i + 1).is_none_or(|next| {
+                next.new_combo() || next.is_spinner() || objects[i].is_spinner()
+            });
+            if !ends_section {
+                continue;
+            }
+
+            let section = &self.objects[section_start..=i];
+            bonuses[i] = if section.iter().all(|j| j.result == HitResult::Hit300) {
+                Some(SectionBonus::Geki)
+            } else if section
+                .iter()
+                .all(|j| matches!(j.result, HitResult::Hit300 | HitResult::Hit100))
+            {
+                Some(SectionBonus::Katu)
+            } else {
+                None
+            };
+            section_start = i + 1;
+        }
+        bonuses
+    }
+}
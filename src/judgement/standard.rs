@@ -0,0 +1,550 @@
+use std::f64::consts::PI;
+
+use crate::{
+    map::{
+        difficulty::{difficulty_range, HitWindows},
+        hit_object::{HitObject, HitObjectData, SliderData},
+        slider_path::distance,
+        Beatmap,
+    },
+    osu_data::{GameMode, PLAYFIELD_HEIGHT, PLAYFIELD_WIDTH},
+    replay::{Mods, Replay},
+};
+
+use super::*;
+
+/// Clicks earlier than this many milliseconds before an object are ignored by it.
+pub(crate) const HITTABLE_RANGE: f64 = 400.0;
+/// The radius of the follow circle relative to the circle radius while a slider is being tracked.
+pub(crate) const FOLLOW_CIRCLE_SCALE: f32 = 2.4;
+/// The slider end is checked this many milliseconds before the slider actually ends.
+pub(crate) const SLIDER_END_LENIENCY: f64 = 36.0;
+/// Ticks closer than this many milliseconds to the end of a span are left out.
+const TICK_MIN_DISTANCE_FROM_END: f64 = 10.0;
+/// The fastest a spinner can be spun in radians per millisecond (about 477 rotations per minute).
+const MAX_SPIN_RATE: f64 = 0.05;
+
+/// The state of the input at a single replay frame.
+#[derive(Debug, Clone, Copy)]
+struct Frame {
+    time: f64,
+    position: (f32, f32),
+    left: bool,
+    right: bool,
+}
+
+impl Frame {
+    fn key_down(&self) -> bool {
+        self.left || self.right
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Click {
+    time: f64,
+    position: (f32, f32),
+}
+
+/// The judgement of a circle or slider head.
+#[derive(Debug, Clone, Copy)]
+struct HeadJudgement {
+    time: f64,
+    result: HitResult,
+    hit_error: Option<f64>,
+    cursor_offset: Option<(f32, f32)>,
+}
+
+/// Simulates the judgement of every object in an osu!standard beatmap given the replay played on it.
+///
+/// This replicates osu!stable's rules: circles and slider heads can only be hit in order (note lock),
+/// sliders are judged by the fraction of their head, ticks, repeats and end which were hit,
+/// and spinners by the number of rotations.
+pub fn judge(map: &Beatmap, replay: &Replay) -> Result<Judgements, &'static str> {
+    if !matches!(replay.mode, GameMode::Standard) {
+        return Err("Only osu!standard replays can be judged on osu!standard beatmaps");
+    }
+
+    let difficulty = map.difficulty().for_replay(replay);
+    let map = map.apply_mods_in_song_time(replay.mods);
+    let objects = map.hit_objects();
+    let radius = difficulty.circle_radius();
+    let hit_windows = difficulty.hit_windows();
+
+    let frames = replay
+        .timed_frames()
+        .map(|(time, frame)| Frame {
+            time: time as f64,
+            position: frame.position(),
+            left: frame.input_keys().left(),
+            right: frame.input_keys().right(),
+        })
+        .collect::<Vec<_>>();
+
+    let heads = judge_heads(objects, &frames, radius, &hit_windows);
+
+    let objects = objects
+        .iter()
+        .enumerate()
+        .map(|(i, obj)| match obj.object_data() {
+            HitObjectData::Circle | HitObjectData::Hold(_) => {
+                let head = heads[i].unwrap();
+                ObjectJudgement {
+                    object_index: i,
+                    time: head.time,
+                    result: head.result,
+                    hit_error: head.hit_error,
+                    cursor_offset: head.cursor_offset,
+                    details: JudgementDetails::Circle,
+                }
+            }
+            HitObjectData::Slider(slider) => {
+                let score_v2 = replay.mods.contains(Mods::SCORE_V2);
+                judge_slider(i, obj, slider, heads[i].unwrap(), &frames, radius, score_v2)
+            }
+            HitObjectData::Spinner(_) => {
+                let spins_per_second =
+                    spins_per_second(difficulty.difficulty().overall_difficulty());
+                judge_spinner(i, obj, &frames, spins_per_second)
+            }
+        })
+        .collect();
+
+    Ok(Judgements { objects })
+}
+
+/// The judgements of a play in which every object is hit perfectly, i.e. what an autoplay achieves.
+pub fn perfect(map: &Beatmap, mods: Mods) -> Judgements {
+    let difficulty = map.difficulty().with_mods(mods);
+    let map = map.apply_mods_in_song_time(mods);
+
+    let objects = map
+        .hit_objects()
+        .iter()
+        .enumerate()
+        .map(|(i, obj)| {
+            let start = obj.timestamp() as f64;
+            let details = match obj.object_data() {
+                HitObjectData::Circle | HitObjectData::Hold(_) => JudgementDetails::Circle,
+                HitObjectData::Slider(slider) => {
+                    let head = SliderPartJudgement {
+                        kind: SliderPart::Head,
+                        time: start,
+                        hit: true,
+                    };
+                    let parts = slider_checks(obj, slider).into_iter().map(|(time, kind)| {
+                        SliderPartJudgement {
+                            kind,
+                            time,
+                            hit: true,
+                        }
+                    });
+                    JudgementDetails::Slider(std::iter::once(head).chain(parts).collect())
+                }
+                HitObjectData::Spinner(_) => {
+                    let spins_per_second =
+                        spins_per_second(difficulty.difficulty().overall_difficulty());
+                    let required_rotations =
+                        ((obj.end_time() - start) / 1000.0 * spins_per_second).floor();
+                    JudgementDetails::Spinner {
+                        rotations: required_rotations,
+                        required_rotations,
+                    }
+                }
+            };
+
+            ObjectJudgement {
+                object_index: i,
+                time: if obj.is_circle() {
+                    start
+                } else {
+                    obj.end_time()
+                },
+                result: HitResult::Hit300,
+                hit_error: (!obj.is_spinner()).then_some(0.0),
+                cursor_offset: (!obj.is_spinner()).then_some((0.0, 0.0)),
+                details,
+            }
+        })
+        .collect();
+
+    Judgements { objects }
+}
+
+/// Determines the judgement for a hit error, or `None` if it is outside of all hit windows.
+/// Hit errors are compared against the whole milliseconds of each window like osu!stable does.
+fn judge_hit_error(hit_error: f64, hit_windows: &HitWindows) -> Option<HitResult> {
+    let hit_error = hit_error.abs();
+    if hit_error < hit_windows.window_300().floor() {
+        Some(HitResult::Hit300)
+    } else if hit_error < hit_windows.window_100().floor() {
+        Some(HitResult::Hit100)
+    } else if hit_error < hit_windows.window_50().floor() {
+        Some(HitResult::Hit50)
+    } else {
+        None
+    }
+}
+
+/// Extracts every press of the left or right button.
+fn clicks(frames: &[Frame]) -> Vec<Click> {
+    let mut clicks = vec![];
+    let (mut left, mut right) = (false, false);
+    for frame in frames {
+        if frame.left && !left {
+            clicks.push(Click {
+                time: frame.time,
+                position: frame.position,
+            });
+        }
+        if frame.right && !right {
+            clicks.push(Click {
+                time: frame.time,
+                position: frame.position,
+            });
+        }
+        left = frame.left;
+        right = frame.right;
+    }
+    clicks
+}
+
+/// Judges all circles and slider heads. The result contains a judgement for every circle and slider.
+fn judge_heads(
+    objects: &[HitObject],
+    frames: &[Frame],
+    radius: f32,
+    hit_windows: &HitWindows,
+) -> Vec<Option<HeadJudgement>> {
+    let mut judgements: Vec<Option<HeadJudgement>> = vec![None; objects.len()];
+    let clickable = objects
+        .iter()
+        .enumerate()
+        .filter(|(_, obj)| !obj.is_spinner())
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let last_hittable = |i: usize| objects[i].timestamp() as f64 + hit_windows.window_50().floor();
+
+    // Objects before this index in `clickable` have all been judged
+    let mut first_pending = 0;
+
+    let miss_expired = |judgements: &mut [Option<HeadJudgement>], time: f64| {
+        for &i in &clickable {
+            if judgements[i].is_none() && last_hittable(i) <= time {
+                judgements[i] = Some(HeadJudgement {
+                    time: last_hittable(i),
+                    result: HitResult::Miss,
+                    hit_error: None,
+                    cursor_offset: None,
+                });
+            }
+            if objects[i].timestamp() as f64 > time {
+                break;
+            }
+        }
+    };
+
+    for click in clicks(frames) {
+        miss_expired(&mut judgements, click.time);
+        while first_pending < clickable.len() && judgements[clickable[first_pending]].is_some() {
+            first_pending += 1;
+        }
+
+        // The click goes to the earliest object under the cursor
+        let target = clickable[first_pending..]
+            .iter()
+            .copied()
+            .filter(|&i| judgements[i].is_none())
+            .take_while(|&i| objects[i].timestamp() as f64 - HITTABLE_RANGE <= click.time)
+            .find(|&i| distance(click.position, objects[i].stacked_position(radius)) <= radius);
+
+        let target = match target {
+            Some(target) => target,
+            None => continue,
+        };
+
+        // Note lock: the click is ignored as long as an earlier object can still be hit
+        if clickable[first_pending..]
+            .iter()
+            .take_while(|&&i| i < target)
+            .any(|&i| judgements[i].is_none())
+        {
+            continue;
+        }
+
+        let object = &objects[target];
+        let hit_error = click.time - object.timestamp() as f64;
+        let (x, y) = object.stacked_position(radius);
+        judgements[target] = Some(HeadJudgement {
+            time: click.time,
+            result: judge_hit_error(hit_error, hit_windows).unwrap_or(HitResult::Miss),
+            hit_error: Some(hit_error),
+            cursor_offset: Some((click.position.0 - x, click.position.1 - y)),
+        });
+    }
+    miss_expired(&mut judgements, f64::INFINITY);
+
+    judgements
+}
+
+/// Calculates the times and kinds of all ticks, repeats and the end of a slider which are checked for tracking.
+pub(crate) fn slider_checks(obj: &HitObject, slider: &SliderData) -> Vec<(f64, SliderPart)> {
+    let start = obj.timestamp() as f64;
+    let span_duration = slider.span_duration();
+    let length = slider.length();
+    let mut checks = vec![];
+
+    let min_distance_from_end = slider.velocity() * TICK_MIN_DISTANCE_FROM_END;
+    let tick_distance = slider.tick_distance().min(length);
+
+    for span in 0..slides_of(slider) {
+        let span_start = start + span as f64 * span_duration;
+        let reversed = span % 2 == 1;
+
+        if tick_distance > 0.0 {
+            let mut d = tick_distance;
+            while d < length - min_distance_from_end {
+                let progress = d / length;
+                let time_progress = if reversed { 1.0 - progress } else { progress };
+                checks.push((span_start + time_progress * span_duration, SliderPart::Tick));
+                d += tick_distance;
+            }
+        }
+
+        if span + 1 < slides_of(slider) {
+            checks.push((span_start + span_duration, SliderPart::Repeat));
+        }
+    }
+
+    // osu!stable checks the slider end a bit early to make it more lenient
+    let end = obj.end_time();
+    checks.push((
+        (end - SLIDER_END_LENIENCY).max(start + slider.duration() / 2.0),
+        SliderPart::End,
+    ));
+
+    // osu!stable works with whole milliseconds
+    checks
+        .iter_mut()
+        .for_each(|check| check.0 = check.0.floor());
+    checks.sort_by(|a, b| a.0.total_cmp(&b.0));
+    checks
+}
+
+fn slides_of(slider: &SliderData) -> usize {
+    slider.slides().max(1)
+}
+
+fn judge_slider(
+    index: usize,
+    obj: &HitObject,
+    slider: &SliderData,
+    head: HeadJudgement,
+    frames: &[Frame],
+    radius: f32,
+    score_v2: bool,
+) -> ObjectJudgement {
+    let start = obj.timestamp() as f64;
+    let end = obj.end_time();
+    let (offset_x, offset_y) = obj.stack_offset(radius);
+    let checks = slider_checks(obj, slider);
+
+    let mut parts = vec![SliderPartJudgement {
+        kind: SliderPart::Head,
+        time: head.time,
+        hit: head.result.is_hit(),
+    }];
+
+    let mut tracking = false;
+    let mut next_check = 0;
+    let first_frame = frames.partition_point(|frame| frame.time < start);
+    for frame in &frames[first_frame..] {
+        if next_check >= checks.len() {
+            break;
+        }
+
+        let (x, y) = slider.position_at(frame.time.min(end) - start);
+        let ball = (x + offset_x, y + offset_y);
+        let follow_radius = if tracking {
+            radius * FOLLOW_CIRCLE_SCALE
+        } else {
+            radius
+        };
+        tracking = frame.key_down() && distance(frame.position, ball) <= follow_radius;
+
+        while next_check < checks.len() && checks[next_check].0 <= frame.time {
+            let (time, kind) = checks[next_check];
+            parts.push(SliderPartJudgement {
+                kind,
+                time,
+                hit: tracking,
+            });
+            next_check += 1;
+        }
+    }
+    // Checks after the last frame can't have been hit
+    for &(time, kind) in &checks[next_check..] {
+        parts.push(SliderPartJudgement {
+            kind,
+            time,
+            hit: false,
+        });
+    }
+
+    let hits = parts.iter().filter(|part| part.hit).count();
+    let mut result = if hits == parts.len() {
+        HitResult::Hit300
+    } else if hits * 2 >= parts.len() {
+        HitResult::Hit100
+    } else if hits > 0 {
+        HitResult::Hit50
+    } else {
+        HitResult::Miss
+    };
+
+    // With ScoreV2 the accuracy of the slider head limits the slider's judgement,
+    // where a missed head still allows a 50
+    if score_v2 && result.is_hit() {
+        let limit = if head.result.is_hit() {
+            head.result
+        } else {
+            HitResult::Hit50
+        };
+        if limit.score_value() < result.score_value() {
+            result = limit;
+        }
+    }
+
+    ObjectJudgement {
+        object_index: index,
+        time: end,
+        result,
+        hit_error: head.hit_error,
+        cursor_offset: head.cursor_offset,
+        details: JudgementDetails::Slider(parts),
+    }
+}
+
+/// The number of rotations per second needed to clear a spinner.
+fn spins_per_second(overall_difficulty: f32) -> f64 {
+    difficulty_range(overall_difficulty as f64, 1.5, 2.5, 3.75)
+}
+
+fn judge_spinner(
+    index: usize,
+    obj: &HitObject,
+    frames: &[Frame],
+    spins_per_second: f64,
+) -> ObjectJudgement {
+    let start = obj.timestamp() as f64;
+    let end = obj.end_time();
+    let required_rotations = ((end - start) / 1000.0 * spins_per_second).floor();
+    let center = (PLAYFIELD_WIDTH / 2.0, PLAYFIELD_HEIGHT / 2.0);
+    let angle = |(x, y): (f32, f32)| ((y - center.1) as f64).atan2((x - center.0) as f64);
+
+    let mut total_angle = 0.0;
+    let first_frame = frames.partition_point(|frame| frame.time <= start).max(1);
+    for pair in frames[first_frame - 1..].windows(2) {
+        let (prev, frame) = (pair[0], pair[1]);
+        if frame.time > end {
+            break;
+        }
+        if !frame.key_down() {
+            continue;
+        }
+
+        let mut delta = angle(frame.position) - angle(prev.position);
+        if delta > PI {
+            delta -= 2.0 * PI;
+        } else if delta < -PI {
+            delta += 2.0 * PI;
+        }
+        let max_delta = MAX_SPIN_RATE * (frame.time - prev.time.max(start));
+        total_angle += delta.clamp(-max_delta, max_delta);
+    }
+
+    let rotations = total_angle.abs() / (2.0 * PI);
+    let progress = if required_rotations > 0.0 {
+        rotations / required_rotations
+    } else {
+        1.0
+    };
+    let result = if progress >= 1.0 {
+        HitResult::Hit300
+    } else if progress > 0.9 {
+        HitResult::Hit100
+    } else if progress > 0.75 {
+        HitResult::Hit50
+    } else {
+        HitResult::Miss
+    };
+
+    ObjectJudgement {
+        object_index: index,
+        time: end,
+        result,
+        hit_error: None,
+        cursor_offset: None,
+        details: JudgementDetails::Spinner {
+            rotations,
+            required_rotations,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_data;
+
+    use super::*;
+
+    #[test]
+    fn judgements_match_sample_replays() {
+        let samples = test_data::replays_with_maps();
+        assert!(!samples.is_empty());
+        for (path, replay, map) in samples {
+            let judgements = judge(&map, &replay).unwrap();
+            let counts = (
+                judgements.n_300(),
+                judgements.n_100(),
+                judgements.n_50(),
+                judgements.n_miss(),
+            );
+            let expected = (
+                replay.n_300 as usize,
+                replay.n_100 as usize,
+                replay.n_50 as usize,
+                replay.n_miss as usize,
+            );
+            assert_eq!(counts, expected, "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn judgements_of_tiferet() {
+        let (_, replay) = test_data::replays()
+            .into_iter()
+            .find(|(_, replay)| replay.player_name == "Mitalun" && replay.n_300 == 788)
+            .unwrap();
+        let map = test_data::map_of(&replay).unwrap();
+        let judgements = judge(&map, &replay).unwrap();
+        assert_eq!(judgements.n_300(), 788);
+        assert_eq!(judgements.n_100(), 39);
+        assert_eq!(judgements.n_50(), 1);
+        assert_eq!(judgements.n_miss(), 1);
+    }
+
+    #[test]
+    fn perfect_judgements() {
+        for (_, replay, map) in test_data::replays_with_maps() {
+            let judgements = perfect(&map, replay.mods);
+            assert_eq!(judgements.n_300(), map.hit_objects().len());
+        }
+    }
+
+    #[test]
+    fn judging_other_modes_fails() {
+        let (_, mut replay, map) = test_data::replays_with_maps().remove(0);
+        replay.mode = GameMode::Taiko;
+        assert!(judge(&map, &replay).is_err());
+    }
+}
@@ -0,0 +1,538 @@
+use crate::{
+    map::{
+        difficulty::difficulty_range,
+        taiko::{TaikoHitKind, TaikoObject},
+        Beatmap,
+    },
+    replay::{InputKeys, Replay},
+};
+
+use super::HitResult;
+
+/// Both keys of a big note's colour need to be pressed within this many milliseconds for a strong hit.
+const STRONG_HIT_WINDOW: f64 = 30.0;
+
+/// The maximum absolute hit error in milliseconds for each judgement of a note in osu!taiko.
+#[derive(Debug, Clone, Copy)]
+struct TaikoHitWindows {
+    great: f64,
+    good: f64,
+    /// Hits which are earlier than the good window but within this one count as a miss
+    miss: f64,
+}
+
+impl TaikoHitWindows {
+    fn from_overall_difficulty(overall_difficulty: f32) -> Self {
+        let od = overall_difficulty as f64;
+        Self {
+            great: difficulty_range(od, 50.0, 35.0, 20.0),
+            good: difficulty_range(od, 120.0, 80.0, 50.0),
+            miss: difficulty_range(od, 135.0, 95.0, 70.0),
+        }
+    }
+
+    fn judge(&self, hit_error: f64) -> HitResult {
+        if hit_error.abs() <= self.great {
+            HitResult::Hit300
+        } else if hit_error.abs() <= self.good {
+            HitResult::Hit100
+        } else {
+            HitResult::Miss
+        }
+    }
+}
+
+/// A key going down, where the side is `false` for the left and `true` for the right key.
+#[derive(Debug, Clone, Copy)]
+struct Press {
+    time: f64,
+    kind: TaikoHitKind,
+    right: bool,
+}
+
+/// The judgement of a single don or kat.
+#[derive(Debug, Clone, Copy)]
+pub struct TaikoHitJudgement {
+    time: f64,
+    kind: TaikoHitKind,
+    big: bool,
+    result: HitResult,
+    hit_error: Option<f64>,
+    strong: bool,
+}
+
+impl TaikoHitJudgement {
+    /// The time of the note.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn kind(&self) -> TaikoHitKind {
+        self.kind
+    }
+
+    pub fn big(&self) -> bool {
+        self.big
+    }
+
+    /// The judgement of the note, where 100 stands for a "good".
+    pub fn result(&self) -> HitResult {
+        self.result
+    }
+
+    /// The difference between the time of the hit and the note in milliseconds, negative if the hit was early.
+    pub fn hit_error(&self) -> Option<f64> {
+        self.hit_error
+    }
+
+    /// Whether a big note was hit with both keys, which doubles its score.
+    pub fn strong(&self) -> bool {
+        self.strong
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DrumRollJudgement {
+    start: f64,
+    end: f64,
+    big: bool,
+    ticks: Vec<(f64, bool)>,
+}
+
+impl DrumRollJudgement {
+    pub fn start(&self) -> f64 {
+        self.start
+    }
+
+    pub fn end(&self) -> f64 {
+        self.end
+    }
+
+    pub fn big(&self) -> bool {
+        self.big
+    }
+
+    /// The time of every tick together with whether it was hit.
+    pub fn ticks(&self) -> &[(f64, bool)] {
+        &self.ticks
+    }
+
+    pub fn ticks_hit(&self) -> usize {
+        self.ticks.iter().filter(|(_, hit)| *hit).count()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SwellJudgement {
+    start: f64,
+    end: f64,
+    required_hits: usize,
+    hits: Vec<f64>,
+}
+
+impl SwellJudgement {
+    pub fn start(&self) -> f64 {
+        self.start
+    }
+
+    pub fn end(&self) -> f64 {
+        self.end
+    }
+
+    pub fn required_hits(&self) -> usize {
+        self.required_hits
+    }
+
+    /// The times of all hits which counted towards the swell.
+    pub fn hits(&self) -> &[f64] {
+        &self.hits
+    }
+
+    pub fn completed(&self) -> bool {
+        self.hits.len() >= self.required_hits
+    }
+}
+
+/// The judgements of all objects of an osu!taiko play.
+/// Drum rolls and swells only award bonus score and are kept apart from the notes.
+#[derive(Debug, Clone, Default)]
+pub struct TaikoJudgements {
+    hits: Vec<TaikoHitJudgement>,
+    drum_rolls: Vec<DrumRollJudgement>,
+    swells: Vec<SwellJudgement>,
+}
+
+impl TaikoJudgements {
+    /// The judgements of all dons and kats, ordered by time.
+    pub fn hits(&self) -> &[TaikoHitJudgement] {
+        &self.hits
+    }
+
+    pub fn drum_rolls(&self) -> &[DrumRollJudgement] {
+        &self.drum_rolls
+    }
+
+    pub fn swells(&self) -> &[SwellJudgement] {
+        &self.swells
+    }
+
+    /// The number of notes which received the given judgement.
+    pub fn count(&self, result: HitResult) -> usize {
+        self.hits.iter().filter(|j| j.result == result).count()
+    }
+
+    pub fn n_300(&self) -> usize {
+        self.count(HitResult::Hit300)
+    }
+
+    pub fn n_100(&self) -> usize {
+        self.count(HitResult::Hit100)
+    }
+
+    pub fn n_miss(&self) -> usize {
+        self.count(HitResult::Miss)
+    }
+
+    /// The number of big notes which were hit strongly as a 300.
+    pub fn n_geki(&self) -> usize {
+        self.hits
+            .iter()
+            .filter(|j| j.strong && j.result == HitResult::Hit300)
+            .count()
+    }
+
+    /// The number of big notes which were hit strongly as a 100.
+    pub fn n_katu(&self) -> usize {
+        self.hits
+            .iter()
+            .filter(|j| j.strong && j.result == HitResult::Hit100)
+            .count()
+    }
+}
+
+/// Simulates the judgement of every object in osu!taiko given the replay played on the (unmodified) beatmap.
+///
+/// Presses during drum rolls and swells only count towards those. Every other press judges the next note,
+/// where hitting the wrong colour or hitting too early results in a miss.
+pub fn judge(map: &Beatmap, replay: &Replay) -> TaikoJudgements {
+    let difficulty = map.difficulty().for_replay(replay);
+    let windows =
+        TaikoHitWindows::from_overall_difficulty(difficulty.difficulty().overall_difficulty());
+    let presses = presses(replay);
+    let mut used = vec![false; presses.len()];
+    let mut judgements = TaikoJudgements::default();
+
+    let objects = map.taiko_objects();
+    for object in &objects {
+        match *object {
+            TaikoObject::DrumRoll {
+                start, end, big, ..
+            } => {
+                let ticks = judge_drum_roll(object, &presses, &mut used);
+                judgements.drum_rolls.push(DrumRollJudgement {
+                    start,
+                    end,
+                    big,
+                    ticks,
+                });
+            }
+            TaikoObject::Swell {
+                start,
+                end,
+                required_hits,
+            } => judgements.swells.push(SwellJudgement {
+                start,
+                end,
+                required_hits,
+                hits: judge_swell(start, end, &presses, &mut used),
+            }),
+            TaikoObject::Hit { .. } => {}
+        }
+    }
+
+    let mut next_press = 0;
+    for object in objects {
+        let TaikoObject::Hit { time, kind, big } = object else {
+            continue;
+        };
+
+        // Presses too early for this note did not hit anything
+        while next_press < presses.len()
+            && (used[next_press] || presses[next_press].time < time - windows.miss)
+        {
+            next_press += 1;
+        }
+
+        let mut judgement = TaikoHitJudgement {
+            time,
+            kind,
+            big,
+            result: HitResult::Miss,
+            hit_error: None,
+            strong: false,
+        };
+        if let Some(press) = presses
+            .get(next_press)
+            .filter(|press| press.time <= time + windows.good)
+        {
+            used[next_press] = true;
+            let hit_error = press.time - time;
+            judgement.hit_error = Some(hit_error);
+            if press.kind == kind {
+                judgement.result = windows.judge(hit_error);
+            }
+
+            // A big note is hit strongly if the other key of the same colour is pressed at nearly the same time
+            if big && judgement.result.is_hit() {
+                let second = (next_press + 1..presses.len())
+                    .take_while(|&i| presses[i].time <= press.time + STRONG_HIT_WINDOW)
+                    .find(|&i| {
+                        !used[i] && presses[i].kind == kind && presses[i].right != press.right
+                    });
+                if let Some(i) = second {
+                    used[i] = true;
+                    judgement.strong = true;
+                }
+            }
+            next_press += 1;
+        }
+        judgements.hits.push(judgement);
+    }
+
+    judgements
+}
+
+/// Collects every key that went down, in order.
+/// The left and right centre keys (dons) are stored as M1 and K1, the rims (kats) as M2 and K2.
+fn presses(replay: &Replay) -> Vec<Press> {
+    let keys = [
+        (InputKeys::M1, TaikoHitKind::Don, false),
+        (InputKeys::K1, TaikoHitKind::Don, true),
+        (InputKeys::M2, TaikoHitKind::Kat, false),
+        (InputKeys::K2, TaikoHitKind::Kat, true),
+    ];
+
+    let mut presses = vec![];
+    let mut previous = InputKeys::empty();
+    for (time, frame) in replay.timed_frames() {
+        let pressed = frame.input_keys() - previous;
+        for (key, kind, right) in keys {
+            if pressed.contains(key) {
+                presses.push(Press {
+                    time: time as f64,
+                    kind,
+                    right,
+                });
+            }
+        }
+        previous = frame.input_keys();
+    }
+    presses
+}
+
+/// Every press hits at most one tick, which must be within half the tick spacing of the press.
+fn judge_drum_roll(
+    drum_roll: &TaikoObject,
+    presses: &[Press],
+    used: &mut [bool],
+) -> Vec<(f64, bool)> {
+    let TaikoObject::DrumRoll { tick_spacing, .. } = *drum_roll else {
+        return vec![];
+    };
+    let mut ticks = drum_roll
+        .ticks()
+        .into_iter()
+        .map(|time| (time, false))
+        .collect::<Vec<_>>();
+
+    let mut next_tick = 0;
+    for (press, used) in presses.iter().zip(used.iter_mut()) {
+        if *used {
+            continue;
+        }
+        while next_tick < ticks.len() && ticks[next_tick].0 + tick_spacing / 2.0 < press.time {
+            next_tick += 1;
+        }
+        match ticks.get_mut(next_tick) {
+            Some(tick) if tick.0 - tick_spacing / 2.0 <= press.time => {
+                tick.1 = true;
+                *used = true;
+                next_tick += 1;
+            }
+            Some(_) => {}
+            None => break,
+        }
+    }
+    ticks
+}
+
+/// Counts the presses during a swell which alternate between dons and kats.
+fn judge_swell(start: f64, end: f64, presses: &[Press], used: &mut [bool]) -> Vec<f64> {
+    let mut hits = vec![];
+    let mut last_kind = None;
+    for (press, used) in presses.iter().zip(used.iter_mut()) {
+        if *used || press.time < start || press.time > end {
+            continue;
+        }
+        *used = true;
+        if last_kind != Some(press.kind) {
+            hits.push(press.time);
+            last_kind = Some(press.kind);
+        }
+    }
+    hits
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        autoplay::Autoplay,
+        osu_data::GameMode,
+        replay::Mods,
+        test_data::{self, TestMap},
+    };
+
+    use super::*;
+
+    fn taiko_map(hit_objects: &[&str]) -> Beatmap {
+        TestMap {
+            mode: 1,
+            ..TestMap::new(hit_objects)
+        }
+        .build()
+    }
+
+    /// A replay pressing the given keys for 20ms each.
+    fn replay(presses: &[(i64, InputKeys)]) -> Replay {
+        let presses = presses
+            .iter()
+            .map(|&(time, keys)| (time, time + 20, keys))
+            .collect::<Vec<_>>();
+        let frames = test_data::key_frames((256.0, 192.0), &presses);
+        test_data::replay(GameMode::Taiko, Mods::empty(), &frames)
+    }
+
+    #[test]
+    fn notes() {
+        let map = taiko_map(&[
+            "256,192,1000,1,0",
+            "256,192,1500,1,2",
+            "256,192,2000,1,0",
+            "256,192,2500,1,4",
+            "256,192,3000,1,4",
+            "256,192,4000,1,0",
+        ]);
+        let replay = replay(&[
+            (1000, InputKeys::M1),
+            // Wrong colour
+            (1500, InputKeys::M1),
+            (2060, InputKeys::K1),
+            (2500, InputKeys::M1 | InputKeys::K1),
+            // Big note hit with a single key
+            (3010, InputKeys::M1),
+            // Too early
+            (3910, InputKeys::K1),
+        ]);
+
+        let judgements = judge(&map, &replay);
+        let results = judgements
+            .hits()
+            .iter()
+            .map(|j| (j.result(), j.strong()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            results,
+            [
+                (HitResult::Hit300, false),
+                (HitResult::Miss, false),
+                (HitResult::Hit100, false),
+                (HitResult::Hit300, true),
+                (HitResult::Hit300, false),
+                (HitResult::Miss, false),
+            ]
+        );
+        assert_eq!(judgements.hits()[5].hit_error(), Some(-90.0));
+        assert_eq!(judgements.n_geki(), 1);
+    }
+
+    #[test]
+    fn drum_roll_ticks() {
+        let map = taiko_map(&["256,192,1000,2,0,L|536:192,1,280", "256,192,3000,1,0"]);
+        let replay = replay(&[
+            (1000, InputKeys::M1),
+            (1130, InputKeys::K1),
+            (1250, InputKeys::M2),
+            (1700, InputKeys::K2),
+            (3000, InputKeys::M1),
+        ]);
+
+        let judgements = judge(&map, &replay);
+        let drum_roll = &judgements.drum_rolls()[0];
+        assert_eq!(drum_roll.ticks().len(), 9);
+        assert_eq!(drum_roll.ticks_hit(), 4);
+        // Presses during the drum roll do not take away from the notes after it
+        assert_eq!(judgements.n_300(), 1);
+    }
+
+    #[test]
+    fn swell_counts_alternating_hits() {
+        let map = taiko_map(&["256,192,1000,12,0,3000"]);
+        let judgements = judge(
+            &map,
+            &replay(&[
+                (1100, InputKeys::M1),
+                (1200, InputKeys::K1),
+                (1300, InputKeys::M2),
+                (3100, InputKeys::K1),
+            ]),
+        );
+        let swell = &judgements.swells()[0];
+        assert_eq!(swell.hits(), [1100.0, 1300.0]);
+        assert!(!swell.completed());
+
+        let presses = (0..20)
+            .map(|i| {
+                let keys = if i % 2 == 0 {
+                    InputKeys::M1
+                } else {
+                    InputKeys::M2
+                };
+                (1000 + i * 90, keys)
+            })
+            .collect::<Vec<_>>();
+        let judgements = judge(&map, &replay(&presses));
+        assert!(judgements.swells()[0].completed());
+    }
+
+    #[test]
+    fn autoplay_is_perfect() {
+        let map = taiko_map(&[
+            "256,192,1000,1,0",
+            "256,192,1500,1,2",
+            "256,192,2000,1,4",
+            "256,192,2500,1,6",
+            "256,192,3000,2,0,L|536:192,1,280",
+            "256,192,5000,12,0,7000",
+            "256,192,8000,1,0",
+        ]);
+        let replay = Autoplay::new(GameMode::Taiko, Mods::empty()).generate(&map);
+
+        let judgements = judge(&map, &replay);
+        assert_eq!(judgements.n_300(), 5);
+        assert_eq!(judgements.n_geki(), 2);
+        let drum_roll = &judgements.drum_rolls()[0];
+        assert_eq!(drum_roll.ticks_hit(), drum_roll.ticks().len());
+        assert!(judgements.swells()[0].completed());
+    }
+
+    #[test]
+    fn autoplay_of_converted_beatmaps_is_perfect() {
+        for (path, _, map) in test_data::replays_with_maps() {
+            let replay = Autoplay::new(GameMode::Taiko, Mods::empty()).generate(&map);
+            let judgements = judge(&map, &replay);
+            assert_eq!(judgements.n_miss(), 0, "{}", path.display());
+            assert_eq!(judgements.n_100(), 0, "{}", path.display());
+            assert!(judgements.swells().iter().all(SwellJudgement::completed));
+        }
+    }
+}
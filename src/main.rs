@@ -7,19 +7,50 @@ use crate::{
     map::Beatmap,
 };
 
+mod aim;
+mod anonymise;
+mod autoplay;
+mod combo_break;
+mod commands;
+mod cursor;
 mod file_reading;
+mod frame_timing;
+mod graph;
+mod health;
+mod hit_error;
+mod input;
+mod judgement;
 #[allow(unused)]
 mod map;
 mod osu_data;
+mod performance;
+mod random;
 #[allow(unused)]
 mod replay;
+mod scoring;
+mod signals;
+mod similarity;
+mod star_rating;
+#[cfg(test)]
+mod test_data;
+mod time_stamp;
 
 mod interpolation;
 
 fn main() -> std::io::Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if !args.is_empty() {
+        if let Err(e) = commands::run(&args) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     let replays = file_reading::get_available_replays()?;
     let maps = file_reading::get_available_maps()?;
-    let (_map, _replay) = choose_replay(replays.as_slice(), &maps).unwrap();
+    let (map, replay) = choose_replay(replays.as_slice(), &maps).unwrap();
+    commands::report(&map, &replay);
 
     Ok(())
 }
@@ -90,7 +121,7 @@ fn choose_replay(
             
         );
     }*/
-    let ((x, y), rad) = find_circle_rnd::<i8, f64>((-3, 0), (0, 3), (3, 0));
+    let ((x, y), rad) = find_circle::<i8, f64>((-3, 0), (0, 3), (3, 0));
     let start = (3f64, 0f64);
     let end = (-3f64, 0f64);
 
@@ -0,0 +1,62 @@
+use std::str::FromStr;
+
+/// A period of the beatmap without any objects, during which HP does not drain.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BreakPeriod {
+    start: u64,
+    end: u64,
+}
+
+impl BreakPeriod {
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    /// The length of the break in milliseconds.
+    pub fn duration(&self) -> u64 {
+        self.end.saturating_sub(self.start)
+    }
+
+    /// Whether the given time lies within this break.
+    pub fn contains(&self, time: f64) -> bool {
+        self.start as f64 <= time && time <= self.end as f64
+    }
+
+    /// Scales the break for a song played at the given rate.
+    pub(super) fn apply_clock_rate(&mut self, clock_rate: f64) {
+        self.start = (self.start as f64 / clock_rate).round() as u64;
+        self.end = (self.end as f64 / clock_rate).round() as u64;
+    }
+}
+
+impl FromStr for BreakPeriod {
+    type Err = &'static str;
+
+    /// Parses a break event of the form `2,start,end` (or `Break,start,end`) from the `[Events]` section.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut split = s.split(',').map(|token| token.trim());
+
+        match split.next() {
+            Some("2") | Some("Break") => {}
+            _ => return Err("Event is not a break"),
+        }
+
+        let start = split
+            .next()
+            .and_then(|token| token.parse::<f64>().ok())
+            .ok_or("Error parsing break start time")?;
+        let end = split
+            .next()
+            .and_then(|token| token.parse::<f64>().ok())
+            .ok_or("Error parsing break end time")?;
+
+        Ok(Self {
+            start: start.max(0.0) as u64,
+            end: end.max(start).max(0.0) as u64,
+        })
+    }
+}
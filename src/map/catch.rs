@@ -0,0 +1,477 @@
+use crate::{osu_data::PLAYFIELD_WIDTH, random::LegacyRandom, replay::Mods};
+
+use super::{
+    hit_object::{HitObjectData, SliderData},
+    Beatmap,
+};
+
+/// The seed osu!stable uses for the random offsets of tiny droplets, bananas and Hard Rock.
+const RNG_SEED: i32 = 1337;
+
+/// The width of the catcher at a scale of 1.
+const BASE_CATCHER_WIDTH: f32 = 106.75;
+
+/// Only this fraction of the catcher's width actually catches objects.
+const ALLOWED_CATCH_RANGE: f32 = 0.8;
+
+/// The speed of the catcher while dashing in osu!pixels per millisecond.
+pub const BASE_DASH_SPEED: f64 = 1.0;
+
+/// The speed of the catcher while walking in osu!pixels per millisecond.
+pub const BASE_WALK_SPEED: f64 = 0.5;
+
+/// Ticks closer to the end of a juice stream than this many milliseconds (times the velocity) are left out.
+const TICK_MIN_DISTANCE_FROM_END: f64 = 10.0;
+
+/// osu!stable places the last droplets of a juice stream up to this many milliseconds before its end.
+const LEGACY_LAST_TICK_OFFSET: f64 = 36.0;
+
+/// The width of the area in which the catcher catches objects, given the circle size.
+pub fn catch_width(circle_size: f32) -> f32 {
+    let scale = 1.0 - 0.7 * (circle_size - 5.0) / 5.0;
+    BASE_CATCHER_WIDTH * scale.abs() * ALLOWED_CATCH_RANGE
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CatchObjectKind {
+    /// Made from circles as well as the heads, repeats and tails of sliders
+    Fruit,
+    /// Made from slider ticks
+    Droplet,
+    /// Fill the space between the other objects of a juice stream
+    TinyDroplet,
+    /// Fall during banana showers, which are made from spinners
+    Banana,
+}
+
+impl CatchObjectKind {
+    /// Whether catching this object increases the combo and missing it breaks the combo.
+    pub fn affects_combo(&self) -> bool {
+        matches!(self, Self::Fruit | Self::Droplet)
+    }
+}
+
+/// A single object falling down in osu!catch.
+#[derive(Debug, Clone, Copy)]
+pub struct CatchObject {
+    kind: CatchObjectKind,
+    time: f64,
+    x: f32,
+    hyper_dash: bool,
+    distance_to_hyper_dash: f32,
+}
+
+impl CatchObject {
+    fn new(kind: CatchObjectKind, time: f64, x: f32) -> Self {
+        Self {
+            kind,
+            time,
+            x,
+            hyper_dash: false,
+            distance_to_hyper_dash: 0.0,
+        }
+    }
+
+    pub fn kind(&self) -> CatchObjectKind {
+        self.kind
+    }
+
+    /// The time at which the object reaches the catcher in milliseconds.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// The horizontal position of the object including all random offsets.
+    pub fn x(&self) -> f32 {
+        self.x
+    }
+
+    /// Whether catching this object gives a hyperdash, because the next one cannot be reached by dashing.
+    pub fn hyper_dash(&self) -> bool {
+        self.hyper_dash
+    }
+
+    /// How much further the next object could be away before this object would give a hyperdash.
+    pub fn distance_to_hyper_dash(&self) -> f32 {
+        self.distance_to_hyper_dash
+    }
+}
+
+impl Beatmap {
+    /// The objects of this beatmap as they are played in osu!catch with the given mods, ordered by time.
+    ///
+    /// The random offsets of tiny droplets, bananas and Hard Rock are generated just like osu!stable does it,
+    /// so they are the same for every play of the beatmap.
+    pub fn catch_objects(&self, mods: Mods) -> Vec<CatchObject> {
+        let mut rng = LegacyRandom::new(RNG_SEED);
+        let hard_rock = mods.contains(Mods::HARD_ROCK);
+        let mut last_position = None;
+        let mut last_start_time = 0.0;
+        let mut objects = vec![];
+
+        for obj in &self.hit_objects {
+            let start = obj.timestamp() as f64;
+            match obj.object_data() {
+                HitObjectData::Circle | HitObjectData::Hold(_) => {
+                    let mut x = obj.x() as f32;
+                    if hard_rock {
+                        x = hard_rock_position(
+                            x,
+                            start,
+                            &mut last_position,
+                            &mut last_start_time,
+                            &mut rng,
+                        );
+                    }
+                    objects.push(CatchObject::new(CatchObjectKind::Fruit, start, x));
+                }
+                HitObjectData::Slider(slider) => {
+                    // osu!stable uses the last control point instead of the end of the path as well as the start time
+                    let last_point = slider
+                        .curve_points()
+                        .last()
+                        .map_or(obj.x() as f32, |p| p.0 as f32);
+                    last_position = Some(last_point);
+                    last_start_time = start;
+
+                    for mut nested in juice_stream(start, slider) {
+                        match nested.kind {
+                            CatchObjectKind::TinyDroplet => {
+                                let offset = rng.next_in_range(-20.0, 20.0) as f32;
+                                nested.x += offset.clamp(-nested.x, PLAYFIELD_WIDTH - nested.x);
+                            }
+                            CatchObjectKind::Droplet => {
+                                // osu!stable retrieved a random droplet rotation
+                                rng.next_i32();
+                            }
+                            _ => {}
+                        }
+                        objects.push(nested);
+                    }
+                }
+                HitObjectData::Spinner(_) => {
+                    for time in banana_times(start, obj.end_time()) {
+                        let x = rng.next_in_range(0.0, PLAYFIELD_WIDTH as f64) as f32;
+                        // osu!stable retrieved a random banana type, rotation and colour
+                        rng.next_i32();
+                        rng.next_i32();
+                        rng.next_i32();
+                        objects.push(CatchObject::new(CatchObjectKind::Banana, time, x));
+                    }
+                }
+            }
+        }
+
+        objects.sort_by(|a, b| a.time.total_cmp(&b.time));
+        let circle_size = self.difficulty.with_mods(mods).difficulty().circle_size();
+        initialise_hyper_dash(&mut objects, catch_width(circle_size));
+        objects
+    }
+}
+
+/// The fruits, droplets and tiny droplets of a slider, before random offsets are applied.
+fn juice_stream(start: f64, slider: &SliderData) -> Vec<CatchObject> {
+    let spans = slider.slides().max(1);
+    let span_duration = slider.span_duration();
+    let length = slider.length();
+    let path = slider.path();
+    let x_at = |progress: f64| path.position_at(progress).0;
+
+    // (time, path progress, kind) of every event, where `None` is the legacy last tick which only
+    // influences the placement of tiny droplets
+    let mut events = vec![(start, 0.0, Some(CatchObjectKind::Fruit))];
+    let min_distance_from_end = slider.velocity() * TICK_MIN_DISTANCE_FROM_END;
+    let tick_distance = slider.tick_distance().min(length);
+    for span in 0..spans {
+        let span_start = start + span as f64 * span_duration;
+        let reversed = span % 2 == 1;
+        let mut ticks = vec![];
+        if tick_distance > 0.0 {
+            let mut d = tick_distance;
+            while d < length - min_distance_from_end {
+                let progress = d / length;
+                let time_progress = if reversed { 1.0 - progress } else { progress };
+                ticks.push((
+                    span_start + time_progress * span_duration,
+                    progress,
+                    Some(CatchObjectKind::Droplet),
+                ));
+                d += tick_distance;
+            }
+        }
+        if reversed {
+            ticks.reverse();
+        }
+        events.extend(ticks);
+        if span + 1 < spans {
+            events.push((
+                span_start + span_duration,
+                ((span + 1) % 2) as f64,
+                Some(CatchObjectKind::Fruit),
+            ));
+        }
+    }
+
+    let total_duration = spans as f64 * span_duration;
+    let final_span_start = start + (spans - 1) as f64 * span_duration;
+    let final_span_end = (start + total_duration / 2.0)
+        .max(final_span_start + span_duration - LEGACY_LAST_TICK_OFFSET);
+    let mut final_progress = if span_duration > 0.0 {
+        (final_span_end - final_span_start) / span_duration
+    } else {
+        0.0
+    };
+    if spans.is_multiple_of(2) {
+        final_progress = 1.0 - final_progress;
+    }
+    events.push((final_span_end, final_progress, None));
+    events.push((
+        start + total_duration,
+        (spans % 2) as f64,
+        Some(CatchObjectKind::Fruit),
+    ));
+
+    let mut objects = vec![];
+    let mut last_event: Option<(f64, f64)> = None;
+    for (time, progress, kind) in events {
+        if let Some((last_time, last_progress)) = last_event {
+            // osu!stable works with whole milliseconds here
+            let since_last_tick = time.trunc() - last_time.trunc();
+            if since_last_tick > 80.0 {
+                let mut time_between_tiny = since_last_tick;
+                while time_between_tiny > 100.0 {
+                    time_between_tiny /= 2.0;
+                }
+                let mut t = time_between_tiny;
+                while t < since_last_tick {
+                    let tiny_progress =
+                        last_progress + (t / since_last_tick) * (progress - last_progress);
+                    objects.push(CatchObject::new(
+                        CatchObjectKind::TinyDroplet,
+                        last_time + t,
+                        x_at(tiny_progress),
+                    ));
+                    t += time_between_tiny;
+                }
+            }
+        }
+        last_event = Some((time, progress));
+
+        if let Some(kind) = kind {
+            objects.push(CatchObject::new(kind, time, x_at(progress)));
+        }
+    }
+
+    objects
+}
+
+/// The times of the bananas of a banana shower, which are spaced at most 100 milliseconds apart.
+fn banana_times(start: f64, end: f64) -> Vec<f64> {
+    let mut spacing = end - start;
+    while spacing > 100.0 {
+        spacing /= 2.0;
+    }
+    if spacing <= 0.0 {
+        return vec![];
+    }
+
+    let mut times = vec![];
+    let mut time = start;
+    while time <= end {
+        times.push(time);
+        time += spacing;
+    }
+    times
+}
+
+/// Moves fruits which are close to the previous one in time around a bit, like osu!stable does with Hard Rock.
+fn hard_rock_position(
+    x: f32,
+    time: f64,
+    last_position: &mut Option<f32>,
+    last_start_time: &mut f64,
+    rng: &mut LegacyRandom,
+) -> f32 {
+    let Some(last) = *last_position else {
+        *last_position = Some(x);
+        *last_start_time = time;
+        return x;
+    };
+
+    let position_diff = x - last;
+    // osu!stable calculated time differences as integers, which affects the randomisation
+    let time_diff = (time - *last_start_time) as i32;
+    if time_diff > 1000 {
+        *last_position = Some(x);
+        *last_start_time = time;
+        return x;
+    }
+
+    let mut position = x;
+    if position_diff == 0.0 {
+        // The last position is deliberately not updated here
+        let right = rng.next_bool();
+        let offset = (rng.next_in_range(0.0, (time_diff as f64 / 4.0).max(0.0)) as f32).min(20.0);
+        if right {
+            position += if position + offset <= PLAYFIELD_WIDTH {
+                offset
+            } else {
+                -offset
+            };
+        } else {
+            position += if position - offset >= 0.0 {
+                -offset
+            } else {
+                offset
+            };
+        }
+        return position;
+    }
+
+    if position_diff.abs() < (time_diff / 3) as f32 {
+        let amount = position_diff;
+        if (amount > 0.0 && position + amount < PLAYFIELD_WIDTH)
+            || (amount <= 0.0 && position + amount > 0.0)
+        {
+            position += amount;
+        }
+    }
+
+    *last_position = Some(position);
+    *last_start_time = time;
+    position
+}
+
+/// Marks every fruit and droplet from which the next one cannot be reached in time by dashing.
+///
+/// Like osu!stable, this uses the full width of the catcher rather than the part which actually catches.
+fn initialise_hyper_dash(objects: &mut [CatchObject], catch_width: f32) {
+    let half_catcher_width = (catch_width / 2.0 / ALLOWED_CATCH_RANGE) as f64;
+    let palpable = objects
+        .iter()
+        .enumerate()
+        .filter(|(_, obj)| obj.kind.affects_combo())
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+
+    let mut last_direction = 0;
+    let mut last_excess = half_catcher_width;
+    for pair in palpable.windows(2) {
+        let (current, next) = (objects[pair[0]], objects[pair[1]]);
+        let direction = if next.x > current.x { 1 } else { -1 };
+        // A quarter of a frame of grace time, with times truncated like in osu!stable
+        let time_to_next = next.time.trunc() - current.time.trunc() - 1000.0 / 60.0 / 4.0;
+        let distance_to_next = (next.x - current.x).abs() as f64
+            - if last_direction == direction {
+                last_excess
+            } else {
+                half_catcher_width
+            };
+        let distance_to_hyper = (time_to_next * BASE_DASH_SPEED - distance_to_next) as f32;
+
+        let current = &mut objects[pair[0]];
+        if distance_to_hyper < 0.0 {
+            current.hyper_dash = true;
+            current.distance_to_hyper_dash = 0.0;
+            last_excess = half_catcher_width;
+        } else {
+            current.hyper_dash = false;
+            current.distance_to_hyper_dash = distance_to_hyper;
+            last_excess = (distance_to_hyper as f64).clamp(0.0, half_catcher_width);
+        }
+        last_direction = direction;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_data::TestMap;
+
+    use super::*;
+
+    fn catch_map(hit_objects: &[&str]) -> Beatmap {
+        TestMap {
+            mode: 2,
+            ..TestMap::new(hit_objects)
+        }
+        .build()
+    }
+
+    fn count(objects: &[CatchObject], kind: CatchObjectKind) -> usize {
+        objects.iter().filter(|obj| obj.kind() == kind).count()
+    }
+
+    #[test]
+    fn juice_stream() {
+        // Two beats long with one tick per beat
+        let map = catch_map(&["100,192,1000,2,0,L|380:192,1,280"]);
+        let objects = map.catch_objects(Mods::empty());
+        let combo_objects = objects
+            .iter()
+            .filter(|obj| obj.kind().affects_combo())
+            .map(|obj| (obj.kind(), obj.time().round(), obj.x().round()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            combo_objects,
+            [
+                (CatchObjectKind::Fruit, 1000.0, 100.0),
+                (CatchObjectKind::Droplet, 1500.0, 240.0),
+                (CatchObjectKind::Fruit, 2000.0, 380.0),
+            ]
+        );
+
+        // Tiny droplets are at most 100ms apart up to the legacy last tick 36ms before the end
+        assert_eq!(count(&objects, CatchObjectKind::TinyDroplet), 14);
+        for obj in objects
+            .iter()
+            .filter(|obj| obj.kind() == CatchObjectKind::TinyDroplet)
+        {
+            let (x, _) = map.hit_objects()[0].position();
+            let on_path = x + (obj.time() - 1000.0) as f32 / 1000.0 * 280.0;
+            assert!((obj.x() - on_path).abs() <= 20.0, "{obj:?}");
+        }
+    }
+
+    #[test]
+    fn banana_shower() {
+        let map = catch_map(&["256,192,1000,12,0,2000"]);
+        let objects = map.catch_objects(Mods::empty());
+        // Spaced 62.5ms apart, including both ends
+        assert_eq!(count(&objects, CatchObjectKind::Banana), 17);
+        assert!(objects
+            .iter()
+            .all(|obj| (0.0..PLAYFIELD_WIDTH).contains(&obj.x())));
+        assert_eq!(objects[1].time() - objects[0].time(), 62.5);
+    }
+
+    #[test]
+    fn hyper_dash() {
+        let map = catch_map(&["0,192,1000,1,0", "512,192,1100,1,0", "412,192,2100,1,0"]);
+        let objects = map.catch_objects(Mods::empty());
+        assert!(objects[0].hyper_dash());
+        assert_eq!(objects[0].distance_to_hyper_dash(), 0.0);
+        assert!(!objects[1].hyper_dash());
+        assert!(objects[1].distance_to_hyper_dash() > 0.0);
+        // The last object has nothing to dash to
+        assert!(!objects[2].hyper_dash());
+    }
+
+    #[test]
+    fn hard_rock_offsets_fruits_on_the_same_position() {
+        let map = catch_map(&["256,192,1000,1,0", "256,192,1100,1,0", "256,192,3000,1,0"]);
+        let positions = |mods| {
+            map.catch_objects(mods)
+                .iter()
+                .map(CatchObject::x)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(positions(Mods::empty()), [256.0, 256.0, 256.0]);
+
+        let positions = positions(Mods::HARD_ROCK);
+        assert_eq!(positions[0], 256.0);
+        assert_ne!(positions[1], 256.0);
+        assert!((positions[1] - 256.0).abs() <= 20.0);
+        // Fruits more than a second after the previous one are not moved
+        assert_eq!(positions[2], 256.0);
+    }
+}
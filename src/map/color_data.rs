@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct ColorData {
     combo_colors: Vec<Color>,
     slider_track: Option<Color>,
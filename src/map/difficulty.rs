@@ -1,3 +1,9 @@
+use crate::{
+    health,
+    map::Beatmap,
+    replay::{Mods, Replay},
+};
+
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Difficulty {
     hp_drain_rate: f32,
@@ -8,6 +14,212 @@ pub struct Difficulty {
     slider_tick_rate: f32,
 }
 
+impl Difficulty {
+    pub fn hp_drain_rate(&self) -> f32 {
+        self.hp_drain_rate
+    }
+
+    pub fn circle_size(&self) -> f32 {
+        self.circle_size
+    }
+
+    pub fn overall_difficulty(&self) -> f32 {
+        self.overall_difficulty
+    }
+
+    pub fn approach_rate(&self) -> f32 {
+        self.approach_rate
+    }
+
+    pub fn slider_multiplier(&self) -> f32 {
+        self.slider_multiplier
+    }
+
+    pub fn slider_tick_rate(&self) -> f32 {
+        self.slider_tick_rate
+    }
+
+    /// The time in milliseconds before an object's timestamp at which it starts to appear.
+    pub fn preempt(&self) -> f64 {
+        difficulty_range(self.approach_rate as f64, 1800.0, 1200.0, 450.0)
+    }
+
+    /// The radius of circles in osu!pixels.
+    pub fn circle_radius(&self) -> f32 {
+        54.4 - 4.48 * self.circle_size
+    }
+
+    /// Applies the difficulty scaling of Easy and Hard Rock.
+    /// Hard Rock multiplies circle size by 1.3 and all other values by 1.4, capped at 10. Easy halves everything.
+    pub fn apply_mods(&mut self, mods: Mods) {
+        if mods.contains(Mods::HARD_ROCK) {
+            self.circle_size = (self.circle_size * 1.3).min(10.0);
+            self.approach_rate = (self.approach_rate * 1.4).min(10.0);
+            self.overall_difficulty = (self.overall_difficulty * 1.4).min(10.0);
+            self.hp_drain_rate = (self.hp_drain_rate * 1.4).min(10.0);
+        }
+        if mods.contains(Mods::EASY) {
+            self.circle_size *= 0.5;
+            self.approach_rate *= 0.5;
+            self.overall_difficulty *= 0.5;
+            self.hp_drain_rate *= 0.5;
+        }
+    }
+
+    /// Converts approach rate and overall difficulty to the values which result in the same
+    /// preempt time and 300 hit window in real time when the song is played at the given rate.
+    pub fn apply_clock_rate(&mut self, clock_rate: f64) {
+        let preempt = self.preempt() / clock_rate;
+        self.approach_rate = if preempt > 1200.0 {
+            5.0 - (preempt - 1200.0) / 120.0
+        } else {
+            5.0 + (1200.0 - preempt) / 150.0
+        } as f32;
+
+        let window_300 = (80.0 - 6.0 * self.overall_difficulty as f64) / clock_rate;
+        self.overall_difficulty = ((80.0 - window_300) / 6.0) as f32;
+    }
+
+    /// Get the difficulty values that apply when playing with the given mods.
+    pub fn with_mods(&self, mods: Mods) -> EffectiveDifficulty {
+        let mut difficulty = *self;
+        difficulty.apply_mods(mods);
+        EffectiveDifficulty { difficulty, mods }
+    }
+
+    /// Get the difficulty values that applied when the given replay was played.
+    pub fn for_replay(&self, replay: &Replay) -> EffectiveDifficulty {
+        self.with_mods(replay.mods)
+    }
+}
+
+/// The difficulty values of a beatmap as they apply to a play with a set of mods.
+///
+/// All times are in song time, which is also what replay frames and hit object timestamps use.
+/// Use [`EffectiveDifficulty::clock_rate`] to convert them to real time.
+#[derive(Debug, Clone, Copy)]
+pub struct EffectiveDifficulty {
+    difficulty: Difficulty, // with Easy and Hard Rock applied
+    mods: Mods,
+}
+
+impl EffectiveDifficulty {
+    /// The difficulty with Easy and Hard Rock scaling applied.
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    pub fn mods(&self) -> Mods {
+        self.mods
+    }
+
+    /// The speed at which the song is played.
+    pub fn clock_rate(&self) -> f64 {
+        self.mods.clock_rate()
+    }
+
+    /// The time in milliseconds before an object's timestamp at which it starts to appear.
+    pub fn preempt(&self) -> f64 {
+        self.difficulty.preempt()
+    }
+
+    /// The time in milliseconds it takes an object to fully fade in after it starts to appear.
+    pub fn fade_in(&self) -> f64 {
+        if self.mods.contains(Mods::HIDDEN) {
+            self.preempt() * 0.4
+        } else {
+            400.0 * (self.preempt() / 450.0).min(1.0)
+        }
+    }
+
+    /// The radius of circles in osu!pixels.
+    pub fn circle_radius(&self) -> f32 {
+        self.difficulty.circle_radius()
+    }
+
+    /// The HP drain rate setting with Easy and Hard Rock applied.
+    pub fn hp_drain_rate(&self) -> f32 {
+        self.difficulty.hp_drain_rate()
+    }
+
+    /// The health (between 0 and 1) drained per millisecond of song time while playing the given (unmodified) beatmap,
+    /// derived from the HP drain rate like osu!stable does. Multiply it by the clock rate for real time.
+    pub fn health_drain_rate(&self, map: &Beatmap) -> f64 {
+        health::drain_rate(map, self.mods, self.hp_drain_rate() as f64).0
+    }
+
+    /// The hit windows for circles and slider heads.
+    pub fn hit_windows(&self) -> HitWindows {
+        HitWindows::from_overall_difficulty(self.difficulty.overall_difficulty())
+    }
+
+    /// The approach rate that would result in the same preempt time in real time without rate changing mods,
+    /// e.g. AR 9 with Double Time results in AR 10.33.
+    pub fn approach_rate(&self) -> f32 {
+        let mut difficulty = self.difficulty;
+        difficulty.apply_clock_rate(self.clock_rate());
+        difficulty.approach_rate()
+    }
+
+    /// The overall difficulty that would result in the same 300 hit window in real time without rate changing mods.
+    pub fn overall_difficulty(&self) -> f32 {
+        let mut difficulty = self.difficulty;
+        difficulty.apply_clock_rate(self.clock_rate());
+        difficulty.overall_difficulty()
+    }
+}
+
+/// The maximum absolute hit error in milliseconds for each judgement of a circle in osu!standard.
+#[derive(Debug, Clone, Copy)]
+pub struct HitWindows {
+    window_300: f64,
+    window_100: f64,
+    window_50: f64,
+}
+
+impl HitWindows {
+    pub fn from_overall_difficulty(overall_difficulty: f32) -> Self {
+        let od = overall_difficulty as f64;
+        Self {
+            window_300: difficulty_range(od, 80.0, 50.0, 20.0),
+            window_100: difficulty_range(od, 140.0, 100.0, 60.0),
+            window_50: difficulty_range(od, 200.0, 150.0, 100.0),
+        }
+    }
+
+    pub fn window_300(&self) -> f64 {
+        self.window_300
+    }
+
+    pub fn window_100(&self) -> f64 {
+        self.window_100
+    }
+
+    pub fn window_50(&self) -> f64 {
+        self.window_50
+    }
+
+    /// Converts the hit windows to real time for a song played at the given rate.
+    pub fn in_real_time(&self, clock_rate: f64) -> Self {
+        Self {
+            window_300: self.window_300 / clock_rate,
+            window_100: self.window_100 / clock_rate,
+            window_50: self.window_50 / clock_rate,
+        }
+    }
+}
+
+/// Maps a difficulty value between 0 and 10 to a range of values, where 5 maps to `mid`.
+pub fn difficulty_range(difficulty: f64, min: f64, mid: f64, max: f64) -> f64 {
+    if difficulty > 5.0 {
+        mid + (max - mid) * (difficulty - 5.0) / 5.0
+    } else if difficulty < 5.0 {
+        mid - (mid - min) * (5.0 - difficulty) / 5.0
+    } else {
+        mid
+    }
+}
+
 impl<'a, T> From<T> for Difficulty
 where
     T: Iterator<Item = &'a str>,
@@ -37,3 +249,26 @@ where
         diff
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::{health::HealthTimeline, judgement::standard, map::Beatmap, replay::Mods};
+
+    const TIFERET_MAP: &str =
+        "res/maps/714225 xi feat Sta - Tiferet/xi feat. Sta - Tiferet (ktgster) [Another].osu";
+
+    #[test]
+    fn health_drain_rate_depends_on_mods() {
+        let map = Beatmap::from_str(&std::fs::read_to_string(TIFERET_MAP).unwrap()).unwrap();
+        let drain = |mods| map.difficulty().with_mods(mods).health_drain_rate(&map);
+
+        assert!(drain(Mods::EASY) < drain(Mods::NONE));
+        assert!(drain(Mods::NONE) < drain(Mods::HARD_ROCK));
+        for mods in [Mods::NONE, Mods::HARD_ROCK | Mods::DOUBLE_TIME] {
+            let timeline = HealthTimeline::simulate(&map, &standard::perfect(&map, mods), mods);
+            assert_eq!(drain(mods), timeline.drain_rate());
+        }
+    }
+}
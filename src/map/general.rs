@@ -1,6 +1,6 @@
 use super::*;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct General {
     audio_file: String,
     audio_lead_in: usize,
@@ -21,6 +21,16 @@ pub struct General {
     samples_match_playback_rate: bool,
 }
 
+impl General {
+    pub fn stack_leniency(&self) -> f64 {
+        self.stack_leniency
+    }
+
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+}
+
 impl<'a, T> From<T> for General
 where
     T: Iterator<Item = &'a str>,
@@ -2,9 +2,11 @@ use std::str::FromStr;
 
 use crate::{
     map::hit_object,
-    osu_data::{Hitsound, SampleSet},
+    osu_data::{Hitsound, Reflection, SampleSet},
 };
 
+use super::slider_path::SliderPath;
+
 use bitflags::bitflags;
 
 bitflags! {
@@ -97,30 +99,32 @@ impl FromStr for HitSampleData {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum HitObjectData {
     Circle,
     Slider(SliderData),
-    Spinner(u64), // Parameter is duration of the spinner
+    Spinner(u64), // Parameter is the end time of the spinner
+    Hold(u64),    // Parameter is the end time of the osu!mania hold note
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct HitObject {
-    x: u16,
-    y: u16,
+    x: i16,
+    y: i16,
     timestamp: u64,
     hit_object_meta: HitObjectMeta,
     hit_sound: Hitsound,
     hit_sample: CustomHitSample,
     object_data: HitObjectData,
+    stack_height: i32,
 }
 
 impl HitObject {
-    pub fn x(&self) -> u16 {
+    pub fn x(&self) -> i16 {
         self.x
     }
 
-    pub fn y(&self) -> u16 {
+    pub fn y(&self) -> i16 {
         self.y
     }
 
@@ -139,6 +143,125 @@ impl HitObject {
     pub fn object_data(&self) -> &HitObjectData {
         &self.object_data
     }
+
+    pub fn is_circle(&self) -> bool {
+        matches!(self.object_data, HitObjectData::Circle)
+    }
+
+    pub fn is_slider(&self) -> bool {
+        matches!(self.object_data, HitObjectData::Slider(_))
+    }
+
+    pub fn is_spinner(&self) -> bool {
+        matches!(self.object_data, HitObjectData::Spinner(_))
+    }
+
+    /// Whether this object starts a new combo.
+    pub fn new_combo(&self) -> bool {
+        self.hit_object_meta.contains(HitObjectMeta::NEW_COMBO)
+    }
+
+    /// The position of the object as given in the beatmap.
+    pub fn position(&self) -> (f32, f32) {
+        (self.x as f32, self.y as f32)
+    }
+
+    /// The time at which the object ends. For circles this is the same as the timestamp.
+    pub fn end_time(&self) -> f64 {
+        match &self.object_data {
+            HitObjectData::Circle => self.timestamp as f64,
+            HitObjectData::Slider(slider) => self.timestamp as f64 + slider.duration(),
+            HitObjectData::Spinner(end_time) | HitObjectData::Hold(end_time) => *end_time as f64,
+        }
+    }
+
+    /// The position of the object at its end time. For sliders with an even number of slides this is the start position.
+    pub fn end_position(&self) -> (f32, f32) {
+        match &self.object_data {
+            HitObjectData::Slider(slider) if slider.slides % 2 == 1 => slider.path.position_at(1.0),
+            _ => self.position(),
+        }
+    }
+
+    /// The stack height of this object. Objects with positive height are stacked up and to the left,
+    /// objects with negative height down and to the right.
+    pub fn stack_height(&self) -> i32 {
+        self.stack_height
+    }
+
+    pub fn set_stack_height(&mut self, stack_height: i32) {
+        self.stack_height = stack_height;
+    }
+
+    /// The offset applied to this object's positions due to stacking, depending on the circle radius.
+    pub fn stack_offset(&self, circle_radius: f32) -> (f32, f32) {
+        let offset = -(self.stack_height as f32) * circle_radius / 10.0;
+        (offset, offset)
+    }
+
+    /// The position at which the object is actually displayed and hit, considering stacking.
+    pub fn stacked_position(&self, circle_radius: f32) -> (f32, f32) {
+        let (x, y) = self.position();
+        let (dx, dy) = self.stack_offset(circle_radius);
+        (x + dx, y + dy)
+    }
+
+    /// The end position of the object, considering stacking.
+    pub fn stacked_end_position(&self, circle_radius: f32) -> (f32, f32) {
+        let (x, y) = self.end_position();
+        let (dx, dy) = self.stack_offset(circle_radius);
+        (x + dx, y + dy)
+    }
+
+    /// Flips the object's positions along the given axis.
+    pub(super) fn reflect(&mut self, reflection: Reflection) {
+        let (x, y) = reflection.apply(self.position());
+        self.x = x as i16;
+        self.y = y as i16;
+        if let HitObjectData::Slider(slider) = &mut self.object_data {
+            slider.curve_points.iter_mut().for_each(|point| {
+                let (x, y) = reflection.apply((point.0 as f32, point.1 as f32));
+                *point = (x as i16, y as i16);
+            });
+            slider.path.transform(|point| reflection.apply(point));
+        }
+    }
+
+    /// Scales the object's times for a song played at the given rate.
+    /// Slider durations are recalculated from the scaled timing points in [`HitObject::apply_timing`].
+    pub(super) fn apply_clock_rate(&mut self, clock_rate: f64) {
+        self.timestamp = (self.timestamp as f64 / clock_rate).round() as u64;
+        if let HitObjectData::Spinner(end_time) | HitObjectData::Hold(end_time) =
+            &mut self.object_data
+        {
+            *end_time = (*end_time as f64 / clock_rate).round() as u64;
+        }
+    }
+
+    /// Calculates the timing information for sliders. This is needed for the end time of sliders to be correct.
+    ///
+    /// `beat_length` is the length of a beat in milliseconds at the time of this object,
+    /// `slider_velocity` the multiplier from inherited timing points.
+    pub(super) fn apply_timing(
+        &mut self,
+        beat_length: f64,
+        slider_velocity: f64,
+        slider_multiplier: f64,
+        slider_tick_rate: f64,
+        format_version: u8,
+    ) {
+        if let HitObjectData::Slider(slider) = &mut self.object_data {
+            let scoring_distance = 100.0 * slider_multiplier * slider_velocity;
+            slider.velocity = scoring_distance / beat_length;
+            slider.span_duration = slider.length / slider.velocity;
+            // Maps before v8 had ticks which were independent of slider velocity
+            slider.tick_distance = if format_version < 8 {
+                scoring_distance / slider_tick_rate / slider_velocity
+            } else {
+                scoring_distance / slider_tick_rate
+            };
+        }
+    }
 }
 
 impl FromStr for HitObject {
@@ -188,6 +311,21 @@ impl FromStr for HitObject {
         // we need to collect all remaining tokens to see if there are actually object parameters.
         let mut hit_object_data = tokens.collect::<Vec<_>>();
 
+        // Hold notes in osu!mania put their end time in front of the hit sample, separated by a colon
+        let mut hold_end_time = None;
+        if hit_object_meta.contains(HitObjectMeta::OSU_MANIA_HOLD) {
+            let (end_time, hit_sample) = hit_object_data
+                .pop()
+                .and_then(|data| data.split_once(':'))
+                .ok_or("Error reading end time of hold note")?;
+            hold_end_time = Some(
+                end_time
+                    .parse()
+                    .map_err(|_| "Error parsing end time of hold note as integer")?,
+            );
+            hit_object_data.push(hit_sample);
+        }
+
         let hit_sample = if let Some(_) = hit_object_data.last() {
             if hit_object_data.last().unwrap().contains(":") {
                 CustomHitSample::from_str(hit_object_data.pop().unwrap())?
@@ -199,14 +337,26 @@ impl FromStr for HitObject {
         };
 
         let object_data;
-        if hit_object_data.is_empty() {
+        if let Some(end_time) = hold_end_time {
+            object_data = Hold(end_time);
+        } else if hit_object_data.is_empty() {
             object_data = Circle;
         } else if let Ok(spinner_duration) = hit_object_data.first().unwrap().parse() {
             // If the first object data value is a number, it is a spinner with its duration
             object_data = Spinner(spinner_duration);
         } else {
             // Otherwise it's a slider
-            object_data = Slider(SliderData::try_from(&hit_object_data[..])?);
+            let mut slider = SliderData::try_from(&hit_object_data[..])?;
+            let control_points = std::iter::once((x as f32, y as f32))
+                .chain(
+                    slider
+                        .curve_points
+                        .iter()
+                        .map(|&(x, y)| (x as f32, y as f32)),
+                )
+                .collect::<Vec<_>>();
+            slider.path = SliderPath::new(slider.curve_type, &control_points, slider.length);
+            object_data = Slider(slider);
         }
 
         Ok(Self {
@@ -217,6 +367,7 @@ impl FromStr for HitObject {
             hit_sound,
             hit_sample,
             object_data,
+            stack_height: 0,
         })
     }
 }
@@ -252,6 +403,75 @@ pub struct SliderData {
     length: f64,
     edge_sounds: Vec<u8>,
     edge_sets: Vec<HitSampleData>,
+    path: SliderPath,
+    velocity: f64,      // in osu!pixels per millisecond
+    span_duration: f64, // in milliseconds
+    tick_distance: f64, // in osu!pixels
+}
+
+impl SliderData {
+    pub fn curve_type(&self) -> SliderCurveType {
+        self.curve_type
+    }
+
+    pub fn curve_points(&self) -> &[(i16, i16)] {
+        &self.curve_points
+    }
+
+    /// The number of times the slider is traversed, i.e. the number of repeats plus one.
+    pub fn slides(&self) -> usize {
+        self.slides
+    }
+
+    pub fn length(&self) -> f64 {
+        self.length
+    }
+
+    pub fn path(&self) -> &SliderPath {
+        &self.path
+    }
+
+    pub fn velocity(&self) -> f64 {
+        self.velocity
+    }
+
+    /// The duration of one traversal of the slider.
+    pub fn span_duration(&self) -> f64 {
+        self.span_duration
+    }
+
+    /// The total duration of the slider including all repeats.
+    pub fn duration(&self) -> f64 {
+        self.span_duration * self.slides as f64
+    }
+
+    pub fn tick_distance(&self) -> f64 {
+        self.tick_distance
+    }
+
+    /// The hitsounds played at the start, every repeat and the end of the slider, in that order.
+    pub fn edge_sounds(&self) -> Vec<Hitsound> {
+        self.edge_sounds
+            .iter()
+            .map(|&bits| Hitsound::from_bits_truncate(bits))
+            .collect()
+    }
+
+    /// The position of the slider ball at the given time relative to the start of the slider.
+    pub fn position_at(&self, time: f64) -> (f32, f32) {
+        if self.span_duration <= 0.0 {
+            return self.path.position_at(0.0);
+        }
+        let progress = (time / self.span_duration).clamp(0.0, self.slides as f64);
+        let span = (progress.floor() as usize).min(self.slides.max(1) - 1);
+        let span_progress = progress - span as f64;
+        // Every other span goes back along the path
+        if span % 2 == 1 {
+            self.path.position_at(1.0 - span_progress)
+        } else {
+            self.path.position_at(span_progress)
+        }
+    }
 }
 
 impl<'a> TryFrom<&'a [&'a str]> for SliderData {
@@ -309,6 +529,10 @@ impl<'a> TryFrom<&'a [&'a str]> for SliderData {
             length,
             edge_sounds,
             edge_sets,
+            path: SliderPath::default(),
+            velocity: 0.0,
+            span_duration: 0.0,
+            tick_distance: 0.0,
         })
     }
 }
@@ -317,3 +541,22 @@ impl<'a> TryFrom<&'a [&'a str]> for SliderData {
 pub struct SpinnerData {
     end_time: u64,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_data::TestMap;
+
+    use super::*;
+
+    #[test]
+    fn position_of_slider_without_slides() {
+        let map = TestMap::new(&["100,192,1000,2,0,L|380:192,0,280"]).build();
+        let HitObjectData::Slider(slider) = map.hit_objects()[0].object_data() else {
+            panic!("not a slider");
+        };
+        assert_eq!(slider.slides(), 0);
+        assert!(slider.span_duration() > 0.0);
+        assert_eq!(slider.position_at(0.0), (100.0, 192.0));
+        assert_eq!(slider.position_at(500.0), (100.0, 192.0));
+    }
+}
@@ -0,0 +1,216 @@
+use crate::{osu_data::GameMode, random::LegacyRandom, replay::Mods};
+
+use super::{hit_object::HitObjectData, Beatmap};
+
+/// The most columns osu!mania supports.
+const MAX_KEY_COUNT: usize = 18;
+
+/// An object as it appears in osu!mania. All times are in milliseconds.
+#[derive(Debug, Clone, Copy)]
+pub enum ManiaObject {
+    /// A note which is hit by pressing the key of its column
+    Note { time: f64, column: usize },
+    /// A note whose key has to be held until its end and then released
+    Hold { start: f64, end: f64, column: usize },
+}
+
+impl ManiaObject {
+    pub fn start_time(&self) -> f64 {
+        match *self {
+            Self::Note { time, .. } => time,
+            Self::Hold { start, .. } => start,
+        }
+    }
+
+    pub fn end_time(&self) -> f64 {
+        match *self {
+            Self::Note { time, .. } => time,
+            Self::Hold { end, .. } => end,
+        }
+    }
+
+    pub fn column(&self) -> usize {
+        match *self {
+            Self::Note { column, .. } | Self::Hold { column, .. } => column,
+        }
+    }
+
+    fn set_column(&mut self, new_column: usize) {
+        match self {
+            Self::Note { column, .. } | Self::Hold { column, .. } => *column = new_column,
+        }
+    }
+}
+
+impl Beatmap {
+    /// Whether this beatmap was made for osu!mania rather than converted from another mode.
+    pub fn is_mania_map(&self) -> bool {
+        matches!(self.general.mode(), GameMode::Mania)
+    }
+
+    /// The number of columns this beatmap is played with in osu!mania.
+    ///
+    /// osu!mania beatmaps store it as their circle size. Converted beatmaps use the key count of a key mod,
+    /// otherwise they choose it from their overall difficulty and the fraction of sliders and spinners.
+    pub fn mania_key_count(&self, mods: Mods) -> usize {
+        let circle_size = self.difficulty.circle_size().round();
+        if self.is_mania_map() {
+            return (circle_size as usize).clamp(1, MAX_KEY_COUNT);
+        }
+        if let Some(key_count) = mods.key_count() {
+            return key_count;
+        }
+
+        let overall_difficulty = self.difficulty.overall_difficulty().round() as usize;
+        let special_objects = self
+            .hit_objects
+            .iter()
+            .filter(|obj| !obj.is_circle())
+            .count();
+        let special_ratio = special_objects as f64 / self.hit_objects.len().max(1) as f64;
+        if special_ratio < 0.2 {
+            7
+        } else if special_ratio < 0.3 || circle_size >= 5.0 {
+            if overall_difficulty > 5 {
+                7
+            } else {
+                6
+            }
+        } else if special_ratio > 0.6 {
+            if overall_difficulty > 4 {
+                5
+            } else {
+                4
+            }
+        } else {
+            (overall_difficulty + 1).clamp(4, 7)
+        }
+    }
+
+    /// The objects of this beatmap as they are played in osu!mania with the given mods, ordered by time.
+    ///
+    /// The column of an object is decided by its x position. Converted beatmaps are distributed over the columns
+    /// the same way, which only approximates osu!stable's conversion.
+    /// With Random the columns are shuffled using the seed stored in the replay, with Mirror they are flipped.
+    pub fn mania_objects(&self, mods: Mods, seed: Option<i32>) -> Vec<ManiaObject> {
+        let key_count = self.mania_key_count(mods);
+        let column_of =
+            |x: i16| ((x as f32 * key_count as f32 / 512.0) as usize).min(key_count - 1);
+
+        let mut objects = self
+            .hit_objects
+            .iter()
+            .map(|obj| {
+                let column = column_of(obj.x());
+                let start = obj.timestamp() as f64;
+                match obj.object_data() {
+                    HitObjectData::Circle => ManiaObject::Note {
+                        time: start,
+                        column,
+                    },
+                    HitObjectData::Hold(_)
+                    | HitObjectData::Slider(_)
+                    | HitObjectData::Spinner(_) => ManiaObject::Hold {
+                        start,
+                        end: obj.end_time(),
+                        column,
+                    },
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut columns = (0..key_count).collect::<Vec<_>>();
+        if let Some(seed) = seed.filter(|_| mods.contains(Mods::RANDOM)) {
+            let mut rng = LegacyRandom::new(seed);
+            for i in (1..key_count).rev() {
+                let j = rng.next_in_range(0.0, (i + 1) as f64) as usize;
+                columns.swap(i, j);
+            }
+        }
+        if mods.contains(Mods::MIRROR) {
+            columns.reverse();
+        }
+        objects
+            .iter_mut()
+            .for_each(|obj| obj.set_column(columns[obj.column()]));
+
+        objects.sort_by(|a, b| a.start_time().total_cmp(&b.start_time()));
+        objects
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_data::TestMap;
+
+    use super::*;
+
+    #[test]
+    fn key_mods_only_apply_to_converted_beatmaps() {
+        let objects = ["64,192,1000,1,0", "448,192,1500,1,0"];
+        let mania_map = TestMap {
+            mode: 3,
+            ..TestMap::new(&objects)
+        }
+        .build();
+        assert_eq!(mania_map.mania_key_count(Mods::empty()), 4);
+        assert_eq!(mania_map.mania_key_count(Mods::KEY7), 4);
+
+        // Converted from only circles
+        let converted = TestMap::new(&objects).build();
+        assert_eq!(converted.mania_key_count(Mods::empty()), 7);
+        assert_eq!(converted.mania_key_count(Mods::KEY4), 4);
+    }
+
+    #[test]
+    fn columns_from_positions() {
+        let map = TestMap {
+            mode: 3,
+            ..TestMap::new(&[
+                "64,192,1000,1,0",
+                "192,192,1100,1,0",
+                "320,192,1200,128,0,1500:0:0:0:0:",
+                "448,192,1300,1,0",
+                "512,192,1400,1,0",
+            ])
+        }
+        .build();
+        let columns = |mods| {
+            map.mania_objects(mods, None)
+                .iter()
+                .map(ManiaObject::column)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(columns(Mods::empty()), [0, 1, 2, 3, 3]);
+        assert_eq!(columns(Mods::MIRROR), [3, 2, 1, 0, 0]);
+
+        let objects = map.mania_objects(Mods::empty(), None);
+        assert!(matches!(
+            objects[2],
+            ManiaObject::Hold { start, end, column: 2 } if start == 1200.0 && end == 1500.0
+        ));
+    }
+
+    #[test]
+    fn random_shuffles_columns_by_seed() {
+        let map = TestMap {
+            mode: 3,
+            ..TestMap::new(&[
+                "64,192,1000,1,0",
+                "192,192,1100,1,0",
+                "320,192,1200,1,0",
+                "448,192,1300,1,0",
+            ])
+        }
+        .build();
+        let columns = |seed| {
+            map.mania_objects(Mods::RANDOM, seed)
+                .iter()
+                .map(ManiaObject::column)
+                .collect::<Vec<_>>()
+        };
+        // Without a seed the columns cannot be shuffled like in the original play
+        assert_eq!(columns(None), [0, 1, 2, 3]);
+        assert_eq!(columns(Some(1)), [1, 2, 3, 0]);
+    }
+}
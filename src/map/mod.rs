@@ -6,29 +6,47 @@ use bitflags::bitflags;
 use std::collections::HashMap;
 use std::str::FromStr;
 
+use self::break_period::BreakPeriod;
 use self::color_data::ColorData;
 use self::difficulty::Difficulty;
 use self::metadata::Metadata;
 use self::timing_point::TimingPoint;
 
+pub mod break_period;
+pub mod catch;
 pub mod color_data;
 pub mod difficulty;
 pub mod general;
 pub mod hit_object;
+pub mod mania;
 pub mod metadata;
+pub mod slider_path;
+pub mod stacking;
+pub mod taiko;
 pub mod timing_point;
+pub mod transform;
 
-#[derive(Default, Debug)]
+/// The format version osu! assumes for beatmaps without a version header.
+const DEFAULT_FORMAT_VERSION: u8 = 14;
+
+#[derive(Default, Debug, Clone)]
 pub struct Beatmap {
+    format_version: u8,
     general: General,
     metadata: Metadata,
     difficulty: Difficulty,
+    break_periods: Vec<BreakPeriod>,
     timing_points: Vec<TimingPoint>,
     color_data: ColorData,
     hit_objects: Vec<HitObject>,
 }
 
 impl Beatmap {
+    /// Get the version of the .osu file format this beatmap was saved in.
+    pub fn format_version(&self) -> u8 {
+        self.format_version
+    }
+
     /// Get a reference to the beatmap's hit objects.
     pub fn hit_objects(&self) -> &[HitObject] {
         self.hit_objects.as_ref()
@@ -49,6 +67,11 @@ impl Beatmap {
         self.difficulty
     }
 
+    /// Get a reference to the beatmap's break periods, ordered by time.
+    pub fn break_periods(&self) -> &[BreakPeriod] {
+        self.break_periods.as_ref()
+    }
+
     /// Get a reference to the beatmap's timing points.
     pub fn timing_points(&self) -> &[TimingPoint] {
         self.timing_points.as_ref()
@@ -58,6 +81,47 @@ impl Beatmap {
     pub fn color_data(&self) -> &ColorData {
         &self.color_data
     }
+
+    /// Get the uninherited timing point which is active at the given time.
+    /// Objects before the first timing point use the first one.
+    pub fn beat_length_at(&self, time: f64) -> f64 {
+        self.timing_points
+            .iter()
+            .filter(|tp| tp.uninherited())
+            .take_while(|tp| tp.time() as f64 <= time)
+            .last()
+            .or_else(|| self.timing_points.iter().find(|tp| tp.uninherited()))
+            .map(|tp| tp.beat_length())
+            .unwrap_or(1000.0)
+    }
+
+    /// Get the slider velocity multiplier which is active at the given time.
+    pub fn slider_velocity_at(&self, time: f64) -> f64 {
+        self.timing_points
+            .iter()
+            .take_while(|tp| tp.time() as f64 <= time)
+            .last()
+            .map(|tp| tp.slider_velocity())
+            .unwrap_or(1.0)
+    }
+
+    /// Calculates the slider velocities and durations from the timing points.
+    fn apply_timing(&mut self) {
+        let slider_multiplier = self.difficulty.slider_multiplier() as f64;
+        let slider_tick_rate = self.difficulty.slider_tick_rate() as f64;
+        for i in 0..self.hit_objects.len() {
+            let time = self.hit_objects[i].timestamp() as f64;
+            let beat_length = self.beat_length_at(time);
+            let slider_velocity = self.slider_velocity_at(time);
+            self.hit_objects[i].apply_timing(
+                beat_length,
+                slider_velocity,
+                slider_multiplier,
+                slider_tick_rate,
+                self.format_version,
+            );
+        }
+    }
 }
 
 impl FromStr for Beatmap {
@@ -71,15 +135,29 @@ impl FromStr for Beatmap {
                         line.trim() != concat!("[", stringify!($sec_name), "]").to_owned()
                     })
                     .skip(1)
-                    .take_while(|&line| !line.trim().is_empty() || line.trim().starts_with("["))
-                    .filter(|&line| !line.is_empty())
+                    // Sections only end at the next section header, blank lines in between are skipped
+                    .take_while(|&line| !line.trim().starts_with('['))
+                    .filter(|&line| !line.trim().is_empty())
             };
         }
 
+        // The header may be preceded by a byte order mark or blank lines, or be missing entirely
+        let format_version = s
+            .trim_start_matches('\u{feff}')
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .and_then(|line| line.trim().strip_prefix("osu file format v"))
+            .and_then(|version| version.parse().ok())
+            .unwrap_or(DEFAULT_FORMAT_VERSION);
+
         let general = General::from(get_section!(General));
         let metadata = Metadata::from(get_section!(Metadata));
         let difficulty = Difficulty::from(get_section!(Difficulty));
 
+        let break_periods = get_section!(Events)
+            .filter_map(|line| BreakPeriod::from_str(line.trim()).ok())
+            .collect();
+
         let timing_points = get_section!(TimingPoints)
             .map(|line| TimingPoint::from_str(line.trim()).unwrap())
             .collect();
@@ -104,13 +182,67 @@ impl FromStr for Beatmap {
             .map(|(key, values)| (key, Color::from(&values[..])))
             .collect::<HashMap<String, Color>>();
 
-        Ok(Self {
+        let mut map = Self {
+            format_version,
             general,
             metadata,
             difficulty,
+            break_periods,
             timing_points,
             color_data: ColorData::from(color_mappings),
             hit_objects,
-        })
+        };
+        map.apply_timing();
+        if matches!(map.general.mode(), GameMode::Standard) {
+            map.apply_stacking();
+        }
+
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::test_data::TestMap;
+
+    use super::*;
+
+    const HIT_OBJECTS: [&str; 2] = ["256,192,1000,1,0", "100,192,2000,2,0,L|380:192,1,280"];
+
+    #[test]
+    fn parse_with_byte_order_mark() {
+        let s = format!("\u{feff}{}", TestMap::new(&HIT_OBJECTS).to_osu());
+        let map = Beatmap::from_str(&s).unwrap();
+        assert_eq!(map.format_version(), 14);
+        assert_eq!(map.hit_objects().len(), 2);
+    }
+
+    #[test]
+    fn parse_with_blank_lines() {
+        let osu = TestMap {
+            format_version: 9,
+            ..TestMap::new(&HIT_OBJECTS)
+        }
+        .to_osu()
+        .replace('\n', "\n\n");
+        let map = Beatmap::from_str(&format!("\n \n{osu}")).unwrap();
+        assert_eq!(map.format_version(), 9);
+        assert_eq!(map.hit_objects().len(), 2);
+        assert_eq!(map.timing_points().len(), 1);
+    }
+
+    #[test]
+    fn parse_without_header() {
+        let osu = TestMap {
+            format_version: 9,
+            ..TestMap::new(&HIT_OBJECTS)
+        }
+        .to_osu();
+        let s = osu.trim_start_matches("osu file format v9");
+        let map = Beatmap::from_str(s).unwrap();
+        assert_eq!(map.format_version(), DEFAULT_FORMAT_VERSION);
+        assert_eq!(map.hit_objects().len(), 2);
     }
 }
@@ -0,0 +1,225 @@
+use std::f32::consts::PI;
+
+use crate::interpolation::{find_circle, interpolate_bezier, interpolate_catmull};
+
+use super::hit_object::SliderCurveType;
+
+/// Roughly the distance in osu!pixels between two sampled points of a curved slider segment.
+const SAMPLE_DISTANCE: f32 = 2.0;
+/// The number of points sampled for each segment of a catmull slider.
+const CATMULL_SAMPLES: usize = 50;
+
+/// The approximated path a slider ball travels along, in osu!pixels.
+///
+/// The path is cut off or extended so that its length matches the length given in the beatmap.
+#[derive(Debug, Clone, Default)]
+pub struct SliderPath {
+    points: Vec<(f32, f32)>,
+    cumulative_lengths: Vec<f32>,
+}
+
+impl SliderPath {
+    /// Approximates the path of a slider.
+    /// `control_points` includes the slider's start position as its first point.
+    pub fn new(curve_type: SliderCurveType, control_points: &[(f32, f32)], length: f64) -> Self {
+        let mut points = vec![];
+        for segment in split_segments(control_points) {
+            let approximated = match curve_type {
+                SliderCurveType::Linear => segment.to_vec(),
+                SliderCurveType::PerfectCircle if control_points.len() == 3 => {
+                    approximate_circle(segment).unwrap_or_else(|| approximate_bezier(segment))
+                }
+                SliderCurveType::CentripetalCatmullRom => approximate_catmull(segment),
+                _ => approximate_bezier(segment),
+            };
+            for point in approximated {
+                if points.last() != Some(&point) {
+                    points.push(point);
+                }
+            }
+        }
+        if points.is_empty() {
+            points.extend(control_points.first().copied());
+        }
+
+        let mut path = Self {
+            points,
+            cumulative_lengths: vec![],
+        };
+        path.fit_to_length(length as f32);
+        path
+    }
+
+    /// The sampled points along the path.
+    pub fn points(&self) -> &[(f32, f32)] {
+        &self.points
+    }
+
+    /// The length of the path in osu!pixels.
+    pub fn length(&self) -> f32 {
+        self.cumulative_lengths.last().copied().unwrap_or(0.0)
+    }
+
+    /// The position along the path, where `progress` is between 0 (the start) and 1 (the end).
+    pub fn position_at(&self, progress: f64) -> (f32, f32) {
+        if self.points.len() <= 1 {
+            return self.points.first().copied().unwrap_or_default();
+        }
+
+        let distance = self.length() * progress.clamp(0.0, 1.0) as f32;
+        let i = self
+            .cumulative_lengths
+            .partition_point(|&len| len < distance)
+            .clamp(1, self.points.len() - 1);
+
+        let (start, end) = (self.points[i - 1], self.points[i]);
+        let segment_start = self.cumulative_lengths[i - 1];
+        let segment_length = self.cumulative_lengths[i] - segment_start;
+        if segment_length <= 0.0 {
+            return start;
+        }
+
+        let lambda = (distance - segment_start) / segment_length;
+        (
+            start.0 + (end.0 - start.0) * lambda,
+            start.1 + (end.1 - start.1) * lambda,
+        )
+    }
+
+    /// Applies a transformation to every point of the path.
+    /// The transformation must preserve distances (e.g. a reflection), since lengths are not recalculated.
+    pub fn transform(&mut self, f: impl Fn((f32, f32)) -> (f32, f32)) {
+        self.points.iter_mut().for_each(|point| *point = f(*point));
+    }
+
+    /// Cuts off or linearly extends the path so its length matches the slider length given in the beatmap.
+    fn fit_to_length(&mut self, length: f32) {
+        self.cumulative_lengths = Vec::with_capacity(self.points.len());
+        let mut total = 0.0;
+        self.cumulative_lengths.push(0.0);
+        for pair in self.points.windows(2) {
+            total += distance(pair[0], pair[1]);
+            self.cumulative_lengths.push(total);
+        }
+
+        if self.points.len() < 2 || length <= 0.0 {
+            return;
+        }
+
+        // Remove all points past the expected length
+        while self.points.len() > 2 && self.cumulative_lengths[self.points.len() - 2] >= length {
+            self.points.pop();
+            self.cumulative_lengths.pop();
+        }
+
+        // Move the last point so the last segment ends exactly at the expected length
+        let n = self.points.len();
+        let (start, end) = (self.points[n - 2], self.points[n - 1]);
+        let segment_length = distance(start, end);
+        if segment_length > 0.0 {
+            let remaining = length - self.cumulative_lengths[n - 2];
+            let lambda = remaining / segment_length;
+            self.points[n - 1] = (
+                start.0 + (end.0 - start.0) * lambda,
+                start.1 + (end.1 - start.1) * lambda,
+            );
+            self.cumulative_lengths[n - 1] = length;
+        }
+    }
+}
+
+pub fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+/// Splits the control points at "red anchors", which are denoted by two consecutive identical points.
+fn split_segments(control_points: &[(f32, f32)]) -> Vec<&[(f32, f32)]> {
+    let mut segments = vec![];
+    let mut start = 0;
+    for i in 1..control_points.len() {
+        if control_points[i] == control_points[i - 1] {
+            segments.push(&control_points[start..i]);
+            start = i;
+        }
+    }
+    segments.push(&control_points[start..]);
+    segments.into_iter().filter(|seg| seg.len() > 1).collect()
+}
+
+fn approximate_bezier(points: &[(f32, f32)]) -> Vec<(f32, f32)> {
+    let polygon_length = points.windows(2).map(|p| distance(p[0], p[1])).sum::<f32>();
+    let steps = ((polygon_length / SAMPLE_DISTANCE).ceil() as usize).max(1);
+
+    (0..=steps)
+        .map(|i| interpolate_bezier::<f32, f32>(points, i as f32 / steps as f32))
+        .collect()
+}
+
+fn approximate_catmull(points: &[(f32, f32)]) -> Vec<(f32, f32)> {
+    let mut approximated = vec![];
+    for i in 0..points.len() - 1 {
+        let v1 = if i > 0 { points[i - 1] } else { points[i] };
+        let v2 = points[i];
+        let v3 = if i + 1 < points.len() {
+            points[i + 1]
+        } else {
+            (2.0 * v2.0 - v1.0, 2.0 * v2.1 - v1.1)
+        };
+        let v4 = if i + 2 < points.len() {
+            points[i + 2]
+        } else {
+            (2.0 * v3.0 - v2.0, 2.0 * v3.1 - v2.1)
+        };
+
+        for c in 0..CATMULL_SAMPLES {
+            approximated.push(interpolate_catmull::<f32, f32>(
+                v1,
+                v2,
+                v3,
+                v4,
+                c as f32 / CATMULL_SAMPLES as f32,
+            ));
+        }
+    }
+    approximated.extend(points.last().copied());
+    approximated
+}
+
+/// Approximates a perfect circle arc through the three given points.
+/// Returns `None` if the points are (almost) collinear, in which case osu! falls back to a bezier curve.
+fn approximate_circle(points: &[(f32, f32)]) -> Option<Vec<(f32, f32)>> {
+    let (a, b, c) = (points[0], points[1], points[2]);
+    let cross = (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0);
+    if cross.abs() < 1e-3 {
+        return None;
+    }
+
+    let (center, radius) = find_circle::<f32, f32>(a, b, c);
+    let theta_start = (a.1 - center.1).atan2(a.0 - center.0);
+    let mut theta_end = (c.1 - center.1).atan2(c.0 - center.0);
+    while theta_end < theta_start {
+        theta_end += 2.0 * PI;
+    }
+
+    // Decide in which direction to draw the arc, depending on which side of AC the point B lies
+    let mut direction = 1.0;
+    let mut theta_range = theta_end - theta_start;
+    let ortho_a_to_c = (c.1 - a.1, a.0 - c.0);
+    if ortho_a_to_c.0 * (b.0 - a.0) + ortho_a_to_c.1 * (b.1 - a.1) < 0.0 {
+        direction = -1.0;
+        theta_range = 2.0 * PI - theta_range;
+    }
+
+    let steps = ((theta_range * radius / SAMPLE_DISTANCE).ceil() as usize).max(2);
+    Some(
+        (0..=steps)
+            .map(|i| {
+                let theta = theta_start + direction * theta_range * i as f32 / steps as f32;
+                (
+                    center.0 + theta.cos() * radius,
+                    center.1 + theta.sin() * radius,
+                )
+            })
+            .collect(),
+    )
+}
@@ -0,0 +1,301 @@
+use super::{hit_object::HitObjectData, slider_path::distance, Beatmap};
+
+/// Objects closer than this distance (in osu!pixels) are considered to be on top of each other.
+const STACK_DISTANCE: f32 = 3.0;
+
+impl Beatmap {
+    /// Assigns stack heights to all objects, replicating osu!'s stacking of overlapping objects.
+    ///
+    /// Stacking depends on the approach rate and the stack leniency, so this needs to be called
+    /// again whenever the difficulty changes.
+    pub fn apply_stacking(&mut self) {
+        self.hit_objects
+            .iter_mut()
+            .for_each(|obj| obj.set_stack_height(0));
+
+        if self.format_version >= 6 {
+            if !self.hit_objects.is_empty() {
+                self.apply_stacking_modern(0, self.hit_objects.len() - 1);
+            }
+        } else {
+            self.apply_stacking_old();
+        }
+    }
+
+    fn stack_threshold(&self) -> f64 {
+        self.difficulty.preempt() * self.general.stack_leniency()
+    }
+
+    /// The stacking algorithm for beatmaps of format version 6 and above, applied to the objects from
+    /// `start_index` to `end_index` (inclusive). Objects after the range which are stacked onto it are included as well.
+    fn apply_stacking_modern(&mut self, start_index: usize, end_index: usize) {
+        let stack_threshold = self.stack_threshold();
+        let objects = &mut self.hit_objects;
+        objects[start_index..=end_index]
+            .iter_mut()
+            .for_each(|obj| obj.set_stack_height(0));
+
+        // Forward pass to extend the range to the end of stacks which continue after it
+        let mut extended_end_index = end_index;
+        if end_index < objects.len() - 1 {
+            for i in (start_index..=end_index).rev() {
+                let mut stack_base = i;
+                for n in stack_base + 1..objects.len() {
+                    if objects[stack_base].is_spinner() {
+                        break;
+                    }
+                    if objects[n].is_spinner() {
+                        continue;
+                    }
+
+                    if objects[n].timestamp() as f64 - objects[stack_base].end_time()
+                        > stack_threshold
+                    {
+                        // We are no longer within stacking range of the next object
+                        break;
+                    }
+
+                    let base = &objects[stack_base];
+                    let position = objects[n].position();
+                    if distance(base.position(), position) < STACK_DISTANCE
+                        || (base.is_slider()
+                            && distance(base.end_position(), position) < STACK_DISTANCE)
+                    {
+                        stack_base = n;
+                        // Objects after the range haven't been reset yet
+                        objects[n].set_stack_height(0);
+                    }
+                }
+
+                if stack_base > extended_end_index {
+                    extended_end_index = stack_base;
+                    if extended_end_index == objects.len() - 1 {
+                        break;
+                    }
+                }
+            }
+        }
+
+        // Reverse pass for stack calculation
+        let mut extended_start_index = start_index;
+        for i in (start_index + 1..=extended_end_index).rev() {
+            let mut n = i;
+            let mut i_obj = i;
+
+            if objects[i_obj].stack_height() != 0 || objects[i_obj].is_spinner() {
+                continue;
+            }
+
+            if objects[i_obj].is_circle() {
+                // This either ends with a stack of circles only, or a stack of circles that are underneath a slider
+                while n > 0 {
+                    n -= 1;
+                    if objects[n].is_spinner() {
+                        continue;
+                    }
+
+                    if objects[i_obj].timestamp() as f64 - objects[n].end_time() > stack_threshold {
+                        // We are no longer within stacking range of the previous object
+                        break;
+                    }
+
+                    // Objects before the range haven't been reset yet
+                    if n < extended_start_index {
+                        objects[n].set_stack_height(0);
+                        extended_start_index = n;
+                    }
+
+                    // Circles under the *last* slider in a stacked pattern are moved down and to the right
+                    if objects[n].is_slider()
+                        && distance(objects[n].end_position(), objects[i_obj].position())
+                            < STACK_DISTANCE
+                    {
+                        let offset = objects[i_obj].stack_height() - objects[n].stack_height() + 1;
+                        let slider_end = objects[n].end_position();
+                        for obj in &mut objects[n + 1..=i] {
+                            if distance(slider_end, obj.position()) < STACK_DISTANCE {
+                                obj.set_stack_height(obj.stack_height() - offset);
+                            }
+                        }
+
+                        // The slider becomes the new base of the stack in a later iteration
+                        break;
+                    }
+
+                    if distance(objects[n].position(), objects[i_obj].position()) < STACK_DISTANCE {
+                        let height = objects[i_obj].stack_height() + 1;
+                        objects[n].set_stack_height(height);
+                        i_obj = n;
+                    }
+                }
+            } else if objects[i_obj].is_slider() {
+                // We have hit the first slider in a possible stack. From here on we always stack upwards.
+                while n > start_index {
+                    n -= 1;
+                    if objects[n].is_spinner() {
+                        continue;
+                    }
+
+                    if objects[i_obj].timestamp() as f64 - objects[n].timestamp() as f64
+                        > stack_threshold
+                    {
+                        break;
+                    }
+
+                    if distance(objects[n].end_position(), objects[i_obj].position())
+                        < STACK_DISTANCE
+                    {
+                        let height = objects[i_obj].stack_height() + 1;
+                        objects[n].set_stack_height(height);
+                        i_obj = n;
+                    }
+                }
+            }
+        }
+    }
+
+    /// The stacking algorithm used for beatmaps before format version 6.
+    fn apply_stacking_old(&mut self) {
+        let stack_threshold = self.stack_threshold();
+        let objects = &mut self.hit_objects;
+
+        for i in 0..objects.len() {
+            if objects[i].stack_height() != 0 && !objects[i].is_slider() {
+                continue;
+            }
+
+            let mut start_time = objects[i].end_time();
+            let mut slider_stack = 0;
+            // Old maps use the end of the slider path regardless of the number of slides
+            let end_position = match objects[i].object_data() {
+                HitObjectData::Slider(slider) => slider.path().position_at(1.0),
+                _ => objects[i].position(),
+            };
+
+            for j in i + 1..objects.len() {
+                if objects[j].timestamp() as f64 - stack_threshold > start_time {
+                    break;
+                }
+
+                if distance(objects[j].position(), objects[i].position()) < STACK_DISTANCE {
+                    let height = objects[i].stack_height() + 1;
+                    objects[i].set_stack_height(height);
+                    start_time = objects[j].end_time();
+                } else if distance(objects[j].position(), end_position) < STACK_DISTANCE {
+                    // Objects stacked on slider ends are moved down and to the right instead
+                    slider_stack += 1;
+                    let height = objects[j].stack_height() - slider_stack;
+                    objects[j].set_stack_height(height);
+                    start_time = objects[j].end_time();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{map::Beatmap, test_data::TestMap};
+
+    fn beatmap(format_version: u8, hit_objects: &[&str]) -> Beatmap {
+        TestMap {
+            format_version,
+            ..TestMap::new(hit_objects)
+        }
+        .build()
+    }
+
+    fn stack_heights(map: &Beatmap) -> Vec<i32> {
+        map.hit_objects()
+            .iter()
+            .map(|obj| obj.stack_height())
+            .collect()
+    }
+
+    #[test]
+    fn old_stacking_continues_after_slider_end() {
+        // The last circle is only within the stack threshold of the slider's end, not its start
+        let map = beatmap(
+            5,
+            &[
+                "100,192,500,1,0",
+                "100,192,1000,2,0,L|380:192,1,280",
+                "100,192,2500,1,0",
+            ],
+        );
+        assert_eq!(map.hit_objects()[1].end_time().round(), 2000.0);
+        assert_eq!(stack_heights(&map), [2, 1, 0]);
+    }
+
+    #[test]
+    fn old_stacking_moves_objects_on_slider_ends_down() {
+        let map = beatmap(
+            5,
+            &[
+                "100,192,1000,2,0,L|380:192,1,280",
+                "380,192,2200,1,0",
+                "380,192,2400,1,0",
+            ],
+        );
+        assert_eq!(stack_heights(&map), [0, -1, -2]);
+    }
+
+    #[test]
+    fn modern_stacking() {
+        let map = beatmap(
+            14,
+            &[
+                "100,192,1000,2,0,L|380:192,1,280",
+                "380,192,2200,1,0",
+                "256,192,3000,1,0",
+                "256,192,3200,1,0",
+                "256,192,3400,1,0",
+            ],
+        );
+        assert_eq!(stack_heights(&map), [0, -1, 2, 1, 0]);
+    }
+
+    #[test]
+    fn modern_stacking_stops_at_stack_threshold() {
+        let map = beatmap(
+            14,
+            &["256,192,1000,1,0", "256,192,1900,1,0", "256,192,2100,1,0"],
+        );
+        assert_eq!(stack_heights(&map), [0, 1, 0]);
+    }
+
+    #[test]
+    fn modern_stacking_chains_beyond_stack_threshold() {
+        // Each circle is within the stack threshold of the next, but the chain spans 2100ms
+        let map = beatmap(
+            14,
+            &[
+                "256,192,1000,1,0",
+                "256,192,1700,1,0",
+                "256,192,2400,1,0",
+                "256,192,3100,1,0",
+            ],
+        );
+        assert_eq!(stack_heights(&map), [3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn modern_stacking_extends_range_to_stacked_objects() {
+        let mut map = beatmap(
+            14,
+            &[
+                "256,192,1000,1,0",
+                "256,192,1700,1,0",
+                "256,192,2400,1,0",
+                "256,192,3100,1,0",
+            ],
+        );
+        map.hit_objects
+            .iter_mut()
+            .for_each(|obj| obj.set_stack_height(0));
+
+        // The forward pass includes the rest of the stack, which continues after the range
+        map.apply_stacking_modern(0, 1);
+        assert_eq!(stack_heights(&map), [3, 2, 1, 0]);
+    }
+}
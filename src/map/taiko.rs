@@ -0,0 +1,296 @@
+use crate::osu_data::{GameMode, Hitsound};
+
+use super::{difficulty::difficulty_range, hit_object::HitObjectData, Beatmap};
+
+/// Scales the number of hits a swell requires compared to the rotations of a spinner.
+const SWELL_HIT_MULTIPLIER: f64 = 1.65;
+
+/// The colour of a taiko note, which decides the keys it is hit with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TaikoHitKind {
+    /// Red notes, hit on the centre of the drum
+    Don,
+    /// Blue notes, hit on the rim of the drum
+    Kat,
+}
+
+impl TaikoHitKind {
+    /// Notes with a whistle or clap are kats, all others dons.
+    pub fn from_hitsound(hitsound: Hitsound) -> Self {
+        if hitsound.intersects(Hitsound::WHISTLE | Hitsound::CLAP) {
+            Self::Kat
+        } else {
+            Self::Don
+        }
+    }
+}
+
+/// An object as it appears in osu!taiko. All times are in milliseconds.
+#[derive(Debug, Clone, Copy)]
+pub enum TaikoObject {
+    /// A note which is hit once, or with both keys of its colour at the same time for big notes
+    Hit {
+        time: f64,
+        kind: TaikoHitKind,
+        big: bool,
+    },
+    /// A drum roll made from a slider, in which every tick can be hit for bonus score
+    DrumRoll {
+        start: f64,
+        end: f64,
+        big: bool,
+        tick_spacing: f64,
+    },
+    /// A swell made from a spinner, which requires alternating between dons and kats a number of times
+    Swell {
+        start: f64,
+        end: f64,
+        required_hits: usize,
+    },
+}
+
+impl TaikoObject {
+    pub fn start_time(&self) -> f64 {
+        match *self {
+            Self::Hit { time, .. } => time,
+            Self::DrumRoll { start, .. } | Self::Swell { start, .. } => start,
+        }
+    }
+
+    pub fn end_time(&self) -> f64 {
+        match *self {
+            Self::Hit { time, .. } => time,
+            Self::DrumRoll { end, .. } | Self::Swell { end, .. } => end,
+        }
+    }
+
+    /// The times of the ticks of a drum roll. Empty for all other objects.
+    pub fn ticks(&self) -> Vec<f64> {
+        match *self {
+            Self::DrumRoll {
+                start,
+                end,
+                tick_spacing,
+                ..
+            } if tick_spacing > 0.0 => (0..)
+                .map(|i| start + i as f64 * tick_spacing)
+                .take_while(|&time| time < end + tick_spacing / 2.0)
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+impl Beatmap {
+    /// The objects of this beatmap as they are played in osu!taiko, ordered by time.
+    ///
+    /// Beatmaps made for other modes are converted like osu!stable does it,
+    /// which turns short sliders into streams of notes instead of drum rolls.
+    pub fn taiko_objects(&self) -> Vec<TaikoObject> {
+        let is_taiko_map = matches!(self.general.mode(), GameMode::Taiko);
+        let tick_rate = self.difficulty.slider_tick_rate() as f64;
+        let mut objects = vec![];
+
+        for obj in &self.hit_objects {
+            let start = obj.timestamp() as f64;
+            let kind = TaikoHitKind::from_hitsound(obj.hit_sound());
+            let big = obj.hit_sound().contains(Hitsound::FINISH);
+
+            match obj.object_data() {
+                HitObjectData::Circle | HitObjectData::Hold(_) => objects.push(TaikoObject::Hit {
+                    time: start,
+                    kind,
+                    big,
+                }),
+                HitObjectData::Slider(slider) => {
+                    let spans = slider.slides().max(1);
+                    let duration = slider.duration().trunc();
+                    let timing_beat_length = self.beat_length_at(start);
+                    // Old beatmaps use the beat length scaled by the slider velocity for conversion
+                    let beat_length = if self.format_version >= 8 {
+                        timing_beat_length
+                    } else {
+                        timing_beat_length / self.slider_velocity_at(start)
+                    };
+                    let tick_spacing = (beat_length / tick_rate).min(duration / spans as f64);
+
+                    // Sliders which are shorter than two beats become a stream of notes, one for every tick
+                    if !is_taiko_map && tick_spacing > 0.0 && duration < 2.0 * beat_length {
+                        let mut edge_sounds = slider.edge_sounds();
+                        if edge_sounds.is_empty() {
+                            edge_sounds.push(obj.hit_sound());
+                        }
+                        let mut time = start;
+                        let mut i = 0;
+                        while time <= start + duration + tick_spacing / 8.0 {
+                            let hitsound = edge_sounds[i % edge_sounds.len()];
+                            objects.push(TaikoObject::Hit {
+                                time,
+                                kind: TaikoHitKind::from_hitsound(hitsound),
+                                big: hitsound.contains(Hitsound::FINISH),
+                            });
+                            time += tick_spacing;
+                            i += 1;
+                        }
+                    } else {
+                        let ticks_per_beat = if tick_rate == 3.0 { 3.0 } else { 4.0 };
+                        objects.push(TaikoObject::DrumRoll {
+                            start,
+                            end: start + duration,
+                            big,
+                            tick_spacing: timing_beat_length / ticks_per_beat,
+                        });
+                    }
+                }
+                HitObjectData::Spinner(_) => {
+                    let end = obj.end_time();
+                    let hits_per_second = difficulty_range(
+                        self.difficulty.overall_difficulty() as f64,
+                        3.0,
+                        5.0,
+                        7.5,
+                    ) * SWELL_HIT_MULTIPLIER;
+                    objects.push(TaikoObject::Swell {
+                        start,
+                        end,
+                        required_hits: (((end - start) / 1000.0 * hits_per_second) as usize).max(1),
+                    });
+                }
+            }
+        }
+
+        objects.sort_by(|a, b| a.start_time().total_cmp(&b.start_time()));
+        objects
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_data::TestMap;
+
+    use super::*;
+
+    fn taiko_map(hit_objects: &[&str]) -> Beatmap {
+        TestMap {
+            mode: 1,
+            ..TestMap::new(hit_objects)
+        }
+        .build()
+    }
+
+    #[test]
+    fn notes_from_hitsounds() {
+        let map = taiko_map(&[
+            "256,192,1000,1,0",
+            "256,192,1500,1,2",
+            "256,192,2000,1,8",
+            "256,192,2500,1,4",
+            "256,192,3000,1,6",
+        ]);
+        let notes = map
+            .taiko_objects()
+            .iter()
+            .map(|object| match *object {
+                TaikoObject::Hit { kind, big, .. } => (kind, big),
+                _ => panic!("not a note: {object:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            notes,
+            [
+                (TaikoHitKind::Don, false),
+                (TaikoHitKind::Kat, false),
+                (TaikoHitKind::Kat, false),
+                (TaikoHitKind::Don, true),
+                (TaikoHitKind::Kat, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn drum_roll_ticks() {
+        // Two beats long, with four ticks per beat
+        let map = taiko_map(&["256,192,1000,2,0,L|536:192,1,280"]);
+        let objects = map.taiko_objects();
+        assert!(matches!(
+            objects[..],
+            [TaikoObject::DrumRoll { start, end, big: false, .. }]
+                if start == 1000.0 && end == 2000.0
+        ));
+        let ticks = objects[0].ticks();
+        assert_eq!(ticks.len(), 9);
+        assert_eq!(ticks[1] - ticks[0], 125.0);
+    }
+
+    #[test]
+    fn swell_hits_depend_on_overall_difficulty() {
+        let required_hits = |overall_difficulty| {
+            let map = TestMap {
+                mode: 1,
+                overall_difficulty,
+                ..TestMap::new(&["256,192,1000,12,0,3000"])
+            }
+            .build();
+            match map.taiko_objects()[..] {
+                [TaikoObject::Swell { required_hits, .. }] => required_hits,
+                ref objects => panic!("not a swell: {objects:?}"),
+            }
+        };
+        // 5 and 7.5 hits per second for two seconds, times 1.65
+        assert_eq!(required_hits(5.0), 16);
+        assert_eq!(required_hits(10.0), 24);
+    }
+
+    #[test]
+    fn short_sliders_are_converted_to_notes() {
+        // One beat long, so it becomes a note at its head and its end with the sounds of its edges
+        let map = TestMap::new(&["256,192,1000,2,0,L|396:192,1,140,2|4"]).build();
+        let notes = map
+            .taiko_objects()
+            .iter()
+            .map(|object| match *object {
+                TaikoObject::Hit { time, kind, big } => (time, kind, big),
+                _ => panic!("not a note: {object:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            notes,
+            [
+                (1000.0, TaikoHitKind::Kat, false),
+                (1500.0, TaikoHitKind::Don, true)
+            ]
+        );
+
+        // The same slider stays a drum roll in beatmaps made for osu!taiko
+        let map = taiko_map(&["256,192,1000,2,0,L|396:192,1,140,2|4"]);
+        assert!(matches!(
+            map.taiko_objects()[..],
+            [TaikoObject::DrumRoll { .. }]
+        ));
+    }
+
+    #[test]
+    fn conversion_of_old_beatmaps_scales_beat_length_by_slider_velocity() {
+        // At twice the slider velocity the slider lasts one beat, which is only half a scaled beat in old beatmaps
+        let map = |format_version| {
+            TestMap {
+                format_version,
+                timing_points: vec![
+                    "0,500,4,2,0,100,1,0".to_owned(),
+                    "0,-50,4,2,0,100,0,0".to_owned(),
+                ],
+                ..TestMap::new(&["256,192,1000,2,0,L|536:192,1,280"])
+            }
+            .build()
+        };
+        assert!(matches!(
+            map(14).taiko_objects()[..],
+            [TaikoObject::Hit { time: first, .. }, TaikoObject::Hit { time: second, .. }]
+                if first == 1000.0 && second == 1500.0
+        ));
+        assert!(matches!(
+            map(7).taiko_objects()[..],
+            [TaikoObject::DrumRoll { start, end, .. }] if start == 1000.0 && end == 1500.0
+        ));
+    }
+}
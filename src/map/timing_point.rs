@@ -16,6 +16,44 @@ pub struct TimingPoint {
     effects: Effects,
 }
 
+impl TimingPoint {
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    /// For uninherited timing points, the length of a beat in milliseconds.
+    /// For inherited ones, a negative inverse slider velocity multiplier in percent.
+    pub fn beat_length(&self) -> f64 {
+        self.beat_length
+    }
+
+    pub fn uninherited(&self) -> bool {
+        self.uninherited
+    }
+
+    /// The slider velocity multiplier of inherited timing points. Uninherited ones reset it to 1.
+    pub fn slider_velocity(&self) -> f64 {
+        if self.uninherited || self.beat_length >= 0.0 {
+            1.0
+        } else {
+            (-100.0 / self.beat_length).clamp(0.1, 10.0)
+        }
+    }
+
+    pub fn effects(&self) -> Effects {
+        self.effects
+    }
+
+    /// Scales the timing point for a song played at the given rate.
+    /// Inherited timing points only store a slider velocity multiplier, which is unaffected.
+    pub fn apply_clock_rate(&mut self, clock_rate: f64) {
+        self.time = (self.time as f64 / clock_rate).round() as u64;
+        if self.uninherited {
+            self.beat_length /= clock_rate;
+        }
+    }
+}
+
 impl FromStr for TimingPoint {
     type Err = &'static str;
 
@@ -0,0 +1,61 @@
+use crate::{
+    osu_data::{GameMode, Reflection},
+    replay::Mods,
+};
+
+use super::Beatmap;
+
+impl Beatmap {
+    /// Creates the beatmap as it is played with the given mods.
+    ///
+    /// This applies the difficulty changes of Easy and Hard Rock, flips the playfield for Hard Rock and Mirror
+    /// and scales all times by the clock rate of Double Time, Nightcore and Half Time.
+    /// Note that the times of the resulting beatmap are in real time, while replay frames are recorded in song time.
+    pub fn apply_mods(&self, mods: Mods) -> Beatmap {
+        let mut map = self.apply_mods_in_song_time(mods);
+        map.apply_clock_rate(mods.clock_rate());
+        map
+    }
+
+    /// Creates the beatmap as it is played with the given mods, but keeps all times in song time.
+    ///
+    /// This is what replay frames should be compared against, since they are recorded in song time as well.
+    pub fn apply_mods_in_song_time(&self, mods: Mods) -> Beatmap {
+        let mut map = self.clone();
+        map.difficulty.apply_mods(mods);
+        if let Some(reflection) = mods.reflection() {
+            map.reflect(reflection);
+        }
+        if matches!(map.general.mode(), GameMode::Standard) {
+            map.apply_stacking();
+        }
+        map
+    }
+
+    /// Flips all objects along the given axis.
+    pub fn reflect(&mut self, reflection: Reflection) {
+        self.hit_objects
+            .iter_mut()
+            .for_each(|obj| obj.reflect(reflection));
+    }
+
+    /// Scales all times of the beatmap for a song played at the given rate.
+    /// Approach rate and overall difficulty are adjusted so preempt and hit windows stay correct in real time.
+    pub fn apply_clock_rate(&mut self, clock_rate: f64) {
+        if clock_rate == 1.0 {
+            return;
+        }
+
+        self.difficulty.apply_clock_rate(clock_rate);
+        self.break_periods
+            .iter_mut()
+            .for_each(|break_period| break_period.apply_clock_rate(clock_rate));
+        self.timing_points
+            .iter_mut()
+            .for_each(|tp| tp.apply_clock_rate(clock_rate));
+        self.hit_objects
+            .iter_mut()
+            .for_each(|obj| obj.apply_clock_rate(clock_rate));
+        self.apply_timing();
+    }
+}
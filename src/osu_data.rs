@@ -110,7 +110,7 @@ impl Hitsound {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum GameMode {
     Standard,
     Taiko,
@@ -138,6 +138,32 @@ impl TryFrom<u8> for GameMode {
     }
 }
 
+/// The width of the osu! playfield in osu!pixels.
+pub const PLAYFIELD_WIDTH: f32 = 512.0;
+/// The height of the osu! playfield in osu!pixels.
+pub const PLAYFIELD_HEIGHT: f32 = 384.0;
+
+/// An axis along which positions on the playfield can be flipped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reflection {
+    /// Flips positions left to right, like lazer's Mirror mod does by default.
+    Horizontal,
+    /// Flips positions upside down, like Hard Rock does.
+    Vertical,
+    Both,
+}
+
+impl Reflection {
+    pub fn apply(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        use Reflection::*;
+        match self {
+            Horizontal => (PLAYFIELD_WIDTH - x, y),
+            Vertical => (x, PLAYFIELD_HEIGHT - y),
+            Both => (PLAYFIELD_WIDTH - x, PLAYFIELD_HEIGHT - y),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub enum OverlayPosition {
     NoChange,
@@ -0,0 +1,631 @@
+use crate::{
+    map::Beatmap,
+    replay::{Mods, Replay},
+    star_rating::{
+        catch::CatchDifficulty, mania::ManiaDifficulty, standard::StandardDifficulty,
+        taiko::TaikoDifficulty, DifficultyAttributes,
+    },
+};
+
+/// The judgements and combo of a play, as stored in replays.
+///
+/// In osu!catch, 300s are fruits, 100s droplets, 50s tiny droplets and katus missed tiny droplets.
+/// In osu!mania, gekis are rainbow 300s and katus 200s.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HitCounts {
+    pub n_300: usize,
+    pub n_100: usize,
+    pub n_50: usize,
+    pub n_geki: usize,
+    pub n_katu: usize,
+    pub n_miss: usize,
+    pub max_combo: usize,
+}
+
+impl HitCounts {
+    pub fn from_replay(replay: &Replay) -> Self {
+        Self {
+            n_300: replay.n_300 as usize,
+            n_100: replay.n_100 as usize,
+            n_50: replay.n_50 as usize,
+            n_geki: replay.n_geki as usize,
+            n_katu: replay.n_katu as usize,
+            n_miss: replay.n_miss as usize,
+            max_combo: replay.max_combo as usize,
+        }
+    }
+
+    /// The number of judged objects, without the tiny droplets of osu!catch and the gekis and katus of osu!mania.
+    fn total(&self) -> usize {
+        self.n_300 + self.n_100 + self.n_50 + self.n_miss
+    }
+}
+
+/// The performance points (pp) a play is worth and the parts they are made up of.
+///
+/// osu!standard plays are rated by aim, speed, accuracy and flashlight (which is 0 without the Flashlight mod).
+/// All other modes are rated by how hard the beatmap is to play through (the difficulty),
+/// and osu!taiko plays additionally by accuracy.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Performance {
+    pp: f64,
+    aim: f64,
+    speed: f64,
+    accuracy: f64,
+    flashlight: f64,
+    difficulty: f64,
+}
+
+impl Performance {
+    /// Calculates the pp of a play with the given hit counts and mods,
+    /// where the attributes have to be calculated with the same mods.
+    pub fn calculate(attributes: &DifficultyAttributes, counts: HitCounts, mods: Mods) -> Self {
+        match attributes {
+            DifficultyAttributes::Standard(difficulty) => standard(difficulty, counts, mods),
+            DifficultyAttributes::Taiko(difficulty) => taiko(difficulty, counts, mods),
+            DifficultyAttributes::Catch(difficulty) => catch(difficulty, counts, mods),
+            DifficultyAttributes::Mania(difficulty) => mania(difficulty, counts, mods),
+        }
+    }
+
+    /// Calculates the pp of the play recorded in the replay on the given (unmodified) beatmap.
+    pub fn for_replay(map: &Beatmap, replay: &Replay) -> Self {
+        let attributes = DifficultyAttributes::calculate(map, replay.mode, replay.mods);
+        Self::calculate(&attributes, HitCounts::from_replay(replay), replay.mods)
+    }
+
+    /// The pp the play would have been worth if every miss had been a 300 and the combo had never broken.
+    pub fn if_fc(attributes: &DifficultyAttributes, counts: HitCounts, mods: Mods) -> Self {
+        let counts = match attributes {
+            // Missed fruits and droplets can't be told apart, so they are caught as far as the beatmap has them
+            DifficultyAttributes::Catch(difficulty) => HitCounts {
+                n_300: counts.n_300.max(difficulty.n_fruits()),
+                n_100: counts.n_100.max(difficulty.n_droplets()),
+                n_miss: 0,
+                ..counts
+            },
+            DifficultyAttributes::Mania(_) => HitCounts {
+                n_geki: counts.n_geki + counts.n_miss,
+                n_miss: 0,
+                ..counts
+            },
+            _ => HitCounts {
+                n_300: counts.n_300 + counts.n_miss,
+                n_miss: 0,
+                ..counts
+            },
+        };
+        Self::calculate(
+            attributes,
+            HitCounts {
+                max_combo: attributes.max_combo(),
+                ..counts
+            },
+            mods,
+        )
+    }
+
+    /// The pp of a perfect play with the given mods.
+    pub fn if_ss(attributes: &DifficultyAttributes, mods: Mods) -> Self {
+        let max_combo = attributes.max_combo();
+        let counts = match attributes {
+            DifficultyAttributes::Standard(difficulty) => HitCounts {
+                n_300: difficulty.n_circles() + difficulty.n_sliders() + difficulty.n_spinners(),
+                max_combo,
+                ..Default::default()
+            },
+            DifficultyAttributes::Taiko(_) => HitCounts {
+                n_300: max_combo,
+                max_combo,
+                ..Default::default()
+            },
+            DifficultyAttributes::Catch(difficulty) => HitCounts {
+                n_300: difficulty.n_fruits(),
+                n_100: difficulty.n_droplets(),
+                n_50: difficulty.n_tiny_droplets(),
+                max_combo,
+                ..Default::default()
+            },
+            // Holds are judged once in osu!stable
+            DifficultyAttributes::Mania(difficulty) => HitCounts {
+                n_geki: difficulty.n_notes() + difficulty.n_holds(),
+                max_combo,
+                ..Default::default()
+            },
+        };
+        Self::calculate(attributes, counts, mods)
+    }
+
+    /// The total pp.
+    pub fn pp(&self) -> f64 {
+        self.pp
+    }
+
+    /// The pp for aim in osu!standard.
+    pub fn aim(&self) -> f64 {
+        self.aim
+    }
+
+    /// The pp for speed in osu!standard.
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// The pp for accuracy in osu!standard and osu!taiko.
+    pub fn accuracy(&self) -> f64 {
+        self.accuracy
+    }
+
+    /// The pp for playing osu!standard with Flashlight.
+    pub fn flashlight(&self) -> f64 {
+        self.flashlight
+    }
+
+    /// The pp for the difficulty of the beatmap in osu!taiko, osu!catch and osu!mania.
+    pub fn difficulty(&self) -> f64 {
+        self.difficulty
+    }
+}
+
+fn standard(difficulty: &StandardDifficulty, counts: HitCounts, mods: Mods) -> Performance {
+    let total_hits = counts.total() as f64;
+    if total_hits == 0.0 {
+        return Performance::default();
+    }
+    let accuracy =
+        (300 * counts.n_300 + 100 * counts.n_100 + 50 * counts.n_50) as f64 / (300.0 * total_hits);
+    let approach_rate = difficulty.approach_rate() as f64;
+    let overall_difficulty = difficulty.overall_difficulty() as f64;
+    let mut effective_miss_count = effective_miss_count(difficulty, counts);
+
+    let mut multiplier = 1.14;
+    if mods.contains(Mods::NO_FAIL) {
+        multiplier *= f64::max(0.9, 1.0 - 0.02 * effective_miss_count);
+    }
+    if mods.contains(Mods::SPUN_OUT) {
+        multiplier *= 1.0 - (difficulty.n_spinners() as f64 / total_hits).powf(0.85);
+    }
+    let relax = mods.contains(Mods::RELAX);
+    if relax {
+        // Relax can't miss, but 100s and 50s are most likely misses that were saved by it
+        let (ok_multiplier, meh_multiplier) = if overall_difficulty > 0.0 {
+            (
+                (1.0 - (overall_difficulty / 13.33).powf(1.8)).max(0.0),
+                (1.0 - (overall_difficulty / 13.33).powi(5)).max(0.0),
+            )
+        } else {
+            (1.0, 1.0)
+        };
+        effective_miss_count = f64::min(
+            effective_miss_count
+                + counts.n_100 as f64 * ok_multiplier
+                + counts.n_50 as f64 * meh_multiplier,
+            total_hits,
+        );
+    }
+
+    let length_bonus = 0.95
+        + 0.4 * f64::min(1.0, total_hits / 2000.0)
+        + if total_hits > 2000.0 {
+            (total_hits / 2000.0).log10() * 0.5
+        } else {
+            0.0
+        };
+    let miss_penalty = |exponent: f64| {
+        if effective_miss_count > 0.0 {
+            0.97 * (1.0 - (effective_miss_count / total_hits).powf(0.775))
+                .powf(effective_miss_count.powf(exponent))
+        } else {
+            1.0
+        }
+    };
+    let combo_scaling = combo_scaling(counts.max_combo, difficulty.max_combo());
+    let hidden_bonus = if mods.contains(Mods::HIDDEN) {
+        1.0 + 0.04 * (12.0 - approach_rate)
+    } else {
+        1.0
+    };
+
+    let mut aim = base_performance(difficulty.aim()) * length_bonus;
+    aim *= miss_penalty(1.0) * combo_scaling;
+    let approach_rate_factor = if relax {
+        0.0
+    } else if approach_rate > 10.33 {
+        0.3 * (approach_rate - 10.33)
+    } else if approach_rate < 8.0 {
+        0.05 * (8.0 - approach_rate)
+    } else {
+        0.0
+    };
+    aim *= 1.0 + approach_rate_factor * length_bonus;
+    aim *= hidden_bonus;
+    // Players who drop slider ends likely don't follow the hard sliders the aim rating expects
+    if difficulty.n_sliders() > 0 {
+        let difficult_sliders = difficulty.n_sliders() as f64 * 0.15;
+        let dropped_slider_ends = ((counts.n_100 + counts.n_50 + counts.n_miss)
+            .min(difficulty.max_combo().saturating_sub(counts.max_combo))
+            as f64)
+            .clamp(0.0, difficult_sliders);
+        let slider_factor = difficulty.slider_factor();
+        aim *= (1.0 - slider_factor) * (1.0 - dropped_slider_ends / difficult_sliders).powi(3)
+            + slider_factor;
+    }
+    aim *= accuracy * (0.98 + overall_difficulty.powi(2) / 2500.0);
+
+    let speed = if relax {
+        0.0
+    } else {
+        let mut speed = base_performance(difficulty.speed()) * length_bonus;
+        speed *= miss_penalty(0.875) * combo_scaling;
+        let approach_rate_factor = if approach_rate > 10.33 {
+            0.3 * (approach_rate - 10.33)
+        } else {
+            0.0
+        };
+        speed *= 1.0 + approach_rate_factor * length_bonus;
+        speed *= hidden_bonus;
+
+        // Only the accuracy on the notes which are hard to tap quickly counts
+        let speed_notes = difficulty.speed_note_count();
+        let relevant_diff = total_hits - speed_notes;
+        let relevant_300 = (counts.n_300 as f64 - relevant_diff).max(0.0);
+        let relevant_100 =
+            (counts.n_100 as f64 - (relevant_diff - counts.n_300 as f64).max(0.0)).max(0.0);
+        let relevant_50 = (counts.n_50 as f64
+            - (relevant_diff - (counts.n_300 + counts.n_100) as f64).max(0.0))
+        .max(0.0);
+        let relevant_accuracy = if speed_notes == 0.0 {
+            0.0
+        } else {
+            (relevant_300 * 6.0 + relevant_100 * 2.0 + relevant_50) / (speed_notes * 6.0)
+        };
+        speed *= (0.95 + overall_difficulty.powi(2) / 750.0)
+            * ((accuracy + relevant_accuracy) / 2.0)
+                .powf((14.5 - overall_difficulty.max(8.0)) / 2.0);
+        let n_50 = counts.n_50 as f64;
+        if n_50 >= total_hits / 500.0 {
+            speed *= 0.99f64.powf(n_50 - total_hits / 500.0);
+        }
+        speed
+    };
+
+    let accuracy_value = if relax {
+        0.0
+    } else {
+        // Slider heads only have a hit window with ScoreV2
+        let mut objects_with_accuracy = difficulty.n_circles();
+        if mods.contains(Mods::SCORE_V2) {
+            objects_with_accuracy += difficulty.n_sliders();
+        }
+        let objects_with_accuracy = objects_with_accuracy as f64;
+        let better_accuracy = if objects_with_accuracy > 0.0 {
+            (((counts.n_300 as f64 - (total_hits - objects_with_accuracy)) * 6.0
+                + counts.n_100 as f64 * 2.0
+                + counts.n_50 as f64)
+                / (objects_with_accuracy * 6.0))
+                .max(0.0)
+        } else {
+            0.0
+        };
+        let mut value = 1.52163f64.powf(overall_difficulty) * better_accuracy.powi(24) * 2.83;
+        value *= f64::min(1.15, (objects_with_accuracy / 1000.0).powf(0.3));
+        if mods.contains(Mods::HIDDEN) {
+            value *= 1.08;
+        }
+        if mods.contains(Mods::FLASHLIGHT) {
+            value *= 1.02;
+        }
+        value
+    };
+
+    let flashlight = if mods.contains(Mods::FLASHLIGHT) {
+        let mut flashlight = difficulty.flashlight().powi(2) * 25.0;
+        flashlight *= miss_penalty(0.875) * combo_scaling;
+        flashlight *= 0.7
+            + 0.1 * f64::min(1.0, total_hits / 200.0)
+            + if total_hits > 200.0 {
+                0.2 * f64::min(1.0, (total_hits - 200.0) / 200.0)
+            } else {
+                0.0
+            };
+        flashlight * (0.5 + accuracy / 2.0) * (0.98 + overall_difficulty.powi(2) / 2500.0)
+    } else {
+        0.0
+    };
+
+    Performance {
+        pp: norm(1.1, &[aim, speed, accuracy_value, flashlight]) * multiplier,
+        aim,
+        speed,
+        accuracy: accuracy_value,
+        flashlight,
+        difficulty: 0.0,
+    }
+}
+
+/// The number of misses a play is treated as, which includes slider breaks guessed from the combo.
+fn effective_miss_count(difficulty: &StandardDifficulty, counts: HitCounts) -> f64 {
+    let mut combo_based_miss_count = 0.0;
+    if difficulty.n_sliders() > 0 {
+        let full_combo_threshold =
+            difficulty.max_combo() as f64 - 0.1 * difficulty.n_sliders() as f64;
+        if (counts.max_combo as f64) < full_combo_threshold {
+            combo_based_miss_count = full_combo_threshold / counts.max_combo.max(1) as f64;
+        }
+    }
+    let combo_based_miss_count =
+        combo_based_miss_count.min((counts.n_100 + counts.n_50 + counts.n_miss) as f64);
+    combo_based_miss_count.max(counts.n_miss as f64)
+}
+
+fn taiko(difficulty: &TaikoDifficulty, counts: HitCounts, mods: Mods) -> Performance {
+    let total_hits = counts.total() as f64;
+    if total_hits == 0.0 {
+        return Performance::default();
+    }
+    let accuracy = (counts.n_300 as f64 + 0.5 * counts.n_100 as f64) / total_hits;
+    let stars = difficulty.stars();
+
+    let mut multiplier = 1.13;
+    if mods.contains(Mods::HIDDEN) {
+        multiplier *= 1.075;
+    }
+    if mods.contains(Mods::EASY) {
+        multiplier *= 0.975;
+    }
+
+    let mut strain = (5.0 * f64::max(1.0, stars / 0.115) - 4.0).powf(2.25) / 1150.0;
+    let length_bonus = 1.0 + 0.1 * f64::min(1.0, total_hits / 1500.0);
+    strain *= length_bonus * 0.986f64.powi(counts.n_miss as i32);
+    if mods.contains(Mods::EASY) {
+        strain *= 0.985;
+    }
+    if mods.contains(Mods::HIDDEN) {
+        strain *= 1.025;
+    }
+    if mods.contains(Mods::HARD_ROCK) {
+        strain *= 1.05;
+    }
+    if mods.contains(Mods::FLASHLIGHT) {
+        strain *= 1.05 * length_bonus;
+    }
+    strain *= accuracy.powi(2);
+
+    let great_hit_window = difficulty.great_hit_window();
+    let accuracy_value = if great_hit_window > 0.0 {
+        let mut value =
+            (60.0 / great_hit_window).powf(1.1) * accuracy.powi(8) * stars.powf(0.4) * 27.0;
+        let length_bonus = f64::min(1.15, (total_hits / 1500.0).powf(0.3));
+        value *= length_bonus;
+        // Hidden and Flashlight together mean the notes have to be memorised
+        if mods.contains(Mods::HIDDEN | Mods::FLASHLIGHT) {
+            value *= f64::max(1.05, 1.075 * length_bonus);
+        }
+        value
+    } else {
+        0.0
+    };
+
+    Performance {
+        pp: norm(1.1, &[strain, accuracy_value]) * multiplier,
+        accuracy: accuracy_value,
+        difficulty: strain,
+        ..Default::default()
+    }
+}
+
+fn catch(difficulty: &CatchDifficulty, counts: HitCounts, mods: Mods) -> Performance {
+    let combo_hits = (counts.n_300 + counts.n_100 + counts.n_miss) as f64;
+    let all_hits = counts.total() + counts.n_katu;
+    if all_hits == 0 {
+        return Performance::default();
+    }
+    let accuracy = (counts.n_300 + counts.n_100 + counts.n_50) as f64 / all_hits as f64;
+    let approach_rate = difficulty.approach_rate() as f64;
+
+    let mut value = (5.0 * f64::max(1.0, difficulty.stars() / 0.0049) - 4.0).powi(2) / 100000.0;
+    let length_bonus = 0.95
+        + 0.3 * f64::min(1.0, combo_hits / 2500.0)
+        + if combo_hits > 2500.0 {
+            (combo_hits / 2500.0).log10() * 0.475
+        } else {
+            0.0
+        };
+    value *= length_bonus * 0.97f64.powi(counts.n_miss as i32);
+    value *= combo_scaling(counts.max_combo, difficulty.max_combo());
+
+    let mut approach_rate_factor = 1.0;
+    if approach_rate > 9.0 {
+        approach_rate_factor += 0.1 * (approach_rate - 9.0);
+    }
+    if approach_rate > 10.0 {
+        approach_rate_factor += 0.1 * (approach_rate - 10.0);
+    } else if approach_rate < 8.0 {
+        approach_rate_factor += 0.025 * (8.0 - approach_rate);
+    }
+    value *= approach_rate_factor;
+
+    if mods.contains(Mods::HIDDEN) {
+        value *= if approach_rate <= 10.0 {
+            1.05 + 0.075 * (10.0 - approach_rate)
+        } else {
+            1.01 + 0.04 * (11.0 - approach_rate.min(11.0))
+        };
+    }
+    if mods.contains(Mods::FLASHLIGHT) {
+        value *= 1.35 * length_bonus;
+    }
+    value *= accuracy.powf(5.5);
+    if mods.contains(Mods::NO_FAIL) {
+        value *= 0.9;
+    }
+
+    Performance {
+        pp: value,
+        difficulty: value,
+        ..Default::default()
+    }
+}
+
+fn mania(difficulty: &ManiaDifficulty, counts: HitCounts, mods: Mods) -> Performance {
+    let total_hits = counts.total() + counts.n_geki + counts.n_katu;
+    if total_hits == 0 {
+        return Performance::default();
+    }
+    let accuracy = (320 * counts.n_geki
+        + 300 * counts.n_300
+        + 200 * counts.n_katu
+        + 100 * counts.n_100
+        + 50 * counts.n_50) as f64
+        / (320 * total_hits) as f64;
+
+    let mut multiplier = 8.0;
+    if mods.contains(Mods::NO_FAIL) {
+        multiplier *= 0.75;
+    }
+    if mods.contains(Mods::EASY) {
+        multiplier *= 0.5;
+    }
+
+    let value = f64::max(difficulty.stars() - 0.15, 0.05).powf(2.2)
+        * f64::max(0.0, 5.0 * accuracy - 4.0)
+        * (1.0 + 0.1 * f64::min(1.0, total_hits as f64 / 1500.0));
+
+    Performance {
+        pp: value * multiplier,
+        difficulty: value,
+        ..Default::default()
+    }
+}
+
+/// The pp for a skill of the given rating in osu!standard.
+fn base_performance(rating: f64) -> f64 {
+    (5.0 * f64::max(1.0, rating / 0.0675) - 4.0).powi(3) / 100000.0
+}
+
+/// How much of the pp a play with the given combo keeps.
+fn combo_scaling(combo: usize, max_combo: usize) -> f64 {
+    if max_combo == 0 {
+        1.0
+    } else {
+        f64::min((combo as f64 / max_combo as f64).powf(0.8), 1.0)
+    }
+}
+
+/// The p-norm of the values, which weighs larger values more when combining them.
+fn norm(p: f64, values: &[f64]) -> f64 {
+    values
+        .iter()
+        .map(|value| value.powf(p))
+        .sum::<f64>()
+        .powf(1.0 / p)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{autoplay::Autoplay, osu_data::GameMode, test_data};
+
+    use super::*;
+
+    const MODES: [GameMode; 4] = [
+        GameMode::Standard,
+        GameMode::Taiko,
+        GameMode::CatchTheBeat,
+        GameMode::Mania,
+    ];
+
+    /// The hit counts of a perfect play in the mode, taken from autoplay.
+    fn perfect_counts(map: &Beatmap, mode: GameMode) -> HitCounts {
+        HitCounts::from_replay(&Autoplay::new(mode, Mods::empty()).generate(map))
+    }
+
+    /// Turns some of the best judgements of the counts into worse ones.
+    fn with_mistakes(counts: HitCounts, mode: GameMode, mistakes: usize) -> HitCounts {
+        match mode {
+            // Missed tiny droplets only lower the accuracy in osu!catch
+            GameMode::CatchTheBeat => HitCounts {
+                n_50: counts.n_50 - mistakes,
+                n_katu: counts.n_katu + mistakes,
+                ..counts
+            },
+            GameMode::Mania => HitCounts {
+                n_geki: counts.n_geki - mistakes,
+                n_100: counts.n_100 + mistakes,
+                ..counts
+            },
+            _ => HitCounts {
+                n_300: counts.n_300 - mistakes,
+                n_100: counts.n_100 + mistakes,
+                ..counts
+            },
+        }
+    }
+
+    #[test]
+    fn performance_of_tiferet() {
+        // Regression values of this implementation
+        let (_, replay, map) = test_data::replays_with_maps().remove(0);
+        let performance = Performance::for_replay(&map, &replay);
+        assert!((performance.pp() - 177.05).abs() < 0.1, "{performance:?}");
+        assert!((performance.aim() - 86.49).abs() < 0.1, "{performance:?}");
+        assert!((performance.speed() - 52.90).abs() < 0.1, "{performance:?}");
+        assert!(
+            (performance.accuracy() - 36.43).abs() < 0.1,
+            "{performance:?}"
+        );
+        assert_eq!(performance.flashlight(), 0.0);
+    }
+
+    #[test]
+    fn pp_rises_with_accuracy() {
+        let map = test_data::map_named("Another");
+        for mode in MODES {
+            let attributes = DifficultyAttributes::calculate(&map, mode, Mods::empty());
+            let counts = perfect_counts(&map, mode);
+            let pp = [0, 10, 50].map(|mistakes| {
+                let counts = with_mistakes(counts, mode, mistakes);
+                Performance::calculate(&attributes, counts, Mods::empty()).pp()
+            });
+            assert!(pp.windows(2).all(|w| w[0] > w[1]), "{mode:?}: {pp:?}");
+        }
+    }
+
+    #[test]
+    fn perfect_plays_are_worth_the_most() {
+        let map = test_data::map_named("Another");
+        for mode in MODES {
+            let attributes = DifficultyAttributes::calculate(&map, mode, Mods::empty());
+            let perfect = perfect_counts(&map, mode);
+            let if_ss = Performance::if_ss(&attributes, Mods::empty()).pp();
+            assert!(
+                (Performance::calculate(&attributes, perfect, Mods::empty()).pp() - if_ss).abs()
+                    < 1e-9,
+                "{mode:?}"
+            );
+
+            let counts = HitCounts {
+                n_miss: 5,
+                max_combo: perfect.max_combo / 2,
+                ..with_mistakes(perfect, mode, 15)
+            };
+            let actual = Performance::calculate(&attributes, counts, Mods::empty()).pp();
+            let if_fc = Performance::if_fc(&attributes, counts, Mods::empty()).pp();
+            assert!(actual < if_fc, "{mode:?}: {actual} >= {if_fc}");
+            assert!(if_fc <= if_ss, "{mode:?}: {if_fc} > {if_ss}");
+        }
+    }
+
+    #[test]
+    fn if_fc_of_tiferet() {
+        let (_, replay, map) = test_data::replays_with_maps().remove(0);
+        let attributes = DifficultyAttributes::calculate(&map, replay.mode, replay.mods);
+        let counts = HitCounts::from_replay(&replay);
+        let actual = Performance::for_replay(&map, &replay).pp();
+        let if_fc = Performance::if_fc(&attributes, counts, replay.mods).pp();
+        let if_ss = Performance::if_ss(&attributes, replay.mods).pp();
+        assert!(
+            actual < if_fc && if_fc < if_ss,
+            "{actual}, {if_fc}, {if_ss}"
+        );
+    }
+}
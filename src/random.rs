@@ -0,0 +1,111 @@
+/// The xorshift random number generator osu!stable uses wherever randomness has to be reproducible,
+/// e.g. for the positions of bananas in osu!catch.
+#[derive(Debug, Clone)]
+pub struct LegacyRandom {
+    x: u32,
+    y: u32,
+    z: u32,
+    w: u32,
+    bit_buffer: u32,
+    bit_index: u32,
+}
+
+impl LegacyRandom {
+    pub fn new(seed: i32) -> Self {
+        Self {
+            x: seed as u32,
+            y: 842502087,
+            z: 3579807591,
+            w: 273326509,
+            bit_buffer: 0,
+            bit_index: 32,
+        }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let t = self.x ^ (self.x << 11);
+        self.x = self.y;
+        self.y = self.z;
+        self.z = self.w;
+        self.w = self.w ^ (self.w >> 19) ^ t ^ (t >> 8);
+        self.w
+    }
+
+    /// A random non-negative integer.
+    pub fn next_i32(&mut self) -> i32 {
+        (self.next_u32() & 0x7FFF_FFFF) as i32
+    }
+
+    /// A random number between 0 (inclusive) and 1 (exclusive).
+    pub fn next_f64(&mut self) -> f64 {
+        self.next_i32() as f64 / (i32::MAX as f64 + 1.0)
+    }
+
+    /// A random integer between `lower` (inclusive) and `upper` (exclusive).
+    pub fn next_in_range(&mut self, lower: f64, upper: f64) -> i32 {
+        (lower + self.next_f64() * (upper - lower)) as i32
+    }
+
+    /// A random bool, taken bit by bit from a buffered random number.
+    pub fn next_bool(&mut self) -> bool {
+        if self.bit_index == 32 {
+            self.bit_buffer = self.next_u32();
+            self.bit_index = 1;
+            return self.bit_buffer & 1 == 1;
+        }
+        self.bit_index += 1;
+        self.bit_buffer >>= 1;
+        self.bit_buffer & 1 == 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reference values from osu!'s LegacyRandom (osu.Game/Utils/LegacyRandom.cs)
+
+    #[test]
+    fn sequence_matches_osu() {
+        let mut rng = LegacyRandom::new(1337);
+        let values = (0..4).map(|_| rng.next_u32()).collect::<Vec<_>>();
+        assert_eq!(values, [274941776, 2661595948, 3085529888, 4075547577]);
+
+        let mut rng = LegacyRandom::new(-1);
+        let values = (0..3).map(|_| rng.next_u32()).collect::<Vec<_>>();
+        assert_eq!(values, [273327196, 2660063269, 3082850348]);
+    }
+
+    #[test]
+    fn ranges_match_osu() {
+        let mut rng = LegacyRandom::new(1337);
+        let values = (0..4).map(|_| rng.next_i32()).collect::<Vec<_>>();
+        assert_eq!(values, [274941776, 514112300, 938046240, 1928063929]);
+
+        let mut rng = LegacyRandom::new(1337);
+        let offsets = (0..6)
+            .map(|_| rng.next_in_range(-20.0, 20.0))
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, [-14, -10, -2, 15, 17, 1]);
+
+        let mut rng = LegacyRandom::new(1337);
+        let positions = (0..6)
+            .map(|_| rng.next_in_range(0.0, 512.0))
+            .collect::<Vec<_>>();
+        assert_eq!(positions, [65, 122, 223, 459, 482, 269]);
+    }
+
+    #[test]
+    fn bools_match_osu() {
+        let mut rng = LegacyRandom::new(0);
+        let bools = (0..34).map(|_| rng.next_bool() as u8).collect::<Vec<_>>();
+        // The first 32 are the bits of the first number, the rest come from the second one
+        assert_eq!(
+            bools,
+            [
+                0, 0, 1, 0, 0, 1, 0, 1, 1, 1, 0, 0, 0, 1, 0, 1, 0, 1, 0, 1, 0, 0, 1, 0, 0, 0, 0, 0,
+                1, 0, 0, 0, 1, 0
+            ]
+        );
+    }
+}
@@ -1,8 +1,16 @@
-use std::{collections::HashMap, io::BufReader};
+use std::{
+    io::BufReader,
+    path::{Path, PathBuf},
+};
 
 use bitflags::bitflags;
 
-use crate::osu_data::GameMode;
+use crate::{
+    map::Beatmap,
+    osu_data::{GameMode, Reflection},
+    scoring::Score,
+    time_stamp::TimeStamp,
+};
 
 bitflags! {
     pub struct Mods : u32 {
@@ -42,8 +50,53 @@ bitflags! {
     }
 }
 
+impl Mods {
+    /// The speed at which the song is played with these mods.
+    pub fn clock_rate(&self) -> f64 {
+        if self.intersects(Self::DOUBLE_TIME | Self::NIGHTCORE) {
+            1.5
+        } else if self.contains(Self::HALF_TIME) {
+            0.75
+        } else {
+            1.0
+        }
+    }
+
+    /// The number of columns a key mod forces in osu!mania, if any.
+    pub fn key_count(&self) -> Option<usize> {
+        [
+            Self::KEY1,
+            Self::KEY2,
+            Self::KEY3,
+            Self::KEY4,
+            Self::KEY5,
+            Self::KEY6,
+            Self::KEY7,
+            Self::KEY8,
+            Self::KEY9,
+        ]
+        .into_iter()
+        .position(|key| self.contains(key))
+        .map(|i| i + 1)
+    }
+
+    /// The reflection these mods apply to the playfield, if any.
+    /// Mirror is treated like lazer's default setting, which flips horizontally.
+    pub fn reflection(&self) -> Option<Reflection> {
+        match (
+            self.contains(Self::MIRROR),
+            self.contains(Self::HARD_ROCK),
+        ) {
+            (true, true) => Some(Reflection::Both),
+            (true, false) => Some(Reflection::Horizontal),
+            (false, true) => Some(Reflection::Vertical),
+            (false, false) => None,
+        }
+    }
+}
+
 bitflags! {
-    struct InputKeys: u32 {
+    pub struct InputKeys: u32 {
         const M1 = 1;
         const M2 = 1 << 1;
         const K1 = 1 << 2;
@@ -52,14 +105,62 @@ bitflags! {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+impl InputKeys {
+    /// Whether the left button is held, either by M1 or by K1 (which also sets M1).
+    pub fn left(&self) -> bool {
+        self.intersects(Self::M1 | Self::K1)
+    }
+
+    /// Whether the right button is held, either by M2 or by K2 (which also sets M2).
+    pub fn right(&self) -> bool {
+        self.intersects(Self::M2 | Self::K2)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct ReplayFrame {
-    time_delta: u64,
+    time_delta: i64,       // in milliseconds since the previous frame, can be negative
     x: f32,                // x coord between 0 - 512
     y: f32,                // y coord between 0 - 384
     input_keys: InputKeys, // bitwise combination of keys/mouse pressed (M1 = 1, M2 = 2, K1 = 4, K2 = 8, Smoke = 16)
 }
 
+impl ReplayFrame {
+    pub fn new(time_delta: i64, x: f32, y: f32, input_keys: InputKeys) -> Self {
+        Self {
+            time_delta,
+            x,
+            y,
+            input_keys,
+        }
+    }
+
+    pub fn time_delta(&self) -> i64 {
+        self.time_delta
+    }
+
+    pub fn x(&self) -> f32 {
+        self.x
+    }
+
+    pub fn y(&self) -> f32 {
+        self.y
+    }
+
+    pub fn position(&self) -> (f32, f32) {
+        (self.x, self.y)
+    }
+
+    pub fn input_keys(&self) -> InputKeys {
+        self.input_keys
+    }
+
+    /// Flips the cursor position of this frame along the given axis.
+    pub fn reflect(&mut self, reflection: Reflection) {
+        (self.x, self.y) = reflection.apply((self.x, self.y));
+    }
+}
+
 impl Default for ReplayFrame {
     fn default() -> Self {
         Self {
@@ -71,7 +172,7 @@ impl Default for ReplayFrame {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Replay {
     pub mode: GameMode,
     pub game_ver: u32,
@@ -88,10 +189,11 @@ pub struct Replay {
     pub max_combo: u16,
     pub perfect_combo: bool, // represented as 1 byte in the file
     pub mods: Mods,          // represented as a 32 bit int
-    pub life_bar_graph: HashMap<usize, f64>,
-    pub time_stamp: u64,
+    pub life_bar_graph: Vec<(i64, f64)>, // (time in milliseconds, health between 0 and 1), ordered by time
+    pub time_stamp: u64, // in .NET ticks, see `TimeStamp`
     pub compressed_data_length: u32, // in bytes
     pub replay_data: Vec<ReplayFrame>,
+    pub seed: Option<i32>, // stored in a special frame at the end of the replay data, used by the Random mod
     pub online_score_id: u64,
     pub total_hit_accuracy: f64, // only for target practice mod
 }
@@ -104,8 +206,245 @@ impl Replay {
     pub fn iter(&self) -> std::slice::Iter<'_, ReplayFrame> {
         self.replay_data.iter()
     }
+
+    /// The time the replay was played at.
+    pub fn played_at(&self) -> TimeStamp {
+        TimeStamp::from_ticks(self.time_stamp)
+    }
+
+    /// Iterates over the frames together with their absolute time in milliseconds.
+    pub fn timed_frames(&self) -> impl Iterator<Item = (i64, &ReplayFrame)> + '_ {
+        self.replay_data.iter().scan(0i64, |time, frame| {
+            *time += frame.time_delta;
+            Some((*time, frame))
+        })
+    }
+
+    /// Flips the cursor positions of all frames along the given axis.
+    pub fn reflect_frames(&mut self, reflection: Reflection) {
+        self.replay_data
+            .iter_mut()
+            .for_each(|frame| frame.reflect(reflection));
+    }
+
+    /// Encodes the replay in the .osr format, so it can be watched in osu! or read again with [`Replay::try_from`].
+    ///
+    /// The compressed data length is taken from the actually compressed frames rather than from the replay.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.mode as u8];
+        bytes.extend(self.game_ver.to_le_bytes());
+        write_string(&mut bytes, &self.map_md5_hash);
+        write_string(&mut bytes, &self.player_name);
+        write_string(&mut bytes, &self.replay_md5_hash);
+        for count in [
+            self.n_300,
+            self.n_100,
+            self.n_50,
+            self.n_geki,
+            self.n_katu,
+            self.n_miss,
+        ] {
+            bytes.extend(count.to_le_bytes());
+        }
+        bytes.extend(self.total_score.to_le_bytes());
+        bytes.extend(self.max_combo.to_le_bytes());
+        bytes.push(self.perfect_combo as u8);
+        bytes.extend(self.mods.bits().to_le_bytes());
+        let life_bar_graph = self
+            .life_bar_graph
+            .iter()
+            .map(|(time, life)| format!("{time}|{life},"))
+            .collect::<String>();
+        write_string(&mut bytes, &life_bar_graph);
+        bytes.extend(self.time_stamp.to_le_bytes());
+
+        let frames = self
+            .replay_data
+            .iter()
+            .map(|frame| {
+                format!(
+                    "{}|{}|{}|{}",
+                    frame.time_delta,
+                    frame.x,
+                    frame.y,
+                    frame.input_keys.bits()
+                )
+            })
+            .chain(self.seed.map(|seed| format!("-12345|0|0|{seed}")))
+            .collect::<Vec<_>>()
+            .join(",");
+        let mut compressed = vec![];
+        lzma_rs::lzma_compress(&mut BufReader::new(frames.as_bytes()), &mut compressed)
+            .expect("Compressing into memory cannot fail");
+        bytes.extend((compressed.len() as u32).to_le_bytes());
+        bytes.extend(compressed);
+
+        bytes.extend(self.online_score_id.to_le_bytes());
+        if self.mods.contains(Mods::TARGET_PRACTICE) {
+            bytes.extend(self.total_hit_accuracy.to_bits().to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Undoes the reflection the replay's mods applied to the cursor,
+    /// so the frames can be compared against the unmodified beatmap.
+    pub fn unreflect_frames(&mut self) {
+        if let Some(reflection) = self.mods.reflection() {
+            self.reflect_frames(reflection);
+        }
+    }
+
+    /// Keeps only the frames and life bar entries between the start and end time (inclusive, in milliseconds).
+    /// The remaining frames keep their times, so the replay still lines up with the beatmap.
+    pub fn trimmed(mut self, start: i64, end: i64) -> Self {
+        let frames = self
+            .timed_frames()
+            .filter(|&(time, _)| start <= time && time <= end)
+            .map(|(time, frame)| (time, *frame))
+            .collect::<Vec<_>>();
+        self.set_timed_frames(frames);
+        self.life_bar_graph
+            .retain(|&(time, _)| start <= time && time <= end);
+        self
+    }
+
+    /// Replaces every frame from the given time (in milliseconds) on with the frames of the other replay from that time on.
+    /// The life bar is spliced the same way.
+    ///
+    /// Both replays must be of the same beatmap, game mode and mods, otherwise the frames don't line up.
+    pub fn spliced(mut self, other: &Replay, time: i64) -> Result<Self, &'static str> {
+        if self.map_md5_hash != other.map_md5_hash {
+            return Err("Replays are of different beatmaps");
+        }
+        if self.mode != other.mode {
+            return Err("Replays are of different game modes");
+        }
+        if self.mods != other.mods {
+            return Err("Replays are played with different mods");
+        }
+
+        let frames = self
+            .timed_frames()
+            .filter(|&(frame_time, _)| frame_time < time)
+            .chain(
+                other
+                    .timed_frames()
+                    .filter(|&(frame_time, _)| frame_time >= time),
+            )
+            .map(|(time, frame)| (time, *frame))
+            .collect::<Vec<_>>();
+        self.set_timed_frames(frames);
+        self.life_bar_graph
+            .retain(|&(life_time, _)| life_time < time);
+        self.life_bar_graph.extend(
+            other
+                .life_bar_graph
+                .iter()
+                .filter(|&&(life_time, _)| life_time >= time),
+        );
+        Ok(self)
+    }
+
+    /// Moves every frame and life bar entry by the given offset in milliseconds, which is positive to make the replay later.
+    pub fn shifted(mut self, offset: i64) -> Self {
+        if let Some(first) = self.replay_data.first_mut() {
+            first.time_delta += offset;
+        }
+        self.life_bar_graph
+            .iter_mut()
+            .for_each(|(time, _)| *time += offset);
+        self
+    }
+
+    /// Plays the replay at the given rate relative to its current speed, e.g. 1.5 to make it
+    /// as fast as with Double Time. Times are rounded to whole milliseconds.
+    ///
+    /// Only the frames are changed, the mods stay the same. The rate must be positive and finite.
+    pub fn retimed(mut self, rate: f64) -> Result<Self, &'static str> {
+        if !(rate.is_finite() && rate > 0.0) {
+            return Err("Rate must be positive and finite");
+        }
+
+        let scale = |time: i64| (time as f64 / rate).round() as i64;
+        let frames = self
+            .timed_frames()
+            .map(|(time, frame)| (scale(time), *frame))
+            .collect::<Vec<_>>();
+        self.set_timed_frames(frames);
+        self.life_bar_graph
+            .iter_mut()
+            .for_each(|(time, _)| *time = scale(*time));
+        Ok(self)
+    }
+
+    /// Recomputes the hit counts, combo and score by judging the frames on the given (unmodified) beatmap,
+    /// and gives the replay a new replay hash. This is needed after editing the frames, since the values
+    /// stored in the replay no longer match them.
+    pub fn recomputed(mut self, map: &Beatmap) -> Self {
+        let score = Score::for_replay(map, &self);
+        let count = |count: usize| count.min(u16::MAX as usize) as u16;
+        self.n_300 = count(score.n_300());
+        self.n_100 = count(score.n_100());
+        self.n_50 = count(score.n_50());
+        self.n_geki = count(score.n_geki());
+        self.n_katu = count(score.n_katu());
+        self.n_miss = count(score.n_miss());
+        self.max_combo = count(score.max_combo());
+        self.perfect_combo = score.perfect_combo();
+        self.total_score = score.total_score().min(u32::MAX as u64) as u32;
+        self.update_replay_hash();
+        self
+    }
+
+    /// Replaces the replay hash with the MD5 hash of the replay encoded without a replay hash, so an edited replay
+    /// isn't mistaken for the one it was made from. osu! identifies replays by this hash, but doesn't check how it was computed.
+    pub fn update_replay_hash(&mut self) {
+        self.replay_md5_hash.clear();
+        self.replay_md5_hash = format!("{:x}", md5::compute(self.to_bytes()));
+    }
+
+    /// Replaces the frames with frames at the given absolute times, computing their time deltas.
+    fn set_timed_frames(&mut self, frames: Vec<(i64, ReplayFrame)>) {
+        let mut previous_time = 0;
+        self.replay_data = frames
+            .into_iter()
+            .map(|(time, frame)| {
+                let time_delta = time - previous_time;
+                previous_time = time;
+                ReplayFrame {
+                    time_delta,
+                    ..frame
+                }
+            })
+            .collect();
+    }
 }
 
+/// Writes a string the way .osr files store them: a marker byte, the length as ULEB128 and the UTF-8 bytes.
+/// Empty strings are stored as a single zero byte.
+fn write_string(bytes: &mut Vec<u8>, s: &str) {
+    if s.is_empty() {
+        bytes.push(0x00);
+        return;
+    }
+
+    bytes.push(0x0b);
+    let mut len = s.len();
+    loop {
+        let byte = (len & 0b0111_1111) as u8;
+        len >>= 7;
+        if len == 0 {
+            bytes.push(byte);
+            break;
+        }
+        bytes.push(byte | 0b1000_0000);
+    }
+    bytes.extend(s.as_bytes());
+}
+
+/// Reads a replay in the .osr format.
+///
+/// Corrupt or truncated data results in an error rather than a panic, so untrusted files can be read safely.
 impl<'a> TryFrom<&'a [u8]> for Replay {
     type Error = &'a str;
 
@@ -185,6 +524,10 @@ impl<'a> TryFrom<&'a [u8]> for Replay {
                 while current & 0b1000_0000 > 0 {
                     acc += ((current & 0b0111_1111) as u64) << (i * 7);
                     i += 1;
+                    // More bytes than fit into 64 bits
+                    if i * 7 >= 64 {
+                        return Err(err);
+                    }
                     current = bytes.next().ok_or(err)?;
                 }
                 // Read most significant byte
@@ -199,9 +542,10 @@ impl<'a> TryFrom<&'a [u8]> for Replay {
                 let is_present = read_byte!() == 0x0b;
                 if is_present {
                     let byte_len = read_uleb128!() as usize;
-                    let mut byte_vec = vec![0u8; byte_len];
-                    for i in 0..byte_len {
-                        byte_vec[i] = bytes.next().ok_or("Error reading byte for String")?;
+                    // Not preallocated, since a corrupt length could be arbitrarily large
+                    let byte_vec = bytes.by_ref().take(byte_len).collect::<Vec<_>>();
+                    if byte_vec.len() != byte_len {
+                        return Err("Error reading byte for String");
                     }
                     String::from_utf8(byte_vec)
                         .map_err(|_| "Invalid UTF-8 in replay file String value")?
@@ -231,33 +575,45 @@ impl<'a> TryFrom<&'a [u8]> for Replay {
             .filter(|s| !s.is_empty())
             .map(|pair| {
                 let mut split = pair.trim().split('|');
-                (
-                    split.next().map(|v| v.parse().unwrap()).unwrap(),
-                    split.next().map(|v| v.parse().unwrap()).unwrap(),
-                )
+                Some((
+                    split.next()?.parse::<f64>().ok()? as i64,
+                    split.next()?.parse::<f64>().ok()?,
+                ))
             })
-            .collect::<HashMap<usize, f64>>();
+            .collect::<Option<Vec<_>>>()
+            .ok_or("Error reading life bar graph")?;
         let time_stamp = read_long!();
         let compressed_data_length = read_int!() as u32;
+        let mut seed = None;
         let replay_data = {
-            let mut compressed_replay_data = vec![0u8; compressed_data_length as usize];
-            for i in 0..compressed_data_length as usize {
-                compressed_replay_data[i] =
-                    bytes.next().ok_or("Error reading compressed replay data")?;
+            let compressed_replay_data = bytes
+                .by_ref()
+                .take(compressed_data_length as usize)
+                .collect::<Vec<_>>();
+            if compressed_replay_data.len() != compressed_data_length as usize {
+                return Err("Error reading compressed replay data");
             }
 
             let mut comp_reader = BufReader::new(&compressed_replay_data[..]);
             let mut decompressed_replay_data = vec![];
             lzma_rs::lzma_decompress(&mut comp_reader, &mut decompressed_replay_data)
                 .map_err(|_| "Error decompressing replay data")?;
-            let decompressed_replay_data = String::from_utf8(decompressed_replay_data).unwrap();
+            let decompressed_replay_data = String::from_utf8(decompressed_replay_data)
+                .map_err(|_| "Invalid UTF-8 in replay data")?;
             let mut data_iter = decompressed_replay_data.split(',');
 
             let mut frames = vec![];
 
             while let Some(data) = data_iter.next() {
+                // The frames end with a trailing comma
+                if data.is_empty() {
+                    continue;
+                }
                 let mut iter = data.split("|");
-                let time_delta = iter.next().map(|s| s.parse::<i64>().unwrap()).unwrap();
+                let time_delta = iter
+                    .next()
+                    .ok_or("Error reading time delta")
+                    .and_then(|s| s.parse::<i64>().map_err(|_| "Error parsing time delta"))?;
                 let x = iter
                     .next()
                     .ok_or("Error reading x")
@@ -267,21 +623,21 @@ impl<'a> TryFrom<&'a [u8]> for Replay {
                     .ok_or("Error reading y")
                     .and_then(|s| s.parse().map_err(|_| "Error parsing f32"))?;
 
-                // Don't fully unwrap input keys yet. If we're at the special frame, this value will be the seed instead and therefore not a valid input_keys bitstring
-                let input_keys = InputKeys::from_bits(
-                    iter.next()
-                        .ok_or("Error reading y")
-                        .and_then(|s| s.parse().map_err(|_| "Error parsing u32"))?,
-                );
+                // Don't parse input keys yet. If we're at the special frame, this value will be the seed instead and therefore not a valid input_keys bitstring
+                let input_keys = iter.next().ok_or("Error reading input keys")?;
                 // Special Frame has this werid value as time delta
                 if time_delta == -12345 {
-                    // TODO implement Seed. Idk what it's for tho
+                    seed = Some(input_keys.parse().map_err(|_| "Error parsing seed")?);
                     break;
                 }
-                let input_keys = input_keys.ok_or("Invalid input keys value")?;
+                let input_keys = input_keys
+                    .parse()
+                    .ok()
+                    .and_then(InputKeys::from_bits)
+                    .ok_or("Invalid input keys value")?;
 
                 frames.push(ReplayFrame {
-                    time_delta: unsafe { std::mem::transmute(time_delta) },
+                    time_delta,
                     x,
                     y,
                     input_keys,
@@ -316,8 +672,148 @@ impl<'a> TryFrom<&'a [u8]> for Replay {
             time_stamp,
             compressed_data_length,
             replay_data,
+            seed,
             online_score_id,
             total_hit_accuracy,
         })
     }
 }
+
+/// Reads every replay in the given directory (not its subdirectories), in no particular order.
+///
+/// Files which are not replays, can't be read or are corrupt are skipped,
+/// so only failing to read the directory itself is an error.
+pub fn read_replays(
+    directory: impl AsRef<Path>,
+) -> std::io::Result<impl Iterator<Item = (PathBuf, Replay)>> {
+    Ok(std::fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("osr"))
+        .filter_map(|path| {
+            let bytes = std::fs::read(&path).ok()?;
+            let replay = Replay::try_from(&bytes[..]).ok()?;
+            Some((path, replay))
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_data;
+
+    use super::*;
+
+    /// Encodes and decodes the replay, which only changes the compressed data length.
+    fn assert_round_trip(replay: &Replay) {
+        let bytes = replay.to_bytes();
+        let decoded = Replay::try_from(&bytes[..]).unwrap();
+        let expected = Replay {
+            compressed_data_length: decoded.compressed_data_length,
+            ..replay.clone()
+        };
+        assert_eq!(decoded, expected);
+        assert_eq!(decoded.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn sample_replays_round_trip() {
+        let samples = test_data::replays();
+        assert!(!samples.is_empty());
+        for (path, replay) in samples {
+            assert!(!replay.life_bar_graph.is_empty(), "{}", path.display());
+            assert!(replay.seed.is_some(), "{}", path.display());
+            assert_round_trip(&replay);
+        }
+    }
+
+    #[test]
+    fn round_trip_keeps_seed_strings_and_life_bar() {
+        let (_, mut replay) = test_data::replays().remove(0);
+        replay.player_name = "プレイヤー".to_owned();
+        replay.replay_md5_hash = String::new();
+        replay.seed = Some(-42);
+        replay.life_bar_graph = vec![(0, 1.0), (1500, 0.25), (3000, 0.0)];
+        assert_round_trip(&replay);
+
+        replay.seed = None;
+        replay.life_bar_graph.clear();
+        assert_round_trip(&replay);
+    }
+
+    #[test]
+    fn corrupt_data_is_an_error() {
+        let (_, replay) = test_data::replays().remove(0);
+        let bytes = replay.to_bytes();
+        for length in (0..bytes.len()).step_by(101) {
+            assert!(Replay::try_from(&bytes[..length]).is_err());
+        }
+        // Corrupt string and data lengths
+        for (i, value) in [(1 + 4 + 1, 0xff), (bytes.len() - 8 - 2, 0x7f)] {
+            let mut corrupt = bytes.clone();
+            corrupt[i..i + 10].fill(value);
+            assert!(Replay::try_from(&corrupt[..]).is_err());
+        }
+    }
+
+    #[test]
+    fn read_replays_skips_bad_files() {
+        let replays = test_data::replays();
+        let directory = test_data::temp_directory("read-replays");
+        test_data::write_with_bad_files(&directory, &[("sample.osr", &replays[0].1)]);
+
+        let read = read_replays(&directory).unwrap().collect::<Vec<_>>();
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].0, directory.join("sample.osr"));
+        assert_eq!(read[0].1.replay_data, replays[0].1.replay_data);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+        assert!(read_replays(&directory).is_err());
+    }
+
+    #[test]
+    fn recomputed_edit_survives_round_trip() {
+        let (_, replay, map) = test_data::replays_with_maps().remove(0);
+        let (end, _) = replay.timed_frames().last().unwrap();
+        let edited = replay.clone().trimmed(0, end / 2).recomputed(&map);
+        assert!(edited.n_miss > replay.n_miss);
+        assert_ne!(edited.replay_md5_hash, replay.replay_md5_hash);
+
+        let decoded = Replay::try_from(&edited.to_bytes()[..]).unwrap();
+        let counts = |r: &Replay| (r.n_300, r.n_100, r.n_50, r.n_miss, r.max_combo);
+        assert_eq!(counts(&decoded), counts(&edited));
+        assert_eq!(decoded.total_score, edited.total_score);
+        assert_eq!(decoded.replay_md5_hash, edited.replay_md5_hash);
+        assert!(Score::for_replay(&map, &decoded)
+            .validate(&decoded, 0.0)
+            .is_empty());
+
+        let mut rehashed = decoded.clone();
+        rehashed.update_replay_hash();
+        assert_eq!(rehashed.replay_md5_hash, edited.replay_md5_hash);
+    }
+
+    #[test]
+    fn splicing_requires_matching_replays() {
+        let replays = test_data::replays();
+        let (replay, other_map) = (&replays[1].1, &replays[0].1);
+        let spliced = replay.clone().spliced(replay, 10_000).unwrap();
+        assert_eq!(spliced.replay_data, replay.replay_data);
+        assert_eq!(spliced.life_bar_graph, replay.life_bar_graph);
+
+        assert!(replay.clone().spliced(other_map, 10_000).is_err());
+        let mut other_mods = replay.clone();
+        other_mods.mods.toggle(Mods::HIDDEN);
+        assert!(replay.clone().spliced(&other_mods, 10_000).is_err());
+    }
+
+    #[test]
+    fn retiming_requires_valid_rate() {
+        let (_, replay) = test_data::replays().remove(0);
+        for rate in [0.0, -1.5, f64::NAN, f64::INFINITY] {
+            assert!(replay.clone().retimed(rate).is_err());
+        }
+        let retimed = replay.clone().retimed(1.5).unwrap();
+        let end = |r: &Replay| r.timed_frames().last().unwrap().0;
+        assert_eq!(end(&retimed), (end(&replay) as f64 / 1.5).round() as i64);
+    }
+}
@@ -0,0 +1,746 @@
+use crate::{
+    judgement::{
+        catch,
+        catch::CatchJudgements,
+        mania,
+        mania::{ManiaHitResult, ManiaJudgements},
+        standard, taiko,
+        taiko::TaikoJudgements,
+        HitResult, JudgementDetails, JudgementEvent, Judgements, SectionBonus, SliderPart,
+    },
+    map::{catch::CatchObjectKind, Beatmap},
+    osu_data::GameMode,
+    replay::{Mods, Replay},
+};
+
+/// Score awarded for every full spinner rotation.
+const SPIN_SCORE: u64 = 100;
+/// Additional score awarded for every spinner rotation past the required amount.
+const BONUS_SPIN_SCORE: u64 = 1000;
+/// Score awarded for every drum roll tick and swell hit in osu!taiko, doubled for big drum rolls.
+const TAIKO_BONUS_SCORE: u64 = 300;
+/// Score awarded for every banana caught in osu!catch.
+const BANANA_SCORE: u64 = 1100;
+/// Score awarded for every droplet and tiny droplet caught in osu!catch.
+const DROPLET_SCORE: u64 = 100;
+const TINY_DROPLET_SCORE: u64 = 10;
+/// The score of a perfect play in osu!mania without mods.
+const MANIA_MAX_SCORE: f64 = 1_000_000.0;
+
+/// The rank a play is awarded, using osu!'s internal names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Grade {
+    /// Silver SS, with Hidden or Flashlight
+    XH,
+    X,
+    /// Silver S, with Hidden or Flashlight
+    SH,
+    S,
+    A,
+    B,
+    C,
+    D,
+}
+
+/// The score of a play, recomputed from simulated judgements.
+#[derive(Debug, Clone)]
+pub struct Score {
+    n_300: usize,
+    n_100: usize,
+    n_50: usize,
+    n_geki: usize,
+    n_katu: usize,
+    n_miss: usize,
+    max_combo: usize,
+    perfect_combo: bool,
+    score_v1: u64,
+    score_v2: u64,
+    mods: Mods,
+    mode: GameMode,
+}
+
+impl Score {
+    /// Computes the score from the judgements of an osu!standard play of the given (unmodified) beatmap.
+    pub fn calculate(map: &Beatmap, judgements: &Judgements, mods: Mods) -> Self {
+        let difficulty_multiplier = difficulty_multiplier(map);
+        let v1_multiplier = difficulty_multiplier * score_v1_mod_multiplier(mods);
+
+        let mut score = Self {
+            n_300: judgements.n_300(),
+            n_100: judgements.n_100(),
+            n_50: judgements.n_50(),
+            n_miss: judgements.n_miss(),
+            n_geki: 0,
+            n_katu: 0,
+            max_combo: 0,
+            perfect_combo: false,
+            score_v1: 0,
+            score_v2: 0,
+            mods,
+            mode: GameMode::Standard,
+        };
+
+        let mut combo = 0;
+        let mut max_possible_combo = 0;
+        let mut score_v1 = 0;
+        let mut bonus = 0;
+        let mut combo_score = 0.0;
+        let mut max_combo_score = 0.0;
+        for (_, event) in judgements.events() {
+            match event {
+                JudgementEvent::SliderPart(part) => {
+                    if part.hit() {
+                        score_v1 += if part.kind() == SliderPart::Tick {
+                            10
+                        } else {
+                            30
+                        };
+                        combo += 1;
+                    } else if part.kind() != SliderPart::End {
+                        // Missing the end of a slider does not break combo
+                        combo = 0;
+                    }
+                    max_possible_combo += 1;
+                }
+                JudgementEvent::Object(index) => {
+                    let judgement = &judgements.objects()[index];
+                    let value = judgement.result().score_value() as u64;
+                    let combo_multiplier = combo.max(1) as f64 - 1.0;
+                    score_v1 +=
+                        value + (combo_multiplier * ((value / 25) as f64 * v1_multiplier)) as u64;
+                    combo_score += value as f64 * combo as f64;
+                    max_combo_score += 300.0 * max_possible_combo as f64;
+
+                    match judgement.details() {
+                        // The combo of sliders is awarded by their parts
+                        JudgementDetails::Slider(_) => {}
+                        JudgementDetails::Spinner {
+                            rotations,
+                            required_rotations,
+                        } => {
+                            let spins = rotations.floor() as u64;
+                            let bonus_spins = spins.saturating_sub(*required_rotations as u64);
+                            bonus += spins * SPIN_SCORE + bonus_spins * BONUS_SPIN_SCORE;
+                            combo = if judgement.result().is_hit() {
+                                combo + 1
+                            } else {
+                                0
+                            };
+                            max_possible_combo += 1;
+                        }
+                        JudgementDetails::Circle => {
+                            combo = if judgement.result().is_hit() {
+                                combo + 1
+                            } else {
+                                0
+                            };
+                            max_possible_combo += 1;
+                        }
+                    }
+                }
+            }
+            score.max_combo = score.max_combo.max(combo);
+        }
+        score.perfect_combo = score.max_combo == max_possible_combo;
+
+        score.score_v1 = score_v1 + bonus;
+        let combo_portion = if max_combo_score > 0.0 {
+            combo_score / max_combo_score
+        } else {
+            1.0
+        };
+        score.score_v2 =
+            ((700000.0 * combo_portion + 300000.0 * score.accuracy().powi(10) + bonus as f64)
+                * score_v2_mod_multiplier(mods))
+            .round() as u64;
+
+        for bonus in judgements
+            .section_bonuses(map.hit_objects())
+            .into_iter()
+            .flatten()
+        {
+            match bonus {
+                SectionBonus::Geki => score.n_geki += 1,
+                SectionBonus::Katu => score.n_katu += 1,
+            }
+        }
+
+        score
+    }
+
+    /// Computes the score from the judgements of an osu!taiko play of the given (unmodified) beatmap.
+    /// Drum roll ticks and swell hits award bonus score, but affect neither combo nor accuracy.
+    pub fn calculate_taiko(map: &Beatmap, judgements: &TaikoJudgements, mods: Mods) -> Self {
+        let v1_multiplier = difficulty_multiplier(map) * score_v1_mod_multiplier(mods);
+
+        let mut score = Self {
+            n_300: judgements.n_300(),
+            n_100: judgements.n_100(),
+            n_50: 0,
+            n_miss: judgements.n_miss(),
+            n_geki: judgements.n_geki(),
+            n_katu: judgements.n_katu(),
+            max_combo: 0,
+            perfect_combo: false,
+            score_v1: 0,
+            score_v2: 0,
+            mods,
+            mode: GameMode::Taiko,
+        };
+
+        let mut combo = 0;
+        let mut score_v1 = 0;
+        let mut combo_score = 0.0;
+        let mut max_combo_score = 0.0;
+        for (i, hit) in judgements.hits().iter().enumerate() {
+            let value = match hit.result() {
+                HitResult::Hit300 => 300,
+                HitResult::Hit100 => 150,
+                _ => 0,
+            };
+            let strong_value = if hit.strong() { 2 * value } else { value };
+            // The combo bonus grows every 10 combo until it is capped at 100 combo
+            let combo_multiplier = (combo / 10).min(10) as f64;
+            score_v1 += strong_value
+                + (combo_multiplier * ((strong_value / 10) as f64 * v1_multiplier)) as u64;
+            combo_score += value as f64 * combo as f64;
+            max_combo_score += 300.0 * i as f64;
+
+            combo = if hit.result().is_hit() { combo + 1 } else { 0 };
+            score.max_combo = score.max_combo.max(combo);
+        }
+        score.perfect_combo = score.max_combo == judgements.hits().len();
+
+        let bonus = judgements
+            .drum_rolls()
+            .iter()
+            .map(|drum_roll| {
+                let value = if drum_roll.big() {
+                    2 * TAIKO_BONUS_SCORE
+                } else {
+                    TAIKO_BONUS_SCORE
+                };
+                drum_roll.ticks_hit() as u64 * value
+            })
+            .sum::<u64>()
+            + judgements
+                .swells()
+                .iter()
+                .map(|swell| swell.hits().len() as u64 * TAIKO_BONUS_SCORE)
+                .sum::<u64>();
+
+        score.score_v1 = score_v1 + bonus;
+        let combo_portion = if max_combo_score > 0.0 {
+            combo_score / max_combo_score
+        } else {
+            1.0
+        };
+        score.score_v2 =
+            ((700000.0 * combo_portion + 300000.0 * score.accuracy().powi(10) + bonus as f64)
+                * score_v2_mod_multiplier(mods))
+            .round() as u64;
+
+        score
+    }
+
+    /// Computes the score of an osu!catch play.
+    ///
+    /// Only fruits receive the combo bonus. Droplets and tiny droplets award a fixed amount,
+    /// bananas count as bonus score.
+    pub fn calculate_catch(map: &Beatmap, judgements: &CatchJudgements, mods: Mods) -> Self {
+        let v1_multiplier = difficulty_multiplier(map) * score_v1_mod_multiplier(mods);
+
+        let mut score = Self {
+            n_300: judgements.n_300(),
+            n_100: judgements.n_100(),
+            n_50: judgements.n_50(),
+            n_miss: judgements.n_miss(),
+            n_geki: 0,
+            n_katu: judgements.n_katu(),
+            max_combo: judgements.max_combo(),
+            perfect_combo: false,
+            score_v1: 0,
+            score_v2: 0,
+            mods,
+            mode: GameMode::CatchTheBeat,
+        };
+
+        let mut combo = 0;
+        let mut combo_objects = 0;
+        let mut score_v1 = 0;
+        let mut bonus = 0;
+        let mut combo_score = 0.0;
+        let mut max_combo_score = 0.0;
+        for judgement in judgements.judgements().iter().filter(|j| j.caught()) {
+            match judgement.kind() {
+                CatchObjectKind::Fruit => {
+                    score_v1 += 300
+                        + ((combo.max(1) - 1) as f64 * ((300 / 25) as f64 * v1_multiplier)) as u64;
+                }
+                CatchObjectKind::Droplet => score_v1 += DROPLET_SCORE,
+                CatchObjectKind::TinyDroplet => score_v1 += TINY_DROPLET_SCORE,
+                CatchObjectKind::Banana => bonus += BANANA_SCORE,
+            }
+            if judgement.kind().affects_combo() {
+                combo += 1;
+            }
+        }
+        // Combo is counted again including misses for the combo portion of ScoreV2
+        combo = 0;
+        for judgement in judgements
+            .judgements()
+            .iter()
+            .filter(|j| j.kind().affects_combo())
+        {
+            if judgement.caught() {
+                combo_score += 300.0 * combo as f64;
+                combo += 1;
+            } else {
+                combo = 0;
+            }
+            max_combo_score += 300.0 * combo_objects as f64;
+            combo_objects += 1;
+        }
+        score.perfect_combo = score.max_combo == combo_objects;
+
+        score.score_v1 = score_v1 + bonus;
+        let combo_portion = if max_combo_score > 0.0 {
+            combo_score / max_combo_score
+        } else {
+            1.0
+        };
+        score.score_v2 =
+            ((700000.0 * combo_portion + 300000.0 * score.accuracy().powi(10) + bonus as f64)
+                * score_v2_mod_multiplier(mods))
+            .round() as u64;
+
+        score
+    }
+
+    /// Computes the score of an osu!mania play.
+    ///
+    /// Half of the score comes from the judgements themselves, the other half from a bonus which grows with
+    /// consecutive 300s and shrinks with worse judgements. Both are scaled so a perfect play results in 1,000,000.
+    pub fn calculate_mania(judgements: &ManiaJudgements, mods: Mods) -> Self {
+        let mut score = Self {
+            n_300: judgements.n_300(),
+            n_100: judgements.n_100(),
+            n_50: judgements.n_50(),
+            n_miss: judgements.n_miss(),
+            n_geki: judgements.n_geki(),
+            n_katu: judgements.n_katu(),
+            max_combo: judgements.max_combo(),
+            perfect_combo: false,
+            score_v1: 0,
+            score_v2: 0,
+            mods,
+            mode: GameMode::Mania,
+        };
+        score.perfect_combo = score.max_combo == judgements.judgements().len();
+
+        let note_score = MANIA_MAX_SCORE * 0.5 / judgements.judgements().len().max(1) as f64;
+        let mut bonus = 100.0f64;
+        let mut score_v1 = 0.0;
+        let mut combo_score = 0.0;
+        let mut max_combo_score = 0.0;
+        let mut combo = 0;
+        for (i, judgement) in judgements.judgements().iter().enumerate() {
+            use ManiaHitResult::*;
+            let (bonus_value, bonus_change) = match judgement.result() {
+                Max => (32.0, 2.0),
+                Hit300 => (32.0, 1.0),
+                Hit200 => (16.0, -8.0),
+                Hit100 => (8.0, -24.0),
+                Hit50 => (4.0, -44.0),
+                Miss => (0.0, -100.0),
+            };
+            bonus = (bonus + bonus_change).clamp(0.0, 100.0);
+            let value = judgement.result().score_value() as f64;
+            score_v1 += note_score * (value + bonus_value * bonus.sqrt()) / 320.0;
+
+            combo_score += value * combo as f64;
+            max_combo_score += 320.0 * i as f64;
+            combo = if judgement.result().is_hit() {
+                combo + 1
+            } else {
+                0
+            };
+        }
+
+        score.score_v1 = (score_v1 * mania_mod_multiplier(mods)).round() as u64;
+        let combo_portion = if max_combo_score > 0.0 {
+            combo_score / max_combo_score
+        } else {
+            1.0
+        };
+        score.score_v2 = ((700000.0 * combo_portion + 300000.0 * score.accuracy().powi(10))
+            * mania_mod_multiplier(mods))
+        .round() as u64;
+
+        score
+    }
+
+    /// Judges the replay on the given (unmodified) beatmap and computes its score.
+    pub fn for_replay(map: &Beatmap, replay: &Replay) -> Self {
+        match replay.mode {
+            GameMode::Standard => {
+                let judgements =
+                    standard::judge(map, replay).expect("The replay is an osu!standard replay");
+                Self::calculate(map, &judgements, replay.mods)
+            }
+            GameMode::Taiko => {
+                let judgements = taiko::judge(map, replay);
+                Self::calculate_taiko(map, &judgements, replay.mods)
+            }
+            GameMode::CatchTheBeat => {
+                let judgements = catch::judge(map, replay);
+                Self::calculate_catch(map, &judgements, replay.mods)
+            }
+            GameMode::Mania => {
+                let judgements = mania::judge(map, replay);
+                Self::calculate_mania(&judgements, replay.mods)
+            }
+        }
+    }
+
+    pub fn n_300(&self) -> usize {
+        self.n_300
+    }
+
+    pub fn n_100(&self) -> usize {
+        self.n_100
+    }
+
+    pub fn n_50(&self) -> usize {
+        self.n_50
+    }
+
+    pub fn n_geki(&self) -> usize {
+        self.n_geki
+    }
+
+    pub fn n_katu(&self) -> usize {
+        self.n_katu
+    }
+
+    pub fn n_miss(&self) -> usize {
+        self.n_miss
+    }
+
+    pub fn max_combo(&self) -> usize {
+        self.max_combo
+    }
+
+    /// Whether no combo was lost over the whole play.
+    pub fn perfect_combo(&self) -> bool {
+        self.perfect_combo
+    }
+
+    /// The score using the classic scoring system.
+    pub fn score_v1(&self) -> u64 {
+        self.score_v1
+    }
+
+    /// The score using ScoreV2.
+    /// The combo portion weights every object by the combo it was hit at, which closely approximates osu!stable.
+    pub fn score_v2(&self) -> u64 {
+        self.score_v2
+    }
+
+    /// The score shown in-game, which depends on whether ScoreV2 is active.
+    pub fn total_score(&self) -> u64 {
+        if self.mods.contains(Mods::SCORE_V2) {
+            self.score_v2
+        } else {
+            self.score_v1
+        }
+    }
+
+    /// The accuracy between 0 and 1.
+    pub fn accuracy(&self) -> f64 {
+        let total = self.n_300 + self.n_100 + self.n_50 + self.n_miss;
+        if total == 0 {
+            return 1.0;
+        }
+        match self.mode {
+            // Every object caught counts the same, missed tiny droplets are stored as katus
+            GameMode::CatchTheBeat => {
+                let caught = self.n_300 + self.n_100 + self.n_50;
+                caught as f64 / (total + self.n_katu) as f64
+            }
+            // Rainbow 300s (gekis) are worth as much as 300s, 200s (katus) are worth two thirds
+            GameMode::Mania => {
+                let total = total + self.n_geki + self.n_katu;
+                let value = 300 * (self.n_geki + self.n_300)
+                    + 200 * self.n_katu
+                    + 100 * self.n_100
+                    + 50 * self.n_50;
+                value as f64 / (300 * total) as f64
+            }
+            // A good in osu!taiko is worth half a great
+            GameMode::Taiko => (2 * self.n_300 + self.n_100) as f64 / (2 * total) as f64,
+            _ => {
+                (300 * self.n_300 + 100 * self.n_100 + 50 * self.n_50) as f64 / (300 * total) as f64
+            }
+        }
+    }
+
+    pub fn grade(&self) -> Grade {
+        match self.mode {
+            GameMode::CatchTheBeat => return self.accuracy_grade([0.98, 0.94, 0.9, 0.85]),
+            GameMode::Mania => return self.accuracy_grade([0.95, 0.9, 0.8, 0.7]),
+            _ => {}
+        }
+
+        let total = (self.n_300 + self.n_100 + self.n_50 + self.n_miss).max(1) as f64;
+        let ratio_300 = self.n_300 as f64 / total;
+        let ratio_50 = self.n_50 as f64 / total;
+        let silver = self.mods.intersects(Mods::HIDDEN | Mods::FLASHLIGHT);
+
+        if ratio_300 == 1.0 {
+            if silver {
+                Grade::XH
+            } else {
+                Grade::X
+            }
+        } else if ratio_300 > 0.9 && ratio_50 < 0.01 && self.n_miss == 0 {
+            if silver {
+                Grade::SH
+            } else {
+                Grade::S
+            }
+        } else if (ratio_300 > 0.8 && self.n_miss == 0) || ratio_300 > 0.9 {
+            Grade::A
+        } else if (ratio_300 > 0.7 && self.n_miss == 0) || ratio_300 > 0.8 {
+            Grade::B
+        } else if ratio_300 > 0.6 {
+            Grade::C
+        } else {
+            Grade::D
+        }
+    }
+
+    /// Grades in osu!catch and osu!mania only depend on accuracy.
+    /// `thresholds` are the accuracies which have to be exceeded for S, A, B and C.
+    fn accuracy_grade(&self, thresholds: [f64; 4]) -> Grade {
+        let accuracy = self.accuracy();
+        let [s, a, b, c] = thresholds;
+        let silver = self.mods.intersects(Mods::HIDDEN | Mods::FLASHLIGHT);
+        if accuracy == 1.0 {
+            if silver {
+                Grade::XH
+            } else {
+                Grade::X
+            }
+        } else if accuracy > s {
+            if silver {
+                Grade::SH
+            } else {
+                Grade::S
+            }
+        } else if accuracy > a {
+            Grade::A
+        } else if accuracy > b {
+            Grade::B
+        } else if accuracy > c {
+            Grade::C
+        } else {
+            Grade::D
+        }
+    }
+
+    /// Compares the computed values with the ones stored in the replay.
+    /// Any mismatch hints at a corrupted or tampered replay (or an inaccuracy in the judgement simulation).
+    ///
+    /// `score_tolerance` is the relative difference between the recorded and computed total score which is still accepted.
+    /// Slider and spinner judgements are approximations, so the score cannot always be reproduced exactly.
+    pub fn validate(&self, replay: &Replay, score_tolerance: f64) -> Vec<Mismatch> {
+        let mut mismatches = vec![];
+        let mut compare = |field, recorded: u64, computed: u64| {
+            if recorded != computed {
+                mismatches.push(Mismatch {
+                    field,
+                    recorded,
+                    computed,
+                });
+            }
+        };
+
+        compare("n_300", replay.n_300 as u64, self.n_300 as u64);
+        compare("n_100", replay.n_100 as u64, self.n_100 as u64);
+        compare("n_50", replay.n_50 as u64, self.n_50 as u64);
+        compare("n_geki", replay.n_geki as u64, self.n_geki as u64);
+        compare("n_katu", replay.n_katu as u64, self.n_katu as u64);
+        compare("n_miss", replay.n_miss as u64, self.n_miss as u64);
+        compare("max_combo", replay.max_combo as u64, self.max_combo as u64);
+        compare(
+            "perfect_combo",
+            replay.perfect_combo as u64,
+            self.perfect_combo as u64,
+        );
+
+        let recorded_score = replay.total_score as u64;
+        let difference = recorded_score.abs_diff(self.total_score()) as f64;
+        if difference > recorded_score as f64 * score_tolerance {
+            mismatches.push(Mismatch {
+                field: "total_score",
+                recorded: recorded_score,
+                computed: self.total_score(),
+            });
+        }
+
+        mismatches
+    }
+}
+
+/// A value stored in a replay which differs from the recomputed one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mismatch {
+    field: &'static str,
+    recorded: u64,
+    computed: u64,
+}
+
+impl Mismatch {
+    /// The name of the replay field which does not match.
+    pub fn field(&self) -> &'static str {
+        self.field
+    }
+
+    pub fn recorded(&self) -> u64 {
+        self.recorded
+    }
+
+    pub fn computed(&self) -> u64 {
+        self.computed
+    }
+}
+
+/// The score multiplier for the difficulty of the beatmap, ranging from 2 to 6 in practice.
+/// Only the unmodified difficulty settings and the drain time are taken into account.
+fn difficulty_multiplier(map: &Beatmap) -> f64 {
+    let difficulty = map.difficulty();
+    let objects = map.hit_objects();
+    let drain_seconds = match (objects.first(), objects.last()) {
+        (Some(first), Some(last)) => {
+            let break_time = map
+                .break_periods()
+                .iter()
+                .map(|b| b.duration())
+                .sum::<u64>();
+            (last.timestamp() as i64 - first.timestamp() as i64 - break_time as i64) / 1000
+        }
+        _ => 0,
+    };
+    let density = if drain_seconds > 0 {
+        (objects.len() as f64 / drain_seconds as f64 * 8.0).clamp(0.0, 16.0)
+    } else {
+        16.0
+    };
+
+    ((difficulty.hp_drain_rate() as f64
+        + difficulty.overall_difficulty() as f64
+        + difficulty.circle_size() as f64
+        + density)
+        / 38.0
+        * 5.0)
+        .round()
+}
+
+fn score_v1_mod_multiplier(mods: Mods) -> f64 {
+    if mods.intersects(Mods::RELAX | Mods::RELAX2 | Mods::AUTOPLAY) {
+        return 0.0;
+    }
+
+    [
+        (Mods::NO_FAIL, 0.5),
+        (Mods::EASY, 0.5),
+        (Mods::HALF_TIME, 0.3),
+        (Mods::HIDDEN, 1.06),
+        (Mods::HARD_ROCK, 1.06),
+        (Mods::DOUBLE_TIME | Mods::NIGHTCORE, 1.12),
+        (Mods::FLASHLIGHT, 1.12),
+        (Mods::SPUN_OUT, 0.9),
+    ]
+    .iter()
+    .filter(|(m, _)| mods.intersects(*m))
+    .map(|(_, multiplier)| multiplier)
+    .product()
+}
+
+/// osu!mania only reduces the score for mods which make the beatmap easier.
+fn mania_mod_multiplier(mods: Mods) -> f64 {
+    [
+        (Mods::NO_FAIL, 0.5),
+        (Mods::EASY, 0.5),
+        (Mods::HALF_TIME, 0.5),
+    ]
+    .iter()
+    .filter(|(m, _)| mods.intersects(*m))
+    .map(|(_, multiplier)| multiplier)
+    .product()
+}
+
+fn score_v2_mod_multiplier(mods: Mods) -> f64 {
+    [
+        (Mods::EASY, 0.5),
+        (Mods::HALF_TIME, 0.3),
+        (Mods::HIDDEN, 1.06),
+        (Mods::HARD_ROCK, 1.1),
+        (Mods::DOUBLE_TIME | Mods::NIGHTCORE, 1.2),
+        (Mods::FLASHLIGHT, 1.12),
+        (Mods::SPUN_OUT, 0.9),
+    ]
+    .iter()
+    .filter(|(m, _)| mods.intersects(*m))
+    .map(|(_, multiplier)| multiplier)
+    .product()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_data;
+
+    use super::*;
+
+    #[test]
+    fn scores_of_sample_replays_validate() {
+        let samples = test_data::replays_with_maps();
+        assert!(!samples.is_empty());
+        for (path, replay, map) in samples {
+            let score = Score::for_replay(&map, &replay);
+            let mismatches = score.validate(&replay, 0.005);
+            assert!(mismatches.is_empty(), "{}: {mismatches:?}", path.display());
+        }
+    }
+
+    #[test]
+    fn validation_reports_tampered_fields() {
+        let (_, mut replay, map) = test_data::replays_with_maps().remove(0);
+        let score = Score::for_replay(&map, &replay);
+        replay.n_300 += 1;
+        replay.total_score /= 2;
+
+        let fields = score
+            .validate(&replay, 0.005)
+            .iter()
+            .map(Mismatch::field)
+            .collect::<Vec<_>>();
+        assert_eq!(fields, ["n_300", "total_score"]);
+    }
+
+    #[test]
+    fn validation_without_tolerance_reports_any_score_difference() {
+        let (_, mut replay, map) = test_data::replays_with_maps().remove(0);
+        let score = Score::for_replay(&map, &replay);
+        replay.total_score = score.total_score() as u32 + 1;
+        assert!(score.validate(&replay, 0.005).is_empty());
+
+        let fields = score
+            .validate(&replay, 0.0)
+            .iter()
+            .map(Mismatch::field)
+            .collect::<Vec<_>>();
+        assert_eq!(fields, ["total_score"]);
+    }
+}
@@ -0,0 +1,285 @@
+use crate::{
+    aim::AimAnalysis,
+    cursor::CursorTimeline,
+    hit_error::HitErrors,
+    input::{Key, KeyInputs, KeyPress},
+    judgement,
+    map::Beatmap,
+    osu_data::GameMode,
+    replay::Replay,
+};
+
+/// Humans rarely play with an unstable rate below this.
+const CONSISTENT_UNSTABLE_RATE: f64 = 50.0;
+/// Humans rarely hold their keys for durations which deviate less than this (in real time, milliseconds).
+const CONSISTENT_HOLD_DEVIATION: f64 = 15.0;
+/// Humans usually press at least this fraction of their keys where there is nothing to hit.
+const USUAL_EXTRA_PRESS_RATIO: f64 = 0.01;
+/// Humans rarely hit closer to the centre than this on average, relative to the radius.
+const CENTRED_DISTANCE: f64 = 0.25;
+/// Humans slow their cursor down before clicking, so the jerk before clicks is rarely above average.
+const USUAL_SNAPPING_RATIO: f64 = 1.0;
+/// The time before a click in which the cursor is checked for snapping (in milliseconds).
+const SNAP_WINDOW: f64 = 50.0;
+/// The time between the cursor samples the jerk is measured at, one frame at 60 fps (in milliseconds).
+const SAMPLE_INTERVAL: f64 = 1000.0 / 60.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SignalKind {
+    /// The unstable rate of the hits. Relax hits objects with inhumanly consistent timing.
+    UnstableRate,
+    /// The standard deviation of how long the keys were held (in real time, milliseconds).
+    /// Relax and tapping programs often release their keys after a fixed time.
+    HoldDuration,
+    /// The fraction of presses which did not start in the 50 hit window of any circle or slider.
+    /// Humans press keys where there is nothing to hit, relax only where there is.
+    ExtraPresses,
+    /// The average distance from the centre of the hit circles relative to the radius.
+    /// Aim assistance pulls the cursor towards the centre.
+    CenterDistance,
+    /// The average jerk (change in acceleration) of the cursor shortly before clicks
+    /// relative to its average over the whole play. Aim assistance makes the cursor snap onto objects.
+    Snapping,
+}
+
+/// A measurement of a play which is commonly associated with relax hacks or aim assistance.
+#[derive(Debug, Clone, Copy)]
+pub struct Signal {
+    kind: SignalKind,
+    value: f64,
+    score: f64,
+}
+
+impl Signal {
+    pub fn kind(&self) -> SignalKind {
+        self.kind
+    }
+
+    /// The measured value, see [`SignalKind`] for what it means.
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// How unusual the value is for a human, between 0 (normal) and 1 (very suspicious).
+    pub fn score(&self) -> f64 {
+        self.score
+    }
+}
+
+/// Signals of a replay which are commonly associated with relax hacks and aim assistance.
+///
+/// Every signal is scored separately, since each of them can have innocent explanations, e.g. a very
+/// consistent player or a map with few objects. They are meant to point a person reviewing a replay
+/// to what is worth looking at, not to decide whether a replay is legitimate.
+/// Signals which don't apply to the replay's game mode are not included.
+#[derive(Debug, Clone, Default)]
+pub struct SignalReport {
+    signals: Vec<Signal>,
+}
+
+impl SignalReport {
+    /// Analyses a replay of the given (unmodified) beatmap.
+    pub fn new(map: &Beatmap, replay: &Replay) -> Self {
+        let mut signals = vec![];
+
+        if let Some(unstable_rate) = HitErrors::for_replay(map, replay).unstable_rate() {
+            signals.push(Signal {
+                kind: SignalKind::UnstableRate,
+                value: unstable_rate,
+                score: (1.0 - unstable_rate / CONSISTENT_UNSTABLE_RATE).clamp(0.0, 1.0),
+            });
+        }
+
+        if matches!(replay.mode, GameMode::Standard | GameMode::Taiko) {
+            let inputs = KeyInputs::new(replay);
+            let presses = inputs
+                .presses()
+                .iter()
+                .filter(|press| press.key() != Key::Smoke)
+                .collect::<Vec<_>>();
+            if let Some(deviation) = std_dev(
+                presses
+                    .iter()
+                    .map(|press| press.duration() / replay.mods.clock_rate()),
+            ) {
+                signals.push(Signal {
+                    kind: SignalKind::HoldDuration,
+                    value: deviation,
+                    score: (1.0 - deviation / CONSISTENT_HOLD_DEVIATION).clamp(0.0, 1.0),
+                });
+            }
+
+            if matches!(replay.mode, GameMode::Standard) && !presses.is_empty() {
+                let ratio = extra_press_ratio(map, replay, &presses);
+                signals.push(Signal {
+                    kind: SignalKind::ExtraPresses,
+                    value: ratio,
+                    score: (1.0 - ratio / USUAL_EXTRA_PRESS_RATIO).clamp(0.0, 1.0),
+                });
+            }
+        }
+
+        if let Ok(judgements) = judgement::standard::judge(map, replay) {
+            if let Some(distance) = AimAnalysis::new(map, &judgements, replay.mods).mean_distance()
+            {
+                signals.push(Signal {
+                    kind: SignalKind::CenterDistance,
+                    value: distance,
+                    score: (1.0 - distance / CENTRED_DISTANCE).clamp(0.0, 1.0),
+                });
+            }
+
+            let click_times = judgements
+                .iter()
+                .filter_map(|judgement| {
+                    let timestamp = map.hit_objects()[judgement.object_index()].timestamp();
+                    Some(timestamp as f64 + judgement.hit_error()?)
+                })
+                .collect::<Vec<_>>();
+            if let Some(ratio) = snapping_ratio(&CursorTimeline::new(replay), &click_times) {
+                signals.push(Signal {
+                    kind: SignalKind::Snapping,
+                    value: ratio,
+                    score: ((ratio - USUAL_SNAPPING_RATIO) / 2.0).clamp(0.0, 1.0),
+                });
+            }
+        }
+
+        Self { signals }
+    }
+
+    pub fn signals(&self) -> &[Signal] {
+        &self.signals
+    }
+}
+
+/// The fraction of presses which didn't start within the 50 hit window of a circle or slider.
+fn extra_press_ratio(map: &Beatmap, replay: &Replay, presses: &[&KeyPress]) -> f64 {
+    let window = map
+        .difficulty()
+        .for_replay(replay)
+        .hit_windows()
+        .window_50();
+    let mut timestamps = map
+        .hit_objects()
+        .iter()
+        .filter(|obj| !obj.is_spinner())
+        .map(|obj| obj.timestamp() as f64)
+        .collect::<Vec<_>>();
+    timestamps.sort_by(f64::total_cmp);
+
+    let extra = presses
+        .iter()
+        .filter(|press| {
+            let next = timestamps.partition_point(|&time| time < press.start());
+            let distance = [next.checked_sub(1), Some(next)]
+                .into_iter()
+                .flatten()
+                .filter_map(|i| timestamps.get(i))
+                .map(|&time| (time - press.start()).abs())
+                .fold(f64::INFINITY, f64::min);
+            distance > window
+        })
+        .count();
+    extra as f64 / presses.len() as f64
+}
+
+/// The average jerk of the cursor in the snap window before the clicks divided by its average from the first to the last click.
+fn snapping_ratio(timeline: &CursorTimeline, click_times: &[f64]) -> Option<f64> {
+    let (&first, &last) = (click_times.first()?, click_times.last()?);
+    let sample_count = ((last - first) / SAMPLE_INTERVAL) as usize + 1;
+    let overall =
+        mean((0..sample_count).filter_map(|i| jerk(timeline, first + i as f64 * SAMPLE_INTERVAL)))?;
+
+    let before_clicks = mean(click_times.iter().flat_map(|&click| {
+        let steps = (SNAP_WINDOW / SAMPLE_INTERVAL) as usize;
+        (0..=steps).filter_map(move |i| jerk(timeline, click - i as f64 * SAMPLE_INTERVAL))
+    }))?;
+    (overall > 0.0).then(|| before_clicks / overall)
+}
+
+/// The magnitude of the change in acceleration at the given time in osu!pixels per cubic millisecond.
+fn jerk(timeline: &CursorTimeline, time: f64) -> Option<f64> {
+    let half_step = SAMPLE_INTERVAL / 2.0;
+    let before = timeline.acceleration_at(time - half_step)?;
+    let after = timeline.acceleration_at(time + half_step)?;
+    Some((after.0 - before.0).hypot(after.1 - before.1) / SAMPLE_INTERVAL)
+}
+
+fn mean(values: impl IntoIterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values
+        .into_iter()
+        .fold((0.0, 0), |(sum, count), value| (sum + value, count + 1));
+    (count > 0).then(|| sum / count as f64)
+}
+
+/// The population standard deviation of the values.
+fn std_dev(values: impl IntoIterator<Item = f64> + Clone) -> Option<f64> {
+    let mean_value = mean(values.clone())?;
+    let variance = mean(values.into_iter().map(|value| (value - mean_value).powi(2)))?;
+    Some(variance.sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{autoplay::Autoplay, replay::Mods, test_data};
+
+    use super::*;
+
+    fn kinds(report: &SignalReport) -> Vec<SignalKind> {
+        report.signals().iter().map(Signal::kind).collect()
+    }
+
+    fn score(report: &SignalReport, kind: SignalKind) -> f64 {
+        let signal = report.signals().iter().find(|signal| signal.kind() == kind);
+        signal.unwrap().score()
+    }
+
+    #[test]
+    fn human_play_is_not_suspicious() {
+        let (_, replay, map) = test_data::replays_with_maps().remove(0);
+        let report = SignalReport::new(&map, &replay);
+        assert_eq!(
+            kinds(&report),
+            [
+                SignalKind::UnstableRate,
+                SignalKind::HoldDuration,
+                SignalKind::ExtraPresses,
+                SignalKind::CenterDistance,
+                SignalKind::Snapping,
+            ]
+        );
+        assert!(
+            report.signals().iter().all(|signal| signal.score() == 0.0),
+            "{report:?}"
+        );
+    }
+
+    #[test]
+    fn autoplay_hits_perfectly_on_time_and_centre() {
+        let map = test_data::map_named("Another");
+        let replay = Autoplay::new(GameMode::Standard, Mods::empty()).generate(&map);
+        let report = SignalReport::new(&map, &replay);
+        assert_eq!(score(&report, SignalKind::UnstableRate), 1.0);
+        assert!(score(&report, SignalKind::CenterDistance) > 0.99);
+        assert!(score(&report, SignalKind::ExtraPresses) > 0.5);
+    }
+
+    #[test]
+    fn signals_of_other_modes() {
+        let map = test_data::map_named("Another");
+        let report =
+            |mode| SignalReport::new(&map, &Autoplay::new(mode, Mods::empty()).generate(&map));
+
+        let taiko = report(GameMode::Taiko);
+        assert_eq!(
+            kinds(&taiko),
+            [SignalKind::UnstableRate, SignalKind::HoldDuration]
+        );
+        // Autoplay releases every key after the same time
+        assert_eq!(score(&taiko, SignalKind::HoldDuration), 1.0);
+
+        assert_eq!(kinds(&report(GameMode::Mania)), [SignalKind::UnstableRate]);
+        assert!(report(GameMode::CatchTheBeat).signals().is_empty());
+    }
+}
@@ -0,0 +1,391 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    cursor::CursorTimeline,
+    osu_data::GameMode,
+    replay::{read_replays, Replay},
+};
+
+/// The time between the positions which are compared, one frame at 60 fps (in milliseconds).
+const SAMPLE_INTERVAL: f64 = 1000.0 / 60.0;
+/// How far one replay may be shifted in time against the other to line them up (in milliseconds).
+const MAX_TIME_OFFSET: i64 = 100;
+const TIME_OFFSET_STEP: i64 = 2;
+
+/// The cursors of both replays at the same time.
+#[derive(Debug, Clone, Copy)]
+struct SamplePair {
+    time: f64,
+    distance: f64,
+    movements: ((f64, f64), (f64, f64)),
+}
+
+/// How similar the cursor paths of two replays of the same beatmap are, which can reveal a replay
+/// that was copied from another one and possibly altered slightly.
+///
+/// Plays of the same beatmap all aim at the same objects, so their paths are always close on the circles.
+/// A copied replay however also follows the other one between the objects and moves the same way,
+/// so a low mean distance together with a high correlation of the movements is suspicious.
+///
+/// Both replays are compared without the reflection of Hard Rock or Mirror, so adding or removing them doesn't hide a copy.
+/// All times are in song time of the first replay.
+#[derive(Debug, Clone)]
+pub struct Similarity {
+    time_offset: f64,
+    samples: Vec<SamplePair>,
+}
+
+/// The similarity of two replays in a section of time.
+#[derive(Debug, Clone, Copy)]
+pub struct SectionSimilarity {
+    start: f64,
+    mean_distance: f64,
+    correlation: Option<f64>,
+}
+
+impl SectionSimilarity {
+    /// The start time of the section.
+    pub fn start(&self) -> f64 {
+        self.start
+    }
+
+    /// The average distance between the cursors in osu!pixels.
+    pub fn mean_distance(&self) -> f64 {
+        self.mean_distance
+    }
+
+    /// The correlation of the cursor movements, see [`Similarity::correlation`].
+    pub fn correlation(&self) -> Option<f64> {
+        self.correlation
+    }
+}
+
+impl Similarity {
+    /// Compares the cursor paths of two replays of the same beatmap in the same mode.
+    ///
+    /// The second replay is shifted by up to 100 ms in either direction to line it up with the first,
+    /// since a copy may have been offset slightly.
+    pub fn new(replay: &Replay, other: &Replay) -> Result<Self, &'static str> {
+        if replay.map_md5_hash != other.map_md5_hash {
+            return Err("Replays are of different beatmaps");
+        }
+        if replay.mode != other.mode {
+            return Err("Replays are of different game modes");
+        }
+        if matches!(replay.mode, GameMode::Mania) {
+            return Err("osu!mania replays have no cursor to compare");
+        }
+
+        let timeline = unreflected_timeline(replay);
+        let other_timeline = unreflected_timeline(other);
+        let (Some(start), Some(end), Some(other_start), Some(other_end)) = (
+            timeline.start_time(),
+            timeline.end_time(),
+            other_timeline.start_time(),
+            other_timeline.end_time(),
+        ) else {
+            return Err("Replays have no frames to compare");
+        };
+        let (start, end) = (start.max(other_start), end.min(other_end));
+        if end <= start {
+            return Err("Replays do not overlap in time");
+        }
+
+        let sample_count = ((end - start) / SAMPLE_INTERVAL).floor() as usize + 1;
+        let times = (0..sample_count)
+            .map(|i| start + i as f64 * SAMPLE_INTERVAL)
+            .collect::<Vec<_>>();
+        let positions = positions(&timeline, &times, 0.0);
+        let time_offset = (-MAX_TIME_OFFSET..=MAX_TIME_OFFSET)
+            .step_by(TIME_OFFSET_STEP as usize)
+            .map(|offset| {
+                let offset = offset as f64;
+                let other_positions = self::positions(&other_timeline, &times, offset);
+                (offset, mean_distance(&positions, &other_positions))
+            })
+            .min_by(|(a, distance_a), (b, distance_b)| {
+                distance_a
+                    .total_cmp(distance_b)
+                    .then(a.abs().total_cmp(&b.abs()))
+            })
+            .map_or(0.0, |(offset, _)| offset);
+        let other_positions = self::positions(&other_timeline, &times, time_offset);
+
+        let samples = times
+            .iter()
+            .enumerate()
+            .map(|(i, &time)| {
+                let (a, b) = (positions[i], other_positions[i]);
+                let movements = match i.checked_sub(1) {
+                    Some(previous) => (
+                        movement(positions[previous], a),
+                        movement(other_positions[previous], b),
+                    ),
+                    None => ((0.0, 0.0), (0.0, 0.0)),
+                };
+                SamplePair {
+                    time,
+                    distance: distance(a, b),
+                    movements,
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            time_offset,
+            samples,
+        })
+    }
+
+    /// How far the second replay was shifted in time to line it up with the first (in milliseconds).
+    /// A positive offset means the second replay is behind the first.
+    pub fn time_offset(&self) -> f64 {
+        self.time_offset
+    }
+
+    /// The time of every compared position together with the distance between the cursors in osu!pixels.
+    pub fn distances(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.samples
+            .iter()
+            .map(|sample| (sample.time, sample.distance))
+    }
+
+    /// The average distance between the cursors in osu!pixels.
+    pub fn mean_distance(&self) -> f64 {
+        self.samples
+            .iter()
+            .map(|sample| sample.distance)
+            .sum::<f64>()
+            / self.samples.len() as f64
+    }
+
+    /// The fraction of the time the cursors were closer than the given distance in osu!pixels.
+    pub fn fraction_within(&self, distance: f64) -> f64 {
+        let within = self
+            .samples
+            .iter()
+            .filter(|sample| sample.distance < distance)
+            .count();
+        within as f64 / self.samples.len() as f64
+    }
+
+    /// The correlation between the movements of the cursors, between -1 and 1.
+    /// Cursors which always move in the same direction by the same amount have a correlation of 1.
+    ///
+    /// Not present if one of the cursors never moved.
+    pub fn correlation(&self) -> Option<f64> {
+        correlation(&self.samples)
+    }
+
+    /// The similarity in consecutive sections of the given length, which shows whether only parts of a replay were copied.
+    pub fn sections(&self, section_length: f64) -> Vec<SectionSimilarity> {
+        let Some(first) = self.samples.first() else {
+            return vec![];
+        };
+        let mut sections: Vec<Vec<SamplePair>> = vec![];
+        for sample in &self.samples {
+            let index = ((sample.time - first.time) / section_length) as usize;
+            if index >= sections.len() {
+                sections.resize(index + 1, vec![]);
+            }
+            sections[index].push(*sample);
+        }
+
+        sections
+            .into_iter()
+            .enumerate()
+            .filter(|(_, samples)| !samples.is_empty())
+            .map(|(i, samples)| SectionSimilarity {
+                start: first.time + i as f64 * section_length,
+                mean_distance: samples.iter().map(|sample| sample.distance).sum::<f64>()
+                    / samples.len() as f64,
+                correlation: correlation(&samples),
+            })
+            .collect()
+    }
+}
+
+/// Compares the replay against every replay of the same beatmap in the given directory,
+/// ordered from the most to the least similar by mean distance.
+///
+/// Files which are not replays, can't be read or are corrupt, replays of other beatmaps or modes
+/// and copies of the replay itself (with the same replay hash) are skipped.
+pub fn compare_directory(
+    replay: &Replay,
+    directory: impl AsRef<Path>,
+) -> std::io::Result<Vec<(PathBuf, Similarity)>> {
+    let mut similarities = read_replays(directory)?
+        .filter(|(_, other)| other.replay_md5_hash != replay.replay_md5_hash)
+        .filter_map(|(path, other)| Some((path, Similarity::new(replay, &other).ok()?)))
+        .collect::<Vec<_>>();
+    similarities.sort_by(|(_, a), (_, b)| a.mean_distance().total_cmp(&b.mean_distance()));
+    Ok(similarities)
+}
+
+fn unreflected_timeline(replay: &Replay) -> CursorTimeline {
+    let mut replay = replay.clone();
+    replay.unreflect_frames();
+    CursorTimeline::new(&replay)
+}
+
+/// The positions of the cursor at the given times shifted by the offset.
+fn positions(timeline: &CursorTimeline, times: &[f64], offset: f64) -> Vec<(f64, f64)> {
+    times
+        .iter()
+        .map(|&time| {
+            let (x, y) = timeline.position_at(time + offset).unwrap_or_default();
+            (x as f64, y as f64)
+        })
+        .collect()
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+fn movement(from: (f64, f64), to: (f64, f64)) -> (f64, f64) {
+    (to.0 - from.0, to.1 - from.1)
+}
+
+fn mean_distance(positions: &[(f64, f64)], other_positions: &[(f64, f64)]) -> f64 {
+    positions
+        .iter()
+        .zip(other_positions)
+        .map(|(&a, &b)| distance(a, b))
+        .sum::<f64>()
+        / positions.len() as f64
+}
+
+/// The Pearson correlation of the movement vectors, treating both axes as one series.
+fn correlation(samples: &[SamplePair]) -> Option<f64> {
+    let n = 2.0 * samples.len() as f64;
+    let values = || {
+        samples.iter().flat_map(|sample| {
+            let ((ax, ay), (bx, by)) = sample.movements;
+            [(ax, bx), (ay, by)]
+        })
+    };
+    let (mean_a, mean_b) = values().fold((0.0, 0.0), |(sum_a, sum_b), (a, b)| {
+        (sum_a + a / n, sum_b + b / n)
+    });
+    let (covariance, variance_a, variance_b) =
+        values().fold((0.0, 0.0, 0.0), |(cov, var_a, var_b), (a, b)| {
+            let (a, b) = (a - mean_a, b - mean_b);
+            (cov + a * b, var_a + a * a, var_b + b * b)
+        });
+    (variance_a > 0.0 && variance_b > 0.0).then(|| covariance / (variance_a * variance_b).sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{osu_data::Reflection, replay::Mods, test_data};
+
+    use super::*;
+
+    fn tiferet() -> Replay {
+        test_data::replays_with_maps().remove(0).1
+    }
+
+    #[test]
+    fn replay_is_identical_to_itself() {
+        let replay = tiferet();
+        let similarity = Similarity::new(&replay, &replay).unwrap();
+        assert_eq!(similarity.time_offset(), 0.0);
+        assert_eq!(similarity.mean_distance(), 0.0);
+        assert_eq!(similarity.fraction_within(1.0), 1.0);
+        assert!((similarity.correlation().unwrap() - 1.0).abs() < 1e-9);
+        assert!(similarity
+            .sections(10000.0)
+            .iter()
+            .all(|section| section.mean_distance() == 0.0));
+    }
+
+    #[test]
+    fn shifted_copies_are_lined_up() {
+        let replay = tiferet();
+        let copy = replay.clone().shifted(20);
+        let similarity = Similarity::new(&replay, &copy).unwrap();
+        assert_eq!(similarity.time_offset(), 20.0);
+        assert!(
+            similarity.mean_distance() < 0.01,
+            "{}",
+            similarity.mean_distance()
+        );
+        assert!(similarity.correlation().unwrap() > 0.999);
+    }
+
+    #[test]
+    fn reflection_does_not_hide_copies() {
+        let replay = tiferet();
+        let mut copy = replay.clone();
+        copy.mods |= Mods::HARD_ROCK;
+        copy.unreflect_frames();
+        let similarity = Similarity::new(&replay, &copy).unwrap();
+        assert!(
+            similarity.mean_distance() < 0.01,
+            "{}",
+            similarity.mean_distance()
+        );
+    }
+
+    #[test]
+    fn mirrored_paths_are_not_similar() {
+        let replay = tiferet();
+        // Mirrored left to right, so the cursors only meet on the vertical centre line
+        let mut other = replay.clone();
+        other.reflect_frames(Reflection::Horizontal);
+        let similarity = Similarity::new(&replay, &other).unwrap();
+        assert!(
+            similarity.mean_distance() > 50.0,
+            "{}",
+            similarity.mean_distance()
+        );
+        assert!(similarity.correlation().unwrap() < 0.5);
+    }
+
+    #[test]
+    fn only_comparable_replays_are_compared() {
+        let replay = tiferet();
+        let (_, other_map) = test_data::replays()
+            .into_iter()
+            .find(|(_, other)| other.map_md5_hash != replay.map_md5_hash)
+            .unwrap();
+        assert!(Similarity::new(&replay, &other_map).is_err());
+
+        let mut other_mode = replay.clone();
+        other_mode.mode = GameMode::Taiko;
+        assert!(Similarity::new(&replay, &other_mode).is_err());
+
+        let mut mania = replay.clone();
+        mania.mode = GameMode::Mania;
+        assert!(Similarity::new(&mania, &mania).is_err());
+
+        let late = replay.clone().trimmed(0, 1000);
+        assert!(Similarity::new(&late, &replay.clone().trimmed(2000, 3000)).is_err());
+    }
+
+    #[test]
+    fn compare_directory_skips_bad_files() {
+        let replays = test_data::replays();
+        let (_, replay) = &replays[1];
+        let mut copy = replay.clone();
+        copy.update_replay_hash();
+        assert_ne!(copy.replay_md5_hash, replay.replay_md5_hash);
+
+        let directory = test_data::temp_directory("compare-directory");
+        test_data::write_with_bad_files(
+            &directory,
+            &[
+                ("copy.osr", &copy),
+                ("itself.osr", replay),
+                ("other map.osr", &replays[0].1),
+            ],
+        );
+
+        let similarities = compare_directory(replay, &directory).unwrap();
+        std::fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(similarities.len(), 1);
+        assert_eq!(similarities[0].0, directory.join("copy.osr"));
+        assert_eq!(similarities[0].1.mean_distance(), 0.0);
+    }
+}
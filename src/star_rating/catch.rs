@@ -0,0 +1,200 @@
+use crate::{
+    map::{
+        catch::{catch_width, CatchObject, CatchObjectKind},
+        Beatmap,
+    },
+    replay::Mods,
+};
+
+use super::{decaying_strain_peaks, weighted_difficulty, StrainSeries};
+
+const STAR_SCALING_FACTOR: f64 = 0.153;
+const SKILL_MULTIPLIER: f64 = 900.0;
+const STRAIN_DECAY_BASE: f64 = 0.2;
+const DECAY_WEIGHT: f64 = 0.94;
+const SECTION_LENGTH: f64 = 750.0;
+
+/// Positions are scaled as if every fruit had this radius relative to the catcher.
+const NORMALISED_HITOBJECT_RADIUS: f64 = 41.0;
+/// How far from the ideal position players are assumed to catch fruits.
+const ABSOLUTE_PLAYER_POSITIONING_ERROR: f64 = 16.0;
+const DIRECTION_CHANGE_BONUS: f64 = 21.0;
+/// Objects closer together than this many milliseconds are treated as if they were this far apart.
+const MIN_STRAIN_TIME: f64 = 40.0;
+/// Objects which can be reached with less than this many osu!pixels to spare are edge dashes.
+const EDGE_DASH_THRESHOLD: f64 = 20.0;
+
+/// The difficulty of a beatmap in osu!catch with a set of mods, which only depends on how the catcher has to move.
+#[derive(Debug, Clone, Copy)]
+pub struct CatchDifficulty {
+    stars: f64,
+    approach_rate: f32,
+    n_fruits: usize,
+    n_droplets: usize,
+    n_tiny_droplets: usize,
+}
+
+impl CatchDifficulty {
+    /// Calculates the difficulty of the (unmodified) beatmap played with the given mods.
+    pub fn calculate(map: &Beatmap, mods: Mods) -> Self {
+        let effective = map.difficulty().with_mods(mods);
+        let objects = map.catch_objects(mods);
+        let count = |kind| objects.iter().filter(|obj| obj.kind() == kind).count();
+
+        let peaks = movement_peaks(map, mods, &objects);
+        let stars = weighted_difficulty(&peaks, 0, 1.0, DECAY_WEIGHT).sqrt() * STAR_SCALING_FACTOR;
+
+        Self {
+            stars,
+            approach_rate: effective.approach_rate(),
+            n_fruits: count(CatchObjectKind::Fruit),
+            n_droplets: count(CatchObjectKind::Droplet),
+            n_tiny_droplets: count(CatchObjectKind::TinyDroplet),
+        }
+    }
+
+    /// The movement strain over the course of the (unmodified) beatmap played with the given mods.
+    pub fn strains(map: &Beatmap, mods: Mods) -> Vec<StrainSeries> {
+        let clock_rate = map.difficulty().with_mods(mods).clock_rate();
+        let objects = map.catch_objects(mods);
+        let first_time = objects
+            .iter()
+            .filter(|obj| obj.kind().affects_combo())
+            .nth(1)
+            .map(|obj| obj.time() / clock_rate);
+        let peaks = movement_peaks(map, mods, &objects);
+        vec![StrainSeries::new(
+            "movement",
+            peaks,
+            first_time,
+            SECTION_LENGTH,
+            clock_rate,
+        )]
+    }
+
+    pub fn stars(&self) -> f64 {
+        self.stars
+    }
+
+    /// The approach rate in real time.
+    pub fn approach_rate(&self) -> f32 {
+        self.approach_rate
+    }
+
+    /// The combo of a full combo, which is the number of fruits and droplets.
+    pub fn max_combo(&self) -> usize {
+        self.n_fruits + self.n_droplets
+    }
+
+    pub fn n_fruits(&self) -> usize {
+        self.n_fruits
+    }
+
+    pub fn n_droplets(&self) -> usize {
+        self.n_droplets
+    }
+
+    pub fn n_tiny_droplets(&self) -> usize {
+        self.n_tiny_droplets
+    }
+}
+
+/// The section peaks of the movement strain, given the catch objects of the beatmap with the mods.
+fn movement_peaks(map: &Beatmap, mods: Mods, objects: &[CatchObject]) -> Vec<f64> {
+    let effective = map.difficulty().with_mods(mods);
+    let circle_size = effective.difficulty().circle_size();
+    // Small catchers are harder to position precisely than their width suggests
+    let half_catcher_width = catch_width(circle_size) as f64
+        * 0.5
+        * (1.0 - (circle_size as f64 - 5.5).max(0.0) * 0.0625);
+    let palpable = objects
+        .iter()
+        .filter(|obj| obj.kind().affects_combo())
+        .copied()
+        .collect::<Vec<_>>();
+
+    decaying_strain_peaks(
+        movement_strains(&palpable, half_catcher_width, effective.clock_rate()),
+        SKILL_MULTIPLIER,
+        STRAIN_DECAY_BASE,
+        SECTION_LENGTH,
+    )
+}
+
+/// The movement strain of every fruit and droplet but the first, given with its real start time and the time since the previous one.
+///
+/// The catcher is assumed to move as little as possible. Long movements, changes of direction
+/// and edge dashes (movements which can barely be made without a hyperdash) are harder.
+fn movement_strains(
+    objects: &[CatchObject],
+    half_catcher_width: f64,
+    clock_rate: f64,
+) -> Vec<(f64, f64, f64)> {
+    let scaling_factor = NORMALISED_HITOBJECT_RADIUS / half_catcher_width;
+    let catch_range = NORMALISED_HITOBJECT_RADIUS - ABSOLUTE_PLAYER_POSITIONING_ERROR;
+    let mut last_player_position = None;
+    let mut last_distance_moved: f64 = 0.0;
+    let mut last_strain_time: f64 = 0.0;
+
+    objects
+        .windows(2)
+        .map(|pair| {
+            let (last, current) = (&pair[0], &pair[1]);
+            let start_time = current.time() / clock_rate;
+            let delta_time = (current.time() - last.time()) / clock_rate;
+            let strain_time = delta_time.max(MIN_STRAIN_TIME);
+            let position = current.x() as f64 * scaling_factor;
+            let last_position = last.x() as f64 * scaling_factor;
+
+            let previous_player_position = last_player_position.unwrap_or(last_position);
+            let mut player_position =
+                previous_player_position.clamp(position - catch_range, position + catch_range);
+            let distance_moved: f64 = player_position - previous_player_position;
+            let weighted_strain_time = strain_time + 13.0 + 3.0 / clock_rate;
+
+            let mut distance_addition = distance_moved.abs().powf(1.3) / 510.0;
+            let sqrt_strain = weighted_strain_time.sqrt();
+            if distance_moved.abs() > 0.1 {
+                if last_distance_moved.abs() > 0.1
+                    && distance_moved.signum() != last_distance_moved.signum()
+                {
+                    let bonus_factor = distance_moved.abs().min(50.0) / 50.0;
+                    let anti_flow_factor = (last_distance_moved.abs().min(70.0) / 70.0).max(0.38);
+                    distance_addition += DIRECTION_CHANGE_BONUS / (last_strain_time + 16.0).sqrt()
+                        * bonus_factor
+                        * anti_flow_factor
+                        * (1.0 - (weighted_strain_time / 1000.0).powi(3)).max(0.0);
+                }
+                // Base bonus for every movement, giving some weight to streams
+                distance_addition += 12.5
+                    * distance_moved.abs().min(NORMALISED_HITOBJECT_RADIUS * 2.0)
+                    / (NORMALISED_HITOBJECT_RADIUS * 6.0)
+                    / sqrt_strain;
+            }
+
+            let distance_to_hyper_dash = last.distance_to_hyper_dash() as f64;
+            if distance_to_hyper_dash <= EDGE_DASH_THRESHOLD {
+                let edge_dash_bonus = if last.hyper_dash() {
+                    // The hyperdash moves the catcher right to the object
+                    player_position = position;
+                    0.0
+                } else {
+                    5.7
+                };
+                distance_addition *= 1.0
+                    + edge_dash_bonus
+                        * ((EDGE_DASH_THRESHOLD - distance_to_hyper_dash) / EDGE_DASH_THRESHOLD)
+                        * ((strain_time * clock_rate).min(265.0) / 265.0).powf(1.5);
+            }
+
+            last_player_position = Some(player_position);
+            last_distance_moved = distance_moved;
+            last_strain_time = strain_time;
+            (
+                start_time,
+                delta_time,
+                distance_addition / weighted_strain_time,
+            )
+        })
+        .collect()
+}
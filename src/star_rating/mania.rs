@@ -0,0 +1,136 @@
+use crate::{
+    map::{mania::ManiaObject, Beatmap},
+    replay::Mods,
+};
+
+use super::{strain_decay, weighted_difficulty, StrainPeaks, StrainSeries, SECTION_LENGTH};
+
+const STAR_SCALING_FACTOR: f64 = 0.018;
+const INDIVIDUAL_DECAY_BASE: f64 = 0.125;
+const OVERALL_DECAY_BASE: f64 = 0.3;
+/// How close (in milliseconds) the ends of overlapping holds have to be to be released like a single hold.
+const RELEASE_THRESHOLD: f64 = 24.0;
+
+/// The difficulty of a beatmap in osu!mania with a set of mods.
+///
+/// The strain of a note comes from how quickly its column has to be pressed again
+/// and how quickly notes come overall, where holds make the other notes harder to hit.
+#[derive(Debug, Clone, Copy)]
+pub struct ManiaDifficulty {
+    stars: f64,
+    n_notes: usize,
+    n_holds: usize,
+}
+
+impl ManiaDifficulty {
+    /// Calculates the difficulty of the (unmodified) beatmap played with the given mods.
+    pub fn calculate(map: &Beatmap, mods: Mods) -> Self {
+        let clock_rate = map.difficulty().with_mods(mods).clock_rate();
+        let key_count = map.mania_key_count(mods);
+        let objects = map.mania_objects(mods, None);
+
+        let peaks = strain_peaks(&objects, key_count, clock_rate);
+        let stars = weighted_difficulty(&peaks, 0, 1.0, 0.9) * STAR_SCALING_FACTOR;
+
+        let n_holds = objects
+            .iter()
+            .filter(|obj| matches!(obj, ManiaObject::Hold { .. }))
+            .count();
+        Self {
+            stars,
+            n_notes: objects.len() - n_holds,
+            n_holds,
+        }
+    }
+
+    /// The strain over the course of the (unmodified) beatmap played with the given mods.
+    pub fn strains(map: &Beatmap, mods: Mods) -> Vec<StrainSeries> {
+        let clock_rate = map.difficulty().with_mods(mods).clock_rate();
+        let objects = map.mania_objects(mods, None);
+        let peaks = strain_peaks(&objects, map.mania_key_count(mods), clock_rate);
+        let first_time = objects.get(1).map(|obj| obj.start_time() / clock_rate);
+        vec![StrainSeries::new(
+            "strain",
+            peaks,
+            first_time,
+            SECTION_LENGTH,
+            clock_rate,
+        )]
+    }
+
+    pub fn stars(&self) -> f64 {
+        self.stars
+    }
+
+    /// The combo of a full combo, where every hold counts once, just like it is judged once.
+    pub fn max_combo(&self) -> usize {
+        self.n_notes + self.n_holds
+    }
+
+    pub fn n_notes(&self) -> usize {
+        self.n_notes
+    }
+
+    pub fn n_holds(&self) -> usize {
+        self.n_holds
+    }
+}
+
+/// The section peaks of the strain of every object but the first.
+fn strain_peaks(objects: &[ManiaObject], key_count: usize, clock_rate: f64) -> Vec<f64> {
+    let mut start_times = vec![0.0; key_count];
+    let mut end_times = vec![0.0; key_count];
+    let mut individual_strains = vec![0.0; key_count];
+    let mut individual_strain = 0.0;
+    let mut overall_strain = 1.0;
+    let mut peaks = StrainPeaks::default();
+
+    for pair in objects.windows(2) {
+        let (last, current) = (&pair[0], &pair[1]);
+        let last_start = last.start_time() / clock_rate;
+        let start = current.start_time() / clock_rate;
+        let end = current.end_time() / clock_rate;
+        let delta_time = start - last_start;
+        let column = current.column();
+
+        peaks.advance_to(start, |time| {
+            individual_strain * strain_decay(INDIVIDUAL_DECAY_BASE, time - last_start)
+                + overall_strain * strain_decay(OVERALL_DECAY_BASE, time - last_start)
+        });
+
+        // Holding other keys while this object is played makes it harder,
+        // unless the hold ends with this one so both can be released together
+        let mut is_overlapping = false;
+        let mut closest_end_time = (end - last_start).abs();
+        let mut hold_factor = 1.0;
+        for &end_time in &end_times {
+            is_overlapping |= end_time > start + 1.0 && end > end_time + 1.0;
+            if end_time > end + 1.0 {
+                hold_factor = 1.25;
+            }
+            closest_end_time = f64::min(closest_end_time, (end - end_time).abs());
+        }
+        let hold_addition = if is_overlapping {
+            1.0 / (1.0 + (0.5 * (RELEASE_THRESHOLD - closest_end_time)).exp())
+        } else {
+            0.0
+        };
+
+        individual_strains[column] *=
+            strain_decay(INDIVIDUAL_DECAY_BASE, start - start_times[column]);
+        individual_strains[column] += 2.0 * hold_factor;
+        // Chords are as hard as their hardest column
+        individual_strain = if delta_time <= 1.0 {
+            f64::max(individual_strain, individual_strains[column])
+        } else {
+            individual_strains[column]
+        };
+        overall_strain = overall_strain * strain_decay(OVERALL_DECAY_BASE, delta_time)
+            + (1.0 + hold_addition) * hold_factor;
+
+        start_times[column] = start;
+        end_times[column] = end;
+        peaks.add(individual_strain + overall_strain);
+    }
+    peaks.into_peaks()
+}
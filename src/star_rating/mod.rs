@@ -0,0 +1,296 @@
+use crate::{map::Beatmap, osu_data::GameMode, replay::Mods};
+
+use self::{
+    catch::CatchDifficulty, mania::ManiaDifficulty, standard::StandardDifficulty,
+    taiko::TaikoDifficulty,
+};
+
+pub mod catch;
+pub mod mania;
+pub mod standard;
+pub mod taiko;
+
+/// The length of the sections of a beatmap whose highest strains make up a skill's difficulty, in milliseconds.
+const SECTION_LENGTH: f64 = 400.0;
+
+/// The difficulty of a beatmap in one of the game modes.
+#[derive(Debug, Clone, Copy)]
+pub enum DifficultyAttributes {
+    Standard(StandardDifficulty),
+    Taiko(TaikoDifficulty),
+    Catch(CatchDifficulty),
+    Mania(ManiaDifficulty),
+}
+
+impl DifficultyAttributes {
+    /// Calculates the difficulty of the (unmodified) beatmap played in the given mode with the given mods.
+    /// osu!standard beatmaps can be played in every mode, all others only in their own.
+    pub fn calculate(map: &Beatmap, mode: GameMode, mods: Mods) -> Self {
+        match mode {
+            GameMode::Standard => Self::Standard(StandardDifficulty::calculate(map, mods)),
+            GameMode::Taiko => Self::Taiko(TaikoDifficulty::calculate(map, mods)),
+            GameMode::CatchTheBeat => Self::Catch(CatchDifficulty::calculate(map, mods)),
+            GameMode::Mania => Self::Mania(ManiaDifficulty::calculate(map, mods)),
+        }
+    }
+
+    pub fn stars(&self) -> f64 {
+        match self {
+            Self::Standard(difficulty) => difficulty.stars(),
+            Self::Taiko(difficulty) => difficulty.stars(),
+            Self::Catch(difficulty) => difficulty.stars(),
+            Self::Mania(difficulty) => difficulty.stars(),
+        }
+    }
+
+    pub fn max_combo(&self) -> usize {
+        match self {
+            Self::Standard(difficulty) => difficulty.max_combo(),
+            Self::Taiko(difficulty) => difficulty.max_combo(),
+            Self::Catch(difficulty) => difficulty.max_combo(),
+            Self::Mania(difficulty) => difficulty.max_combo(),
+        }
+    }
+
+    /// The strains of every skill of the given mode over the course of the (unmodified) beatmap.
+    pub fn strains(map: &Beatmap, mode: GameMode, mods: Mods) -> Vec<StrainSeries> {
+        match mode {
+            GameMode::Standard => StandardDifficulty::strains(map, mods),
+            GameMode::Taiko => TaikoDifficulty::strains(map, mods),
+            GameMode::CatchTheBeat => CatchDifficulty::strains(map, mods),
+            GameMode::Mania => ManiaDifficulty::strains(map, mods),
+        }
+    }
+}
+
+/// The strain of a skill over the course of a beatmap, given by the highest strain of every section.
+/// Times are in song time (as in the beatmap and replays), so sections are shorter with Half Time.
+#[derive(Debug, Clone)]
+pub struct StrainSeries {
+    skill: &'static str,
+    start_time: f64,
+    section_length: f64,
+    peaks: Vec<f64>,
+}
+
+impl StrainSeries {
+    /// `first_time` is the real start time of the first object given to the skill,
+    /// which decides where its sections start.
+    fn new(
+        skill: &'static str,
+        peaks: Vec<f64>,
+        first_time: Option<f64>,
+        section_length: f64,
+        clock_rate: f64,
+    ) -> Self {
+        let first_section_end =
+            first_time.map_or(0.0, |time| (time / section_length).ceil() * section_length);
+        Self {
+            skill,
+            start_time: (first_section_end - section_length) * clock_rate,
+            section_length: section_length * clock_rate,
+            peaks,
+        }
+    }
+
+    /// The name of the skill, e.g. "aim" or "stamina".
+    pub fn skill(&self) -> &'static str {
+        self.skill
+    }
+
+    /// The time at which the first section starts.
+    pub fn start_time(&self) -> f64 {
+        self.start_time
+    }
+
+    pub fn section_length(&self) -> f64 {
+        self.section_length
+    }
+
+    pub fn peaks(&self) -> &[f64] {
+        &self.peaks
+    }
+
+    /// The end time of every section together with its peak strain, ordered by time.
+    pub fn points(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.peaks
+            .iter()
+            .enumerate()
+            .map(|(i, &peak)| (self.start_time + (i + 1) as f64 * self.section_length, peak))
+    }
+
+    /// The peak strain of the section containing the given time, if there is one.
+    pub fn peak_at(&self, time: f64) -> Option<f64> {
+        // Sections include their end but not their start
+        let section = ((time - self.start_time) / self.section_length).ceil() - 1.0;
+        if section < 0.0 {
+            return None;
+        }
+        self.peaks.get(section as usize).copied()
+    }
+}
+
+/// Collects the highest strain of every section of a beatmap while a skill processes its objects in order.
+#[derive(Debug, Clone)]
+struct StrainPeaks {
+    section_length: f64,
+    peaks: Vec<f64>,
+    section_end: Option<f64>,
+    section_peak: f64,
+}
+
+impl Default for StrainPeaks {
+    fn default() -> Self {
+        Self::new(SECTION_LENGTH)
+    }
+}
+
+impl StrainPeaks {
+    fn new(section_length: f64) -> Self {
+        Self {
+            section_length,
+            peaks: vec![],
+            section_end: None,
+            section_peak: 0.0,
+        }
+    }
+
+    /// Finishes every section which ends before the given (real) time.
+    /// `initial_strain` gives the decayed strain of the skill at the start of a new section.
+    fn advance_to(&mut self, time: f64, initial_strain: impl Fn(f64) -> f64) {
+        let mut section_end = self
+            .section_end
+            .unwrap_or_else(|| (time / self.section_length).ceil() * self.section_length);
+        while time > section_end {
+            self.peaks.push(self.section_peak);
+            self.section_peak = initial_strain(section_end);
+            section_end += self.section_length;
+        }
+        self.section_end = Some(section_end);
+    }
+
+    /// Adds the strain of the current object to the current section.
+    fn add(&mut self, strain: f64) {
+        self.section_peak = self.section_peak.max(strain);
+    }
+
+    /// The peaks of all sections including the current one.
+    fn into_peaks(mut self) -> Vec<f64> {
+        if self.section_end.is_some() {
+            self.peaks.push(self.section_peak);
+        }
+        self.peaks
+    }
+}
+
+/// Combines the section peaks of a skill into its difficulty: the hardest sections count the most,
+/// with every following one weighted `decay_weight` times as much as the one before.
+///
+/// The `reduced_section_count` hardest sections are scaled down (to as little as `reduced_strain_baseline`),
+/// so a few outlying sections don't make a beatmap much harder.
+fn weighted_difficulty(
+    peaks: &[f64],
+    reduced_section_count: usize,
+    reduced_strain_baseline: f64,
+    decay_weight: f64,
+) -> f64 {
+    let mut strains = peaks
+        .iter()
+        .copied()
+        .filter(|&strain| strain > 0.0)
+        .collect::<Vec<_>>();
+    strains.sort_by(|a, b| b.total_cmp(a));
+
+    for (i, strain) in strains.iter_mut().take(reduced_section_count).enumerate() {
+        let progress = (i as f64 / reduced_section_count as f64).clamp(0.0, 1.0);
+        let scale = (1.0 + 9.0 * progress).log10();
+        *strain *= reduced_strain_baseline + (1.0 - reduced_strain_baseline) * scale;
+    }
+    strains.sort_by(|a, b| b.total_cmp(a));
+
+    strains
+        .iter()
+        .zip(std::iter::successors(Some(1.0), |weight| {
+            Some(weight * decay_weight)
+        }))
+        .map(|(strain, weight)| strain * weight)
+        .sum()
+}
+
+/// The section peaks of a skill whose strain decays exponentially over time and increases with every object.
+///
+/// `strains` gives the (real) start time of every object, the time since the previous object and the strain
+/// it adds before being scaled by `skill_multiplier`. `decay_base` is the fraction of strain left after a second.
+fn decaying_strain_peaks(
+    strains: impl IntoIterator<Item = (f64, f64, f64)>,
+    skill_multiplier: f64,
+    decay_base: f64,
+    section_length: f64,
+) -> Vec<f64> {
+    let mut strain = 0.0;
+    let mut peaks = StrainPeaks::new(section_length);
+    for (start_time, delta_time, value) in strains {
+        let previous_start = start_time - delta_time;
+        peaks.advance_to(start_time, |time| {
+            strain * strain_decay(decay_base, time - previous_start)
+        });
+        strain = strain * strain_decay(decay_base, delta_time) + value * skill_multiplier;
+        peaks.add(strain);
+    }
+    peaks.into_peaks()
+}
+
+fn strain_decay(base: f64, time: f64) -> f64 {
+    base.powf(time / 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{autoplay::Autoplay, test_data};
+
+    use super::*;
+
+    const MODES: [GameMode; 4] = [
+        GameMode::Standard,
+        GameMode::Taiko,
+        GameMode::CatchTheBeat,
+        GameMode::Mania,
+    ];
+
+    #[test]
+    fn harder_difficulties_have_more_stars() {
+        let maps = ["Normal", "Advanced", "Insane", "Another"].map(test_data::map_named);
+        for mode in MODES {
+            let stars = maps
+                .iter()
+                .map(|map| DifficultyAttributes::calculate(map, mode, Mods::empty()).stars())
+                .collect::<Vec<_>>();
+            assert!(stars.windows(2).all(|w| w[0] < w[1]), "{mode:?}: {stars:?}");
+        }
+    }
+
+    #[test]
+    fn speed_mods_change_stars() {
+        let map = test_data::map_named("Another");
+        for mode in MODES {
+            let stars = |mods| DifficultyAttributes::calculate(&map, mode, mods).stars();
+            assert!(stars(Mods::HALF_TIME) < stars(Mods::empty()), "{mode:?}");
+            assert!(stars(Mods::empty()) < stars(Mods::DOUBLE_TIME), "{mode:?}");
+            assert_eq!(stars(Mods::DOUBLE_TIME), stars(Mods::NIGHTCORE), "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn max_combo_matches_autoplay() {
+        let map = test_data::map_named("Another");
+        for mode in MODES {
+            let attributes = DifficultyAttributes::calculate(&map, mode, Mods::empty());
+            let replay = Autoplay::new(mode, Mods::empty()).generate(&map);
+            assert_eq!(
+                attributes.max_combo(),
+                replay.max_combo as usize,
+                "{mode:?}"
+            );
+        }
+    }
+}
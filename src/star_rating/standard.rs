@@ -0,0 +1,1007 @@
+use std::f64::consts::PI;
+
+use crate::{
+    judgement::{
+        standard::{slider_checks, SLIDER_END_LENIENCY},
+        SliderPart,
+    },
+    map::{
+        difficulty::EffectiveDifficulty,
+        hit_object::{HitObject, HitObjectData, SliderData},
+        Beatmap,
+    },
+    replay::Mods,
+};
+
+use super::{
+    decaying_strain_peaks, strain_decay, weighted_difficulty, StrainPeaks, StrainSeries,
+    SECTION_LENGTH,
+};
+
+/// Scales the square root of a skill's difficulty to its star rating.
+const DIFFICULTY_MULTIPLIER: f64 = 0.0675;
+/// Distances are scaled as if every circle had this radius.
+const NORMALISED_RADIUS: f64 = 50.0;
+/// Objects closer together than this many milliseconds are treated as if they were this far apart.
+const MIN_DELTA_TIME: f64 = 25.0;
+/// The radius of the follow circle, which the cursor can stay in while still tracking a slider.
+const MAXIMUM_SLIDER_RADIUS: f64 = NORMALISED_RADIUS * 2.4;
+/// How far from the slider ball the cursor is assumed to be when a player moves as little as possible.
+const ASSUMED_SLIDER_RADIUS: f64 = NORMALISED_RADIUS * 1.8;
+/// osu! makes circles slightly larger than their circle size says.
+const RADIUS_ROUNDING_ALLOWANCE: f64 = 1.00041;
+/// Circles with a smaller radius than this get a bonus on their distances.
+const SMALL_CIRCLE_RADIUS: f64 = 30.0;
+
+const AIM_SKILL_MULTIPLIER: f64 = 23.55;
+const AIM_STRAIN_DECAY_BASE: f64 = 0.15;
+const AIM_DIFFICULTY_MULTIPLIER: f64 = 1.06;
+const WIDE_ANGLE_MULTIPLIER: f64 = 1.5;
+const ACUTE_ANGLE_MULTIPLIER: f64 = 1.95;
+const SLIDER_MULTIPLIER: f64 = 1.35;
+const VELOCITY_CHANGE_MULTIPLIER: f64 = 0.75;
+
+const SPEED_SKILL_MULTIPLIER: f64 = 1375.0;
+const SPEED_STRAIN_DECAY_BASE: f64 = 0.3;
+const SPEED_DIFFICULTY_MULTIPLIER: f64 = 1.04;
+const SPEED_REDUCED_SECTION_COUNT: usize = 5;
+/// Jumps up to this distance make streams harder to tap.
+const SINGLE_SPACING_THRESHOLD: f64 = 125.0;
+/// Objects closer together than this many milliseconds get a bonus for their speed.
+const MIN_SPEED_BONUS: f64 = 75.0;
+const SPEED_BALANCING_FACTOR: f64 = 40.0;
+
+/// Rhythm changes which are this many milliseconds in the past don't count any more.
+const RHYTHM_HISTORY_TIME_MAX: f64 = 5000.0;
+const RHYTHM_HISTORY_OBJECTS_MAX: usize = 32;
+const RHYTHM_MULTIPLIER: f64 = 0.75;
+
+const FLASHLIGHT_SKILL_MULTIPLIER: f64 = 0.052;
+const FLASHLIGHT_STRAIN_DECAY_BASE: f64 = 0.15;
+/// How many of the previous objects count towards the difficulty of memorising an object.
+const FLASHLIGHT_HISTORY_OBJECTS_MAX: usize = 10;
+const FLASHLIGHT_MAX_OPACITY_BONUS: f64 = 0.4;
+const FLASHLIGHT_HIDDEN_BONUS: f64 = 0.2;
+/// Sliders slower than this many osu!pixels per millisecond don't get a bonus.
+const FLASHLIGHT_MIN_SLIDER_VELOCITY: f64 = 0.5;
+const FLASHLIGHT_SLIDER_MULTIPLIER: f64 = 1.3;
+const FLASHLIGHT_MIN_ANGLE_MULTIPLIER: f64 = 0.2;
+
+/// With Hidden, objects fade out over this fraction of their preempt time.
+const HIDDEN_FADE_OUT_DURATION_MULTIPLIER: f64 = 0.3;
+
+/// The difficulty of a beatmap in osu!standard with a set of mods, split into its skills.
+///
+/// This follows osu!'s strain based difficulty calculation: every object puts strain on the player's aim,
+/// speed and memory (for Flashlight), which decays over time, and the hardest sections of the beatmap
+/// decide how difficult it is.
+#[derive(Debug, Clone, Copy)]
+pub struct StandardDifficulty {
+    stars: f64,
+    aim: f64,
+    speed: f64,
+    flashlight: f64,
+    slider_factor: f64,
+    speed_note_count: f64,
+    approach_rate: f32,
+    overall_difficulty: f32,
+    hp_drain_rate: f32,
+    max_combo: usize,
+    n_circles: usize,
+    n_sliders: usize,
+    n_spinners: usize,
+}
+
+impl StandardDifficulty {
+    /// Calculates the difficulty of the (unmodified) beatmap played with the given mods.
+    pub fn calculate(map: &Beatmap, mods: Mods) -> Self {
+        let effective = map.difficulty().with_mods(mods);
+        let map = map.apply_mods_in_song_time(mods);
+        let radius = effective.circle_radius();
+        let difficulty_objects = difficulty_objects(&map, &effective);
+
+        let mut aim = rating(aim_difficulty(&aim_peaks(&difficulty_objects, true)));
+        let aim_no_sliders = rating(aim_difficulty(&aim_peaks(&difficulty_objects, false)));
+        let (speed_peaks, speed_note_count) = speed_peaks(&difficulty_objects);
+        let mut speed = rating(speed_difficulty(&speed_peaks));
+        let flashlight_peaks =
+            flashlight_peaks(&difficulty_objects, radius, Opacity::new(&effective));
+        let mut flashlight = rating(flashlight_difficulty(&flashlight_peaks));
+        let slider_factor = if aim > 0.0 { aim_no_sliders / aim } else { 1.0 };
+
+        if mods.contains(Mods::TOUCH_DEVICE) {
+            aim = aim.powf(0.8);
+            flashlight = flashlight.powf(0.8);
+        }
+        if mods.contains(Mods::RELAX) {
+            aim *= 0.9;
+            speed = 0.0;
+            flashlight *= 0.7;
+        }
+
+        let base_flashlight = if mods.contains(Mods::FLASHLIGHT) {
+            flashlight.powi(2) * 25.0
+        } else {
+            0.0
+        };
+        let base_performance = (base_performance(aim).powf(1.1)
+            + base_performance(speed).powf(1.1)
+            + base_flashlight.powf(1.1))
+        .powf(1.0 / 1.1);
+        let stars = if base_performance > 0.00001 {
+            1.12f64.cbrt()
+                * 0.027
+                * ((100000.0 / 2f64.powf(1.0 / 1.1) * base_performance).cbrt() + 4.0)
+        } else {
+            0.0
+        };
+
+        let count =
+            |f: fn(&HitObject) -> bool| map.hit_objects().iter().filter(|obj| f(obj)).count();
+        let max_combo = map
+            .hit_objects()
+            .iter()
+            .map(|obj| match obj.object_data() {
+                HitObjectData::Slider(slider) => 1 + slider_checks(obj, slider).len(),
+                _ => 1,
+            })
+            .sum();
+
+        Self {
+            stars,
+            aim,
+            speed,
+            flashlight,
+            slider_factor,
+            speed_note_count,
+            approach_rate: effective.approach_rate(),
+            overall_difficulty: effective.overall_difficulty(),
+            hp_drain_rate: effective.hp_drain_rate(),
+            max_combo,
+            n_circles: count(HitObject::is_circle),
+            n_sliders: count(HitObject::is_slider),
+            n_spinners: count(HitObject::is_spinner),
+        }
+    }
+
+    /// The aim, speed and flashlight strains over the course of the (unmodified) beatmap played with the given mods.
+    pub fn strains(map: &Beatmap, mods: Mods) -> Vec<StrainSeries> {
+        let effective = map.difficulty().with_mods(mods);
+        let objects = difficulty_objects(&map.apply_mods_in_song_time(mods), &effective);
+        let first_time = objects.first().map(|obj| obj.start_time);
+        let series = |skill, peaks| {
+            StrainSeries::new(
+                skill,
+                peaks,
+                first_time,
+                SECTION_LENGTH,
+                effective.clock_rate(),
+            )
+        };
+
+        let opacity = Opacity::new(&effective);
+        vec![
+            series("aim", aim_peaks(&objects, true)),
+            series("speed", speed_peaks(&objects).0),
+            series(
+                "flashlight",
+                flashlight_peaks(&objects, effective.circle_radius(), opacity),
+            ),
+        ]
+    }
+
+    /// The overall star rating.
+    pub fn stars(&self) -> f64 {
+        self.stars
+    }
+
+    /// The star rating of moving the cursor between objects and along sliders.
+    pub fn aim(&self) -> f64 {
+        self.aim
+    }
+
+    /// The star rating of tapping quickly.
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// The star rating of memorising the beatmap, which only counts towards the stars with Flashlight.
+    pub fn flashlight(&self) -> f64 {
+        self.flashlight
+    }
+
+    /// The fraction of the aim difficulty which remains without following sliders.
+    pub fn slider_factor(&self) -> f64 {
+        self.slider_factor
+    }
+
+    /// The number of objects which are relevant for the speed difficulty, weighted by their strain.
+    pub fn speed_note_count(&self) -> f64 {
+        self.speed_note_count
+    }
+
+    /// The approach rate in real time, e.g. AR 9 with Double Time results in AR 10.33.
+    pub fn approach_rate(&self) -> f32 {
+        self.approach_rate
+    }
+
+    /// The overall difficulty in real time.
+    pub fn overall_difficulty(&self) -> f32 {
+        self.overall_difficulty
+    }
+
+    pub fn hp_drain_rate(&self) -> f32 {
+        self.hp_drain_rate
+    }
+
+    /// The combo of a full combo, counting every circle, slider head, tick, repeat and end and every spinner.
+    pub fn max_combo(&self) -> usize {
+        self.max_combo
+    }
+
+    pub fn n_circles(&self) -> usize {
+        self.n_circles
+    }
+
+    pub fn n_sliders(&self) -> usize {
+        self.n_sliders
+    }
+
+    pub fn n_spinners(&self) -> usize {
+        self.n_spinners
+    }
+}
+
+/// The information about a hit object the difficulty calculation needs. Times are in song time.
+#[derive(Debug, Clone, Copy)]
+struct OsuObject {
+    time: f64,
+    is_slider: bool,
+    is_spinner: bool,
+    position: (f64, f64),
+    end_position: (f64, f64),
+    /// Where the cursor is at the end of a slider when the player moves as little as possible
+    lazy_end_position: (f64, f64),
+    /// How far the cursor moves along a slider when the player moves as little as possible, normalised
+    lazy_travel_distance: f64,
+    /// The time until the slider end is checked
+    lazy_travel_time: f64,
+    repeat_count: usize,
+}
+
+impl OsuObject {
+    fn new(obj: &HitObject, radius: f32) -> Self {
+        let position = to_f64(obj.stacked_position(radius));
+        let mut object = Self {
+            time: obj.timestamp() as f64,
+            is_slider: obj.is_slider(),
+            is_spinner: obj.is_spinner(),
+            position,
+            end_position: to_f64(obj.stacked_end_position(radius)),
+            lazy_end_position: position,
+            lazy_travel_distance: 0.0,
+            lazy_travel_time: 0.0,
+            repeat_count: 0,
+        };
+        if let HitObjectData::Slider(slider) = obj.object_data() {
+            object.follow_slider_lazily(obj, slider, radius);
+        }
+        object
+    }
+
+    /// Moves the cursor along the slider only as far as needed to stay within the follow circle
+    /// for every tick, repeat and the end.
+    fn follow_slider_lazily(&mut self, obj: &HitObject, slider: &SliderData, radius: f32) {
+        let start = self.time;
+        let offset = to_f64(obj.stack_offset(radius));
+        let checks = slider_checks(obj, slider);
+
+        let tracking_end = checks
+            .iter()
+            .filter(|(_, kind)| *kind == SliderPart::Tick)
+            .fold(
+                (start + slider.duration() - SLIDER_END_LENIENCY)
+                    .max(start + slider.duration() / 2.0),
+                |end, &(time, _)| end.max(time),
+            );
+        self.lazy_travel_time = tracking_end - start;
+        self.repeat_count = slider.slides().max(1) - 1;
+
+        let mut end_progress = if slider.span_duration() > 0.0 {
+            self.lazy_travel_time / slider.span_duration()
+        } else {
+            0.0
+        };
+        end_progress = if end_progress % 2.0 >= 1.0 {
+            1.0 - end_progress % 1.0
+        } else {
+            end_progress % 1.0
+        };
+        self.lazy_end_position = add(to_f64(slider.path().position_at(end_progress)), offset);
+
+        let scaling_factor = NORMALISED_RADIUS / object_radius(radius);
+        let mut cursor = self.position;
+        let mut repeats = 0;
+        for (i, &(time, kind)) in checks.iter().enumerate() {
+            let is_last = i + 1 == checks.len();
+            let position = match kind {
+                SliderPart::Repeat => {
+                    repeats += 1;
+                    add(
+                        to_f64(slider.path().position_at((repeats % 2) as f64)),
+                        offset,
+                    )
+                }
+                SliderPart::End => self.end_position,
+                _ => add(to_f64(slider.position_at(time - start)), offset),
+            };
+
+            let mut movement = sub(position, cursor);
+            let mut movement_length = scaling_factor * length(movement);
+            let mut required_movement = ASSUMED_SLIDER_RADIUS;
+            if is_last {
+                let lazy_movement = sub(self.lazy_end_position, cursor);
+                if length(lazy_movement) < length(movement) {
+                    movement = lazy_movement;
+                }
+                movement_length = scaling_factor * length(movement);
+            } else if kind == SliderPart::Repeat {
+                required_movement = NORMALISED_RADIUS;
+            }
+
+            if movement_length > required_movement {
+                let scale = (movement_length - required_movement) / movement_length;
+                cursor = add(cursor, mul(movement, scale));
+                movement_length *= scale;
+                self.lazy_travel_distance += movement_length;
+            }
+            if is_last {
+                self.lazy_end_position = cursor;
+            }
+        }
+    }
+
+    /// Where the cursor is when the object ends.
+    fn end_cursor_position(&self) -> (f64, f64) {
+        self.lazy_end_position
+    }
+}
+
+/// An object together with how it is reached from the previous objects. Times are in real time.
+#[derive(Debug, Clone, Copy)]
+struct DifficultyObject {
+    object: OsuObject,
+    start_time: f64,
+    delta_time: f64,
+    strain_time: f64,
+    /// The normalised distance from the end of the previous object, assuming the cursor moved lazily along sliders
+    lazy_jump_distance: f64,
+    /// The shortest normalised distance the cursor has to move from the previous object, which
+    /// is less than the lazy jump distance after sliders since they can be left early
+    minimum_jump_distance: f64,
+    minimum_jump_time: f64,
+    travel_distance: f64,
+    travel_time: f64,
+    /// The angle between the movements to the previous object and to this one, in radians
+    angle: Option<f64>,
+    /// The width of the 300 hit window in milliseconds
+    hit_window_great: f64,
+}
+
+impl DifficultyObject {
+    fn new(
+        objects: &[OsuObject],
+        index: usize,
+        radius: f32,
+        clock_rate: f64,
+        hit_window_great: f64,
+    ) -> Self {
+        let object = objects[index];
+        let last = objects[index - 1];
+        let delta_time = (object.time - last.time) / clock_rate;
+        let strain_time = delta_time.max(MIN_DELTA_TIME);
+
+        let mut difficulty_object = Self {
+            object,
+            start_time: object.time / clock_rate,
+            delta_time,
+            strain_time,
+            lazy_jump_distance: 0.0,
+            minimum_jump_distance: 0.0,
+            minimum_jump_time: strain_time,
+            travel_distance: 0.0,
+            travel_time: 0.0,
+            angle: None,
+            hit_window_great,
+        };
+
+        if object.is_slider {
+            difficulty_object.travel_distance = object.lazy_travel_distance
+                * (1.0 + object.repeat_count as f64 / 2.5).powf(1.0 / 2.5);
+            difficulty_object.travel_time =
+                (object.lazy_travel_time / clock_rate).max(MIN_DELTA_TIME);
+        }
+        if object.is_spinner || last.is_spinner {
+            return difficulty_object;
+        }
+
+        let radius = object_radius(radius);
+        let mut scaling_factor = NORMALISED_RADIUS / radius;
+        if radius < SMALL_CIRCLE_RADIUS {
+            let small_circle_bonus = (SMALL_CIRCLE_RADIUS - radius).min(5.0) / 50.0;
+            scaling_factor *= 1.0 + small_circle_bonus;
+        }
+
+        let last_cursor_position = last.end_cursor_position();
+        difficulty_object.lazy_jump_distance =
+            length(sub(object.position, last_cursor_position)) * scaling_factor;
+        difficulty_object.minimum_jump_distance = difficulty_object.lazy_jump_distance;
+
+        if last.is_slider {
+            let last_travel_time = (last.lazy_travel_time / clock_rate).max(MIN_DELTA_TIME);
+            difficulty_object.minimum_jump_time =
+                (strain_time - last_travel_time).max(MIN_DELTA_TIME);
+
+            // The cursor can leave a slider anywhere within the follow circle, but also has to
+            // get there from where the slider was tracked lazily
+            let tail_jump_distance =
+                length(sub(last.end_position, object.position)) * scaling_factor;
+            difficulty_object.minimum_jump_distance = (difficulty_object.lazy_jump_distance
+                - (MAXIMUM_SLIDER_RADIUS - ASSUMED_SLIDER_RADIUS))
+                .min(tail_jump_distance - MAXIMUM_SLIDER_RADIUS)
+                .max(0.0);
+        }
+
+        if let Some(last_last) = index
+            .checked_sub(2)
+            .map(|i| objects[i])
+            .filter(|obj| !obj.is_spinner)
+        {
+            let v1 = sub(last_last.end_cursor_position(), last.position);
+            let v2 = sub(object.position, last_cursor_position);
+            let dot = v1.0 * v2.0 + v1.1 * v2.1;
+            let det = v1.0 * v2.1 - v1.1 * v2.0;
+            difficulty_object.angle = Some(det.atan2(dot).abs());
+        }
+
+        difficulty_object
+    }
+}
+
+/// How much an object fades in (and out with Hidden).
+#[derive(Debug, Clone, Copy)]
+struct Opacity {
+    preempt: f64,
+    fade_in: f64,
+    hidden: bool,
+}
+
+impl Opacity {
+    fn new(effective: &EffectiveDifficulty) -> Self {
+        Self {
+            preempt: effective.preempt(),
+            fade_in: effective.fade_in(),
+            hidden: effective.mods().contains(Mods::HIDDEN),
+        }
+    }
+
+    /// The opacity of the object at the given time, both in song time.
+    /// Objects are considered invisible once their start time has passed.
+    fn at(&self, object_time: f64, time: f64) -> f64 {
+        if time > object_time {
+            return 0.0;
+        }
+
+        let fade_in_start = object_time - self.preempt;
+        let fade_in = ((time - fade_in_start) / self.fade_in).clamp(0.0, 1.0);
+        if self.hidden {
+            let fade_out_start = fade_in_start + self.fade_in;
+            let fade_out_duration = self.preempt * HIDDEN_FADE_OUT_DURATION_MULTIPLIER;
+            fade_in.min(1.0 - ((time - fade_out_start) / fade_out_duration).clamp(0.0, 1.0))
+        } else {
+            fade_in
+        }
+    }
+}
+
+/// The difficulty objects of the beatmap, which has to have the mods applied in song time already.
+fn difficulty_objects(map: &Beatmap, effective: &EffectiveDifficulty) -> Vec<DifficultyObject> {
+    let clock_rate = effective.clock_rate();
+    let radius = effective.circle_radius();
+    let objects = map
+        .hit_objects()
+        .iter()
+        .map(|obj| OsuObject::new(obj, radius))
+        .collect::<Vec<_>>();
+    let hit_window_great = 2.0 * effective.hit_windows().window_300() / clock_rate;
+    (1..objects.len())
+        .map(|i| DifficultyObject::new(&objects, i, radius, clock_rate, hit_window_great))
+        .collect()
+}
+
+fn aim_peaks(objects: &[DifficultyObject], with_slider_travel_distance: bool) -> Vec<f64> {
+    decaying_strain_peaks(
+        objects.iter().enumerate().map(|(i, obj)| {
+            let value = evaluate_aim(objects, i, with_slider_travel_distance);
+            (obj.start_time, obj.delta_time, value)
+        }),
+        AIM_SKILL_MULTIPLIER,
+        AIM_STRAIN_DECAY_BASE,
+        SECTION_LENGTH,
+    )
+}
+
+fn aim_difficulty(peaks: &[f64]) -> f64 {
+    weighted_difficulty(peaks, 10, 0.75, 0.9) * AIM_DIFFICULTY_MULTIPLIER
+}
+
+/// The section peaks of the speed strain and the number of objects relevant to it.
+fn speed_peaks(objects: &[DifficultyObject]) -> (Vec<f64>, f64) {
+    let mut strain = 0.0;
+    let mut rhythm = 0.0;
+    let mut object_strains = vec![];
+    let mut peaks = StrainPeaks::default();
+    for (i, obj) in objects.iter().enumerate() {
+        let previous_start = obj.start_time - obj.delta_time;
+        peaks.advance_to(obj.start_time, |time| {
+            strain * rhythm * strain_decay(SPEED_STRAIN_DECAY_BASE, time - previous_start)
+        });
+        strain *= strain_decay(SPEED_STRAIN_DECAY_BASE, obj.strain_time);
+        strain += evaluate_speed(objects, i) * SPEED_SKILL_MULTIPLIER;
+        rhythm = evaluate_rhythm(objects, i);
+        object_strains.push(strain * rhythm);
+        peaks.add(strain * rhythm);
+    }
+
+    let max_strain = object_strains.iter().copied().fold(0.0, f64::max);
+    let note_count = if max_strain > 0.0 {
+        object_strains
+            .iter()
+            .map(|strain| 1.0 / (1.0 + (-(strain / max_strain * 12.0 - 6.0)).exp()))
+            .sum()
+    } else {
+        0.0
+    };
+    (peaks.into_peaks(), note_count)
+}
+
+fn speed_difficulty(peaks: &[f64]) -> f64 {
+    weighted_difficulty(peaks, SPEED_REDUCED_SECTION_COUNT, 0.75, 0.9) * SPEED_DIFFICULTY_MULTIPLIER
+}
+
+fn flashlight_peaks(objects: &[DifficultyObject], radius: f32, opacity: Opacity) -> Vec<f64> {
+    decaying_strain_peaks(
+        objects.iter().enumerate().map(|(i, obj)| {
+            let value = evaluate_flashlight(objects, i, radius, opacity);
+            (obj.start_time, obj.delta_time, value)
+        }),
+        FLASHLIGHT_SKILL_MULTIPLIER,
+        FLASHLIGHT_STRAIN_DECAY_BASE,
+        SECTION_LENGTH,
+    )
+}
+
+fn flashlight_difficulty(peaks: &[f64]) -> f64 {
+    peaks.iter().sum::<f64>() * AIM_DIFFICULTY_MULTIPLIER
+}
+
+/// How hard it is to move the cursor to the object, rewarding high velocities, angles which are hard
+/// to aim, changes in velocity and fast sliders.
+fn evaluate_aim(
+    objects: &[DifficultyObject],
+    index: usize,
+    with_slider_travel_distance: bool,
+) -> f64 {
+    if index <= 1 {
+        return 0.0;
+    }
+    let current = &objects[index];
+    let last = &objects[index - 1];
+    let last_last = &objects[index - 2];
+    if current.object.is_spinner || last.object.is_spinner {
+        return 0.0;
+    }
+
+    // The velocity through a slider and on to the next object counts if it is faster than jumping there directly
+    let velocity = |obj: &DifficultyObject, previous: &DifficultyObject| {
+        let mut velocity = obj.lazy_jump_distance / obj.strain_time;
+        if previous.object.is_slider && with_slider_travel_distance {
+            let travel_velocity = previous.travel_distance / previous.travel_time;
+            let movement_velocity = obj.minimum_jump_distance / obj.minimum_jump_time;
+            velocity = velocity.max(movement_velocity + travel_velocity);
+        }
+        velocity
+    };
+    let mut current_velocity = velocity(current, last);
+    let mut previous_velocity = velocity(last, last_last);
+
+    let mut wide_angle_bonus = 0.0;
+    let mut acute_angle_bonus = 0.0;
+    let mut velocity_change_bonus = 0.0;
+    let mut aim_strain = current_velocity;
+
+    let (fast, slow) = min_max(current.strain_time, last.strain_time);
+    // Angles only matter if the rhythm stays the same
+    if slow < 1.25 * fast {
+        if let (Some(angle), Some(last_angle), Some(last_last_angle)) =
+            (current.angle, last.angle, last_last.angle)
+        {
+            let angle_bonus = current_velocity.min(previous_velocity);
+            wide_angle_bonus = wide_angle_bonus_of(angle);
+            acute_angle_bonus = acute_angle_bonus_of(angle);
+
+            // Only buff acute angles faster than 300 BPM 1/2, scaling up from 150 BPM 1/4 to 200 BPM 1/4,
+            // when the previous angle was acute as well and for distances from the radius up to the diameter
+            if current.strain_time > 100.0 {
+                acute_angle_bonus = 0.0;
+            } else {
+                acute_angle_bonus *= acute_angle_bonus_of(last_angle)
+                    * angle_bonus.min(125.0 / current.strain_time)
+                    * (PI / 2.0 * ((100.0 - current.strain_time) / 25.0).min(1.0))
+                        .sin()
+                        .powi(2)
+                    * (PI / 2.0 * (current.lazy_jump_distance.clamp(50.0, 100.0) - 50.0) / 50.0)
+                        .sin()
+                        .powi(2);
+            }
+
+            // Repeated angles are easier
+            wide_angle_bonus *=
+                angle_bonus * (1.0 - wide_angle_bonus.min(wide_angle_bonus_of(last_angle).powi(3)));
+            acute_angle_bonus *= 0.5
+                + 0.5
+                    * (1.0 - acute_angle_bonus.min(acute_angle_bonus_of(last_last_angle).powi(3)));
+        }
+    }
+
+    if previous_velocity.max(current_velocity) != 0.0 {
+        // Use the average velocity over the whole object for the change in velocity
+        previous_velocity =
+            (last.lazy_jump_distance + last_last.travel_distance) / last.strain_time;
+        current_velocity =
+            (current.lazy_jump_distance + last.travel_distance) / current.strain_time;
+
+        let velocity_difference = (previous_velocity - current_velocity).abs();
+        let distance_ratio = (PI / 2.0 * velocity_difference
+            / previous_velocity.max(current_velocity))
+        .sin()
+        .powi(2);
+        // Overlapping objects still count up to a velocity of 125 / strain time
+        let overlap_velocity_buff = (125.0 / fast).min(velocity_difference);
+        // Rhythm changes are penalised
+        velocity_change_bonus = overlap_velocity_buff * distance_ratio * (fast / slow).powi(2);
+    }
+
+    let slider_bonus = if last.object.is_slider {
+        last.travel_distance / last.travel_time
+    } else {
+        0.0
+    };
+
+    aim_strain += (acute_angle_bonus * ACUTE_ANGLE_MULTIPLIER).max(
+        wide_angle_bonus * WIDE_ANGLE_MULTIPLIER
+            + velocity_change_bonus * VELOCITY_CHANGE_MULTIPLIER,
+    );
+    if with_slider_travel_distance {
+        aim_strain += slider_bonus * SLIDER_MULTIPLIER;
+    }
+    aim_strain
+}
+
+/// How hard it is to tap the object in time, rewarding short gaps between objects and spaced streams,
+/// while penalising objects which can be double tapped.
+fn evaluate_speed(objects: &[DifficultyObject], index: usize) -> f64 {
+    let current = &objects[index];
+    if current.object.is_spinner {
+        return 0.0;
+    }
+
+    let mut strain_time = current.strain_time;
+    let mut doubletapness = 1.0;
+    if let Some(next) = objects.get(index + 1) {
+        let current_delta_time = current.delta_time.max(1.0);
+        let next_delta_time = next.delta_time.max(1.0);
+        let delta_difference = (next_delta_time - current_delta_time).abs();
+        let speed_ratio = current_delta_time / current_delta_time.max(delta_difference);
+        let window_ratio = (current_delta_time / current.hit_window_great)
+            .min(1.0)
+            .powi(2);
+        doubletapness = speed_ratio.powf(1.0 - window_ratio);
+    }
+
+    // Objects within the 300 hit window of each other are a bit easier to tap
+    strain_time /= (strain_time / current.hit_window_great / 0.93).clamp(0.92, 1.0);
+
+    let speed_bonus = if strain_time < MIN_SPEED_BONUS {
+        1.0 + 0.75 * ((MIN_SPEED_BONUS - strain_time) / SPEED_BALANCING_FACTOR).powi(2)
+    } else {
+        1.0
+    };
+
+    let travel_distance = index
+        .checked_sub(1)
+        .map_or(0.0, |i| objects[i].travel_distance);
+    let distance = (travel_distance + current.minimum_jump_distance).min(SINGLE_SPACING_THRESHOLD);
+
+    (speed_bonus + speed_bonus * (distance / SINGLE_SPACING_THRESHOLD).powf(3.5)) * doubletapness
+        / strain_time
+}
+
+/// How complex the rhythm leading up to the object is, as a multiplier of the speed strain.
+/// Rhythm changes count more the more they vary and the longer the groups of evenly spaced objects between them are.
+fn evaluate_rhythm(objects: &[DifficultyObject], index: usize) -> f64 {
+    let current = &objects[index];
+    if current.object.is_spinner {
+        return 0.0;
+    }
+    let previous = |i: usize| &objects[index - 1 - i];
+
+    let mut previous_island_size = 0;
+    let mut rhythm_complexity_sum = 0.0;
+    let mut island_size = 1;
+    let mut start_ratio = 0.0;
+    let mut first_delta_switch = false;
+
+    let historical_note_count = index.min(RHYTHM_HISTORY_OBJECTS_MAX);
+    let mut rhythm_start = 0;
+    while rhythm_start + 2 < historical_note_count
+        && current.start_time - previous(rhythm_start).start_time < RHYTHM_HISTORY_TIME_MAX
+    {
+        rhythm_start += 1;
+    }
+
+    for i in (1..=rhythm_start).rev() {
+        let current_object = previous(i - 1);
+        let previous_object = previous(i);
+        let last_object = previous(i + 1);
+
+        let historical_decay = ((RHYTHM_HISTORY_TIME_MAX
+            - (current.start_time - current_object.start_time))
+            / RHYTHM_HISTORY_TIME_MAX)
+            .min((historical_note_count - i) as f64 / historical_note_count as f64);
+
+        let current_delta = current_object.strain_time;
+        let previous_delta = previous_object.strain_time;
+        let last_delta = last_object.strain_time;
+        let (shorter, longer) = min_max(previous_delta, current_delta);
+        let current_ratio = 1.0 + 6.0 * (PI / (shorter / longer)).sin().powi(2).min(0.5);
+
+        let window = current_object.hit_window_great * 0.3;
+        let window_penalty =
+            (((previous_delta - current_delta).abs() - window).max(0.0) / window).min(1.0);
+        let mut effective_ratio = window_penalty * current_ratio;
+
+        if first_delta_switch {
+            if !(previous_delta > 1.25 * current_delta || previous_delta * 1.25 < current_delta) {
+                // The rhythm stays the same, so the island of evenly spaced objects grows
+                if island_size < 7 {
+                    island_size += 1;
+                }
+            } else {
+                if current_object.object.is_slider {
+                    effective_ratio *= 0.125;
+                }
+                if previous_object.object.is_slider {
+                    effective_ratio *= 0.25;
+                }
+                if previous_island_size == island_size {
+                    effective_ratio *= 0.25;
+                }
+                if previous_island_size % 2 == island_size % 2 {
+                    effective_ratio *= 0.5;
+                }
+                if last_delta > previous_delta + 10.0 && previous_delta > current_delta + 10.0 {
+                    // Speeding up twice in a row is a common pattern
+                    effective_ratio *= 0.125;
+                }
+
+                rhythm_complexity_sum += (effective_ratio * start_ratio).sqrt()
+                    * historical_decay
+                    * (4.0 + island_size as f64).sqrt()
+                    / 2.0
+                    * (4.0 + previous_island_size as f64).sqrt()
+                    / 2.0;
+
+                start_ratio = effective_ratio;
+                previous_island_size = island_size;
+                if previous_delta * 1.25 < current_delta {
+                    // Slowing down ends the series of rhythm changes
+                    first_delta_switch = false;
+                }
+                island_size = 1;
+            }
+        } else if previous_delta > 1.25 * current_delta {
+            // Speeding up starts a series of rhythm changes
+            first_delta_switch = true;
+            start_ratio = effective_ratio;
+            island_size = 1;
+        }
+    }
+
+    (4.0 + rhythm_complexity_sum * RHYTHM_MULTIPLIER).sqrt() / 2.0
+}
+
+/// How hard it is to find the object with Flashlight, rewarding long distances to the previous objects,
+/// especially ones which were barely visible, and fast sliders.
+fn evaluate_flashlight(
+    objects: &[DifficultyObject],
+    index: usize,
+    radius: f32,
+    opacity: Opacity,
+) -> f64 {
+    let current = &objects[index];
+    if current.object.is_spinner {
+        return 0.0;
+    }
+
+    let scaling_factor = 52.0 / object_radius(radius);
+    let mut small_distance_nerf = 1.0;
+    let mut cumulative_strain_time = 0.0;
+    let mut result = 0.0;
+    let mut last = current;
+    let mut angle_repeat_count = 0.0;
+
+    for i in 0..index.min(FLASHLIGHT_HISTORY_OBJECTS_MAX) {
+        let previous = &objects[index - 1 - i];
+        if !previous.object.is_spinner {
+            let jump_distance = length(sub(current.object.position, previous.object.end_position));
+            cumulative_strain_time += last.strain_time;
+
+            // Objects close to the current one are easy to find
+            if i == 0 {
+                small_distance_nerf = (jump_distance / 75.0).min(1.0);
+            }
+            // Stacked objects are only hard to find once
+            let stack_nerf = ((previous.lazy_jump_distance / scaling_factor) / 25.0).min(1.0);
+            let opacity_bonus = 1.0
+                + FLASHLIGHT_MAX_OPACITY_BONUS
+                    * (1.0 - opacity.at(current.object.time, previous.object.time));
+
+            result += stack_nerf * opacity_bonus * scaling_factor * jump_distance
+                / cumulative_strain_time;
+
+            if let (Some(previous_angle), Some(angle)) = (previous.angle, current.angle) {
+                if (previous_angle - angle).abs() < 0.02 {
+                    angle_repeat_count += (1.0 - 0.1 * i as f64).max(0.0);
+                }
+            }
+        }
+        last = previous;
+    }
+
+    result = (small_distance_nerf * result).powi(2);
+    if opacity.hidden {
+        result *= 1.0 + FLASHLIGHT_HIDDEN_BONUS;
+    }
+    // Patterns of repeated angles are easier to memorise
+    result *= FLASHLIGHT_MIN_ANGLE_MULTIPLIER
+        + (1.0 - FLASHLIGHT_MIN_ANGLE_MULTIPLIER) / (angle_repeat_count + 1.0);
+
+    if current.object.is_slider {
+        let pixel_travel_distance = current.object.lazy_travel_distance / scaling_factor;
+        let mut slider_bonus = (pixel_travel_distance / current.travel_time
+            - FLASHLIGHT_MIN_SLIDER_VELOCITY)
+            .max(0.0)
+            .sqrt()
+            * pixel_travel_distance;
+        slider_bonus /= (current.object.repeat_count + 1) as f64;
+        result += slider_bonus * FLASHLIGHT_SLIDER_MULTIPLIER;
+    }
+
+    result
+}
+
+/// Angles between 30° and 150° get a bonus for being wide, up to the maximum at 150°.
+fn wide_angle_bonus_of(angle: f64) -> f64 {
+    (3.0 / 4.0 * (angle.clamp(PI / 6.0, 5.0 / 6.0 * PI) - PI / 6.0))
+        .sin()
+        .powi(2)
+}
+
+fn acute_angle_bonus_of(angle: f64) -> f64 {
+    1.0 - wide_angle_bonus_of(angle)
+}
+
+/// Converts the difficulty of a skill to its star rating.
+fn rating(difficulty: f64) -> f64 {
+    difficulty.sqrt() * DIFFICULTY_MULTIPLIER
+}
+
+/// The performance points a skill with the given star rating is worth on its own.
+fn base_performance(rating: f64) -> f64 {
+    (5.0 * (rating / DIFFICULTY_MULTIPLIER).max(1.0) - 4.0).powi(3) / 100000.0
+}
+
+/// The radius of circles as osu! calculates it, which is slightly larger than the one given by the circle size.
+fn object_radius(radius: f32) -> f64 {
+    radius as f64 * RADIUS_ROUNDING_ALLOWANCE
+}
+
+fn min_max(a: f64, b: f64) -> (f64, f64) {
+    (a.min(b), a.max(b))
+}
+
+fn to_f64((x, y): (f32, f32)) -> (f64, f64) {
+    (x as f64, y as f64)
+}
+
+fn add(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+fn sub(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn mul(a: (f64, f64), factor: f64) -> (f64, f64) {
+    (a.0 * factor, a.1 * factor)
+}
+
+fn length(a: (f64, f64)) -> f64 {
+    a.0.hypot(a.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{autoplay::Autoplay, osu_data::GameMode, test_data};
+
+    use super::*;
+
+    #[test]
+    fn difficulty_of_tiferet() {
+        // Regression values of this implementation, which follows osu!'s calculation
+        let difficulty =
+            StandardDifficulty::calculate(&test_data::map_named("Another"), Mods::empty());
+        assert!((difficulty.stars() - 6.05).abs() < 0.01, "{difficulty:?}");
+        assert!((difficulty.aim() - 3.06).abs() < 0.01, "{difficulty:?}");
+        assert!((difficulty.speed() - 2.75).abs() < 0.01, "{difficulty:?}");
+        assert_eq!(
+            (
+                difficulty.n_circles(),
+                difficulty.n_sliders(),
+                difficulty.n_spinners()
+            ),
+            (450, 376, 3)
+        );
+        assert_eq!(difficulty.approach_rate(), 9.3);
+        assert_eq!(difficulty.overall_difficulty(), 8.0);
+    }
+
+    #[test]
+    fn mods_change_difficulty() {
+        let map = test_data::map_named("Another");
+        let stars = |mods| StandardDifficulty::calculate(&map, mods).stars();
+        let nomod = stars(Mods::empty());
+        assert!(stars(Mods::DOUBLE_TIME) > nomod);
+        assert!(stars(Mods::HALF_TIME) < nomod);
+        assert!(stars(Mods::HARD_ROCK) > nomod);
+        assert!(stars(Mods::EASY) < nomod);
+        assert!(stars(Mods::FLASHLIGHT) > nomod);
+        assert!(stars(Mods::RELAX) < nomod);
+        // Hidden only affects flashlight
+        assert_eq!(stars(Mods::HIDDEN), nomod);
+
+        // Approach rate and overall difficulty include the change in speed
+        let double_time = StandardDifficulty::calculate(&map, Mods::DOUBLE_TIME);
+        assert!(double_time.approach_rate() > 10.0);
+        assert!(double_time.overall_difficulty() > 9.0);
+    }
+
+    #[test]
+    fn harder_difficulties_have_more_stars() {
+        let stars = ["Normal", "Advanced", "Insane", "Another"].map(|version| {
+            StandardDifficulty::calculate(&test_data::map_named(version), Mods::empty()).stars()
+        });
+        assert!(stars.windows(2).all(|w| w[0] < w[1]), "{stars:?}");
+    }
+
+    #[test]
+    fn max_combo_matches_autoplay() {
+        let map = test_data::map_named("Another");
+        for mods in [Mods::empty(), Mods::DOUBLE_TIME] {
+            let replay = Autoplay::new(GameMode::Standard, mods).generate(&map);
+            let difficulty = StandardDifficulty::calculate(&map, mods);
+            assert_eq!(difficulty.max_combo(), replay.max_combo as usize);
+        }
+    }
+}
@@ -0,0 +1,442 @@
+use std::f64::consts::PI;
+
+use crate::{
+    map::{
+        difficulty::difficulty_range,
+        taiko::{TaikoHitKind, TaikoObject},
+        Beatmap,
+    },
+    osu_data::GameMode,
+    replay::Mods,
+};
+
+use super::{decaying_strain_peaks, weighted_difficulty, StrainSeries, SECTION_LENGTH};
+
+const DIFFICULTY_MULTIPLIER: f64 = 1.35;
+const FINAL_MULTIPLIER: f64 = 0.0625;
+const RHYTHM_SKILL_MULTIPLIER: f64 = 0.2 * FINAL_MULTIPLIER;
+const COLOUR_SKILL_MULTIPLIER: f64 = 0.375 * FINAL_MULTIPLIER;
+const STAMINA_SKILL_MULTIPLIER: f64 = 0.375 * FINAL_MULTIPLIER;
+
+const COLOUR_STRAIN_MULTIPLIER: f64 = 0.12;
+const COLOUR_STRAIN_DECAY_BASE: f64 = 0.8;
+const RHYTHM_STRAIN_MULTIPLIER: f64 = 10.0;
+const RHYTHM_STRAIN_DECAY: f64 = 0.96;
+/// How many of the previous rhythm changes are looked at to find repeated patterns.
+const RHYTHM_HISTORY_MAX_LENGTH: usize = 8;
+const STAMINA_STRAIN_MULTIPLIER: f64 = 1.1;
+const STAMINA_STRAIN_DECAY_BASE: f64 = 0.4;
+/// Colour patterns which repeat after more than this many patterns are not considered repetitions.
+const MAX_REPETITION_INTERVAL: usize = 16;
+
+/// The ratios between the gaps before two consecutive notes which are common in beatmaps,
+/// together with the difficulty of the rhythm change.
+const COMMON_RHYTHMS: [(f64, f64); 9] = [
+    (1.0, 0.0),
+    (2.0, 0.3),
+    (0.5, 0.5),
+    (3.0, 0.3),
+    (1.0 / 3.0, 0.35),
+    (1.5, 0.6),
+    (2.0 / 3.0, 0.4),
+    (1.25, 0.5),
+    (0.8, 0.7),
+];
+
+/// The difficulty of a beatmap in osu!taiko with a set of mods.
+///
+/// The strain of reading the colours of notes, of following changes in rhythm and of hitting
+/// notes quickly with the same hand are combined into the star rating.
+#[derive(Debug, Clone, Copy)]
+pub struct TaikoDifficulty {
+    stars: f64,
+    stamina: f64,
+    rhythm: f64,
+    colour: f64,
+    great_hit_window: f64,
+    max_combo: usize,
+}
+
+impl TaikoDifficulty {
+    /// Calculates the difficulty of the (unmodified) beatmap played with the given mods.
+    pub fn calculate(map: &Beatmap, mods: Mods) -> Self {
+        let effective = map.difficulty().with_mods(mods);
+        let clock_rate = effective.clock_rate();
+        let objects = map.taiko_objects();
+        let difficulty_objects = difficulty_objects(&objects, clock_rate);
+        let [colour_peaks, rhythm_peaks, stamina_peaks] = skill_peaks(&difficulty_objects);
+
+        let skill_rating = |peaks: &[f64], multiplier: f64| {
+            weighted_difficulty(peaks, 0, 1.0, 0.9) * multiplier * DIFFICULTY_MULTIPLIER
+        };
+        let colour = skill_rating(&colour_peaks, COLOUR_SKILL_MULTIPLIER);
+        let rhythm = skill_rating(&rhythm_peaks, RHYTHM_SKILL_MULTIPLIER);
+        let stamina = skill_rating(&stamina_peaks, STAMINA_SKILL_MULTIPLIER);
+
+        // Every section is rated by combining the skills, where stamina and colour go together more closely
+        let combined_peaks = colour_peaks
+            .iter()
+            .zip(&rhythm_peaks)
+            .zip(&stamina_peaks)
+            .map(|((colour, rhythm), stamina)| {
+                let peak = norm(
+                    1.5,
+                    colour * COLOUR_SKILL_MULTIPLIER,
+                    stamina * STAMINA_SKILL_MULTIPLIER,
+                );
+                norm(2.0, peak, rhythm * RHYTHM_SKILL_MULTIPLIER)
+            })
+            .collect::<Vec<_>>();
+        let combined = weighted_difficulty(&combined_peaks, 0, 1.0, 0.9) * DIFFICULTY_MULTIPLIER;
+
+        let mut stars = rescale(combined * 1.4);
+        // Converted beatmaps can often be played with unintended techniques
+        if !matches!(map.general().mode(), GameMode::Taiko) {
+            stars *= 0.925;
+            if colour < 2.0 && stamina > 8.0 {
+                stars *= 0.8;
+            }
+        }
+
+        let overall_difficulty = effective.difficulty().overall_difficulty() as f64;
+        Self {
+            stars,
+            stamina,
+            rhythm,
+            colour,
+            great_hit_window: difficulty_range(overall_difficulty, 50.0, 35.0, 20.0) / clock_rate,
+            max_combo: objects
+                .iter()
+                .filter(|obj| matches!(obj, TaikoObject::Hit { .. }))
+                .count(),
+        }
+    }
+
+    /// The colour, rhythm and stamina strains over the course of the (unmodified) beatmap played with the given mods.
+    pub fn strains(map: &Beatmap, mods: Mods) -> Vec<StrainSeries> {
+        let clock_rate = map.difficulty().with_mods(mods).clock_rate();
+        let objects = difficulty_objects(&map.taiko_objects(), clock_rate);
+        let first_time = objects.first().map(|obj| obj.start_time);
+        ["colour", "rhythm", "stamina"]
+            .into_iter()
+            .zip(skill_peaks(&objects))
+            .map(|(skill, peaks)| {
+                StrainSeries::new(skill, peaks, first_time, SECTION_LENGTH, clock_rate)
+            })
+            .collect()
+    }
+
+    pub fn stars(&self) -> f64 {
+        self.stars
+    }
+
+    /// The difficulty of hitting notes quickly with the same hand.
+    pub fn stamina(&self) -> f64 {
+        self.stamina
+    }
+
+    /// The difficulty of following changes in rhythm.
+    pub fn rhythm(&self) -> f64 {
+        self.rhythm
+    }
+
+    /// The difficulty of reading changes between dons and kats.
+    pub fn colour(&self) -> f64 {
+        self.colour
+    }
+
+    /// The maximum hit error in milliseconds (in real time) for a great.
+    pub fn great_hit_window(&self) -> f64 {
+        self.great_hit_window
+    }
+
+    /// The combo of a full combo, which is the number of dons and kats.
+    pub fn max_combo(&self) -> usize {
+        self.max_combo
+    }
+}
+
+/// An object together with how it relates to the previous objects. Times are in real time.
+#[derive(Debug, Clone, Copy)]
+struct DifficultyObject {
+    start_time: f64,
+    delta_time: f64,
+    /// The colour of a note, `None` for drum rolls and swells
+    kind: Option<TaikoHitKind>,
+    /// The index of the closest ratio in [`COMMON_RHYTHMS`]
+    rhythm: usize,
+    /// The difficulty of the colour patterns starting at this note
+    colour: f64,
+    /// The start time of the previous note hit by the same key, which is two notes of the same colour prior
+    previous_same_key: Option<f64>,
+}
+
+/// Creates the difficulty objects for every object but the first two, which have no rhythm to compare to.
+fn difficulty_objects(objects: &[TaikoObject], clock_rate: f64) -> Vec<DifficultyObject> {
+    let mut dons = vec![];
+    let mut kats = vec![];
+    let mut difficulty_objects = (2..objects.len())
+        .map(|i| {
+            let start = objects[i].start_time();
+            let last = objects[i - 1].start_time();
+            let last_last = objects[i - 2].start_time();
+            let delta_time = (start - last) / clock_rate;
+            let previous_delta_time = (last - last_last) / clock_rate;
+            let ratio = delta_time / previous_delta_time;
+            let rhythm = (0..COMMON_RHYTHMS.len())
+                .min_by(|&a, &b| {
+                    (ratio - COMMON_RHYTHMS[a].0)
+                        .abs()
+                        .total_cmp(&(ratio - COMMON_RHYTHMS[b].0).abs())
+                })
+                .unwrap_or(0);
+
+            let start_time = start / clock_rate;
+            let kind = match objects[i] {
+                TaikoObject::Hit { kind, .. } => Some(kind),
+                _ => None,
+            };
+            let same_colour = match kind {
+                Some(TaikoHitKind::Don) => Some(&mut dons),
+                Some(TaikoHitKind::Kat) => Some(&mut kats),
+                None => None,
+            };
+            let previous_same_key = same_colour.and_then(|same_colour| {
+                let previous = same_colour.len().checked_sub(2).map(|i| same_colour[i]);
+                same_colour.push(start_time);
+                previous
+            });
+
+            DifficultyObject {
+                start_time,
+                delta_time,
+                kind,
+                rhythm,
+                colour: 0.0,
+                previous_same_key,
+            }
+        })
+        .collect::<Vec<_>>();
+    assign_colour_difficulty(&mut difficulty_objects);
+    difficulty_objects
+}
+
+/// Consecutive notes of the same colour.
+#[derive(Debug, Clone, Copy)]
+struct MonoStreak {
+    first: usize,
+    kind: TaikoHitKind,
+    run_length: usize,
+}
+
+/// Groups the notes into streaks of the same colour, streaks of the same length into alternating patterns
+/// and patterns which repeat shortly after each other into repeating patterns. The first note of each
+/// group is given the difficulty of the group, which is higher for groups which are not repeated.
+fn assign_colour_difficulty(objects: &mut [DifficultyObject]) {
+    let mut streaks: Vec<MonoStreak> = vec![];
+    for (i, obj) in objects.iter().enumerate() {
+        let Some(kind) = obj.kind else {
+            continue;
+        };
+        match streaks.last_mut() {
+            Some(streak) if streak.kind == kind => streak.run_length += 1,
+            _ => streaks.push(MonoStreak {
+                first: i,
+                kind,
+                run_length: 1,
+            }),
+        }
+    }
+
+    // Alternating patterns as slices of the streaks
+    let mut patterns: Vec<&[MonoStreak]> = vec![];
+    let mut pattern_start = 0;
+    for i in 1..=streaks.len() {
+        if i == streaks.len() || streaks[i].run_length != streaks[i - 1].run_length {
+            patterns.push(&streaks[pattern_start..i]);
+            pattern_start = i;
+        }
+    }
+    let is_repetition = |a: &[MonoStreak], b: &[MonoStreak]| {
+        a[0].run_length == b[0].run_length && a.len() == b.len() && a[0].kind == b[0].kind
+    };
+
+    // Repeating patterns as slices of the alternating patterns
+    let mut repeating: Vec<&[&[MonoStreak]]> = vec![];
+    let mut i = 0;
+    while i < patterns.len() {
+        let start = i;
+        let is_coupled =
+            |i: usize| i + 2 < patterns.len() && is_repetition(patterns[i], patterns[i + 2]);
+        if is_coupled(i) {
+            while is_coupled(i) {
+                i += 1;
+            }
+            i += 1;
+        }
+        let end = (i + 1).min(patterns.len());
+        repeating.push(&patterns[start..end]);
+        i = end;
+    }
+
+    let sigmoid = |value: f64| (PI * (value - 2.0) / 2.0).tanh();
+    for (index, group) in repeating.iter().enumerate() {
+        let same_lengths = |other: &[&[MonoStreak]]| {
+            group.len() == other.len()
+                && group
+                    .iter()
+                    .zip(other)
+                    .take(2)
+                    .all(|(a, b)| a[0].run_length == b[0].run_length)
+        };
+        let repetition_interval = (1..MAX_REPETITION_INTERVAL)
+            .take_while(|&interval| interval <= index)
+            .find(|&interval| same_lengths(repeating[index - interval]))
+            .unwrap_or(MAX_REPETITION_INTERVAL + 1);
+
+        let group_difficulty = 2.0 * (1.0 - sigmoid(repetition_interval as f64) * 0.5 + 0.5);
+        objects[group[0][0].first].colour += group_difficulty;
+        for (pattern_index, pattern) in group.iter().enumerate() {
+            let pattern_difficulty = (sigmoid(pattern_index as f64) * 0.5 + 0.5) * group_difficulty;
+            objects[pattern[0].first].colour += pattern_difficulty;
+            for (streak_index, streak) in pattern.iter().enumerate() {
+                objects[streak.first].colour +=
+                    (sigmoid(streak_index as f64) * 0.5 + 0.5) * pattern_difficulty * 0.5;
+            }
+        }
+    }
+}
+
+/// The rhythm strain of every object, which only increases when the rhythm changes.
+/// Changes are worth less if they repeat a recent pattern, follow too shortly or too long after the
+/// previous change or happen at slow speeds.
+/// The section peaks of the colour, rhythm and stamina strains.
+fn skill_peaks(objects: &[DifficultyObject]) -> [Vec<f64>; 3] {
+    let colour = decaying_strain_peaks(
+        objects
+            .iter()
+            .map(|obj| (obj.start_time, obj.delta_time, obj.colour)),
+        COLOUR_STRAIN_MULTIPLIER,
+        COLOUR_STRAIN_DECAY_BASE,
+        SECTION_LENGTH,
+    );
+    let rhythm = decaying_strain_peaks(
+        rhythm_strains(objects),
+        RHYTHM_STRAIN_MULTIPLIER,
+        0.0,
+        SECTION_LENGTH,
+    );
+    let stamina = decaying_strain_peaks(
+        objects
+            .iter()
+            .map(|obj| (obj.start_time, obj.delta_time, evaluate_stamina(obj))),
+        STAMINA_STRAIN_MULTIPLIER,
+        STAMINA_STRAIN_DECAY_BASE,
+        SECTION_LENGTH,
+    );
+    [colour, rhythm, stamina]
+}
+
+fn rhythm_strains(objects: &[DifficultyObject]) -> Vec<(f64, f64, f64)> {
+    let mut history: Vec<(usize, usize)> = vec![];
+    let mut strain = 0.0;
+    let mut notes_since_rhythm_change = 0;
+
+    objects
+        .iter()
+        .enumerate()
+        .map(|(index, obj)| {
+            let value = if obj.kind.is_none() {
+                strain = 0.0;
+                notes_since_rhythm_change = 0;
+                0.0
+            } else {
+                strain *= RHYTHM_STRAIN_DECAY;
+                notes_since_rhythm_change += 1;
+                let difficulty = COMMON_RHYTHMS[obj.rhythm].1;
+                if difficulty == 0.0 {
+                    0.0
+                } else {
+                    history.push((index, obj.rhythm));
+                    if history.len() > RHYTHM_HISTORY_MAX_LENGTH {
+                        history.remove(0);
+                    }
+                    let object_strain = difficulty
+                        * repetition_penalty(&history, index)
+                        * pattern_length_penalty(notes_since_rhythm_change)
+                        * speed_penalty(obj.delta_time);
+                    notes_since_rhythm_change = 0;
+                    if object_strain == 0.0 && obj.delta_time >= 210.0 {
+                        // Slow notes reset the rhythm entirely
+                        strain = 0.0;
+                    }
+                    strain += object_strain;
+                    strain
+                }
+            };
+            (obj.start_time, obj.delta_time, value)
+        })
+        .collect()
+}
+
+/// Penalises rhythm changes which repeat a pattern of the last few changes.
+fn repetition_penalty(history: &[(usize, usize)], index: usize) -> f64 {
+    let mut penalty = 1.0;
+    for pattern_length in 2..=RHYTHM_HISTORY_MAX_LENGTH / 2 {
+        let Some(latest_start) = history.len().checked_sub(pattern_length) else {
+            continue;
+        };
+        let latest = &history[latest_start..];
+        let repeated = (0..latest_start).rev().find(|&start| {
+            history[start..start + pattern_length]
+                .iter()
+                .zip(latest)
+                .all(|(a, b)| a.1 == b.1)
+        });
+        if let Some(start) = repeated {
+            let notes_since = index - history[start].0;
+            penalty *= (0.032 * notes_since as f64).min(1.0);
+        }
+    }
+    penalty
+}
+
+/// Penalises rhythm changes which follow very shortly or very long after the previous one.
+fn pattern_length_penalty(pattern_length: usize) -> f64 {
+    let short_pattern_penalty = (0.15 * pattern_length as f64).min(1.0);
+    let long_pattern_penalty = (2.5 - 0.15 * pattern_length as f64).clamp(0.0, 1.0);
+    short_pattern_penalty.min(long_pattern_penalty)
+}
+
+/// Penalises rhythm changes at slow speeds, where rhythm changes slower than 210 milliseconds don't count at all.
+fn speed_penalty(note_length: f64) -> f64 {
+    if note_length < 80.0 {
+        1.0
+    } else if note_length < 210.0 {
+        (1.4 - 0.005 * note_length).max(0.0)
+    } else {
+        0.0
+    }
+}
+
+/// How hard it is to hit the note shortly after the previous note hit with the same key.
+fn evaluate_stamina(obj: &DifficultyObject) -> f64 {
+    match obj.previous_same_key {
+        // Cap the speed at 600 BPM 1/4 (a key is hit every 50 milliseconds)
+        Some(previous) => 0.5 + 30.0 / (obj.start_time - previous).max(50.0),
+        None => 0.0,
+    }
+}
+
+/// Scales the combined rating to stars, flattening very high ratings.
+fn rescale(stars: f64) -> f64 {
+    if stars < 0.0 {
+        stars
+    } else {
+        10.43 * (stars / 8.0 + 1.0).ln()
+    }
+}
+
+fn norm(p: f64, a: f64, b: f64) -> f64 {
+    (a.powf(p) + b.powf(p)).powf(1.0 / p)
+}
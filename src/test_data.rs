@@ -0,0 +1,219 @@
+//! Access to the sample beatmaps and replays in `res` for tests.
+
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{
+    map::Beatmap,
+    osu_data::GameMode,
+    replay::{InputKeys, Mods, Replay, ReplayFrame},
+};
+
+const MAPS_DIRECTORY: &str = "res/maps";
+const REPLAYS_DIRECTORY: &str = "res/replays";
+
+/// Every sample replay together with its path, ordered by file name.
+pub fn replays() -> Vec<(PathBuf, Replay)> {
+    let mut paths = std::fs::read_dir(REPLAYS_DIRECTORY)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "osr"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let replay = Replay::try_from(&std::fs::read(&path).unwrap()[..]).unwrap();
+            (path, replay)
+        })
+        .collect()
+}
+
+/// The sample beatmap a replay was played on, if it is included.
+pub fn map_of(replay: &Replay) -> Option<Beatmap> {
+    std::fs::read_dir(MAPS_DIRECTORY)
+        .unwrap()
+        .flat_map(|set| std::fs::read_dir(set.unwrap().path()).unwrap())
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "osu"))
+        .map(|path| std::fs::read_to_string(path).unwrap())
+        .find(|s| {
+            // The maps may have been checked out with different line endings than osu! hashed them with
+            let crlf = s.replace("\r\n", "\n").replace('\n', "\r\n");
+            [s.as_str(), crlf.as_str()]
+                .iter()
+                .any(|s| format!("{:x}", md5::compute(s)) == replay.map_md5_hash)
+        })
+        .map(|s| Beatmap::from_str(&s).unwrap())
+}
+
+/// The sample beatmap with the given difficulty name.
+pub fn map_named(version: &str) -> Beatmap {
+    std::fs::read_dir(MAPS_DIRECTORY)
+        .unwrap()
+        .flat_map(|set| std::fs::read_dir(set.unwrap().path()).unwrap())
+        .map(|entry| entry.unwrap().path())
+        .find(|path| {
+            path.file_name()
+                .is_some_and(|name| name.to_string_lossy().ends_with(&format!("[{version}].osu")))
+        })
+        .map(|path| Beatmap::from_str(&std::fs::read_to_string(path).unwrap()).unwrap())
+        .unwrap()
+}
+
+/// Every sample replay whose beatmap is included, together with that beatmap.
+pub fn replays_with_maps() -> Vec<(PathBuf, Replay, Beatmap)> {
+    replays()
+        .into_iter()
+        .filter_map(|(path, replay)| {
+            let map = map_of(&replay)?;
+            Some((path, replay, map))
+        })
+        .collect()
+}
+
+/// Creates an empty temporary directory for the test with the given name.
+pub fn temp_directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("sabi-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+/// Writes the replays into the directory together with files that must be skipped when reading it:
+/// a truncated replay, a file which is no replay at all, a file of another type and a directory named like a replay.
+pub fn write_with_bad_files(directory: &Path, replays: &[(&str, &Replay)]) {
+    for (name, replay) in replays {
+        std::fs::write(directory.join(name), replay.to_bytes()).unwrap();
+    }
+    let bytes = replays[0].1.to_bytes();
+    std::fs::write(directory.join("truncated.osr"), &bytes[..bytes.len() / 2]).unwrap();
+    std::fs::write(directory.join("garbage.osr"), b"not a replay").unwrap();
+    std::fs::write(directory.join("notes.txt"), b"").unwrap();
+    std::fs::create_dir(directory.join("directory.osr")).unwrap();
+}
+
+/// A hand-built beatmap for tests. By default its preempt is 1200ms and its stack threshold 840ms,
+/// and there is one beat every 500ms in which sliders move 140 osu!pixels.
+#[derive(Debug, Clone)]
+pub struct TestMap {
+    pub format_version: u8,
+    pub mode: u8,
+    pub hp_drain_rate: f32,
+    pub circle_size: f32,
+    pub overall_difficulty: f32,
+    pub approach_rate: f32,
+    pub slider_multiplier: f32,
+    pub slider_tick_rate: f32,
+    pub stack_leniency: f32,
+    pub timing_points: Vec<String>,
+    pub hit_objects: Vec<String>,
+}
+
+impl TestMap {
+    pub fn new(hit_objects: &[&str]) -> Self {
+        Self {
+            format_version: 14,
+            mode: 0,
+            hp_drain_rate: 5.0,
+            circle_size: 4.0,
+            overall_difficulty: 5.0,
+            approach_rate: 5.0,
+            slider_multiplier: 1.4,
+            slider_tick_rate: 1.0,
+            stack_leniency: 0.7,
+            timing_points: vec!["0,500,4,2,0,100,1,0".to_owned()],
+            hit_objects: hit_objects.iter().map(|&obj| obj.to_owned()).collect(),
+        }
+    }
+
+    /// The beatmap in the .osu format.
+    pub fn to_osu(&self) -> String {
+        format!(
+            "osu file format v{}\n\n\
+             [General]\nStackLeniency: {}\nMode: {}\n\n\
+             [Difficulty]\nHPDrainRate:{}\nCircleSize:{}\nOverallDifficulty:{}\nApproachRate:{}\n\
+             SliderMultiplier:{}\nSliderTickRate:{}\n\n\
+             [TimingPoints]\n{}\n\n\
+             [HitObjects]\n{}\n",
+            self.format_version,
+            self.stack_leniency,
+            self.mode,
+            self.hp_drain_rate,
+            self.circle_size,
+            self.overall_difficulty,
+            self.approach_rate,
+            self.slider_multiplier,
+            self.slider_tick_rate,
+            self.timing_points.join("\n"),
+            self.hit_objects.join("\n"),
+        )
+    }
+
+    pub fn build(&self) -> Beatmap {
+        Beatmap::from_str(&self.to_osu()).unwrap()
+    }
+}
+
+/// A replay of the given mode consisting of frames at the given absolute times.
+/// The hit counts, score and combo are left empty.
+pub fn replay(mode: GameMode, mods: Mods, frames: &[(i64, f32, f32, InputKeys)]) -> Replay {
+    let mut previous = 0;
+    let replay_data = frames
+        .iter()
+        .map(|&(time, x, y, keys)| {
+            let frame = ReplayFrame::new(time - previous, x, y, keys);
+            previous = time;
+            frame
+        })
+        .collect();
+    Replay {
+        mode,
+        game_ver: 0,
+        map_md5_hash: String::new(),
+        player_name: String::new(),
+        replay_md5_hash: String::new(),
+        n_300: 0,
+        n_100: 0,
+        n_50: 0,
+        n_geki: 0,
+        n_katu: 0,
+        n_miss: 0,
+        total_score: 0,
+        max_combo: 0,
+        perfect_combo: false,
+        mods,
+        life_bar_graph: vec![],
+        time_stamp: 0,
+        compressed_data_length: 0,
+        replay_data,
+        seed: None,
+        online_score_id: 0,
+        total_hit_accuracy: 0.0,
+    }
+}
+
+/// The frames of keys held from the start until the end time (exclusive) of each press, with the cursor at the given position.
+pub fn key_frames(
+    position: (f32, f32),
+    presses: &[(i64, i64, InputKeys)],
+) -> Vec<(i64, f32, f32, InputKeys)> {
+    let mut times = presses
+        .iter()
+        .flat_map(|&(start, end, _)| [start, end])
+        .collect::<Vec<_>>();
+    times.sort_unstable();
+    times.dedup();
+    times
+        .into_iter()
+        .map(|time| {
+            let keys = presses
+                .iter()
+                .filter(|&&(start, end, _)| start <= time && time < end)
+                .fold(InputKeys::empty(), |held, &(_, _, keys)| held | keys);
+            (time, position.0, position.1, keys)
+        })
+        .collect()
+}
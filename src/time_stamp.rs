@@ -0,0 +1,159 @@
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+
+use crate::replay::{read_replays, Replay};
+
+/// The number of .NET ticks (100 nanoseconds each) in a millisecond.
+const TICKS_PER_MILLISECOND: u64 = 10_000;
+/// The number of .NET ticks in a day.
+const TICKS_PER_DAY: u64 = 24 * 60 * 60 * 1000 * TICKS_PER_MILLISECOND;
+/// The number of .NET ticks between 0001-01-01 and the Unix epoch (1970-01-01).
+const UNIX_EPOCH_TICKS: u64 = 621_355_968_000_000_000;
+
+/// A point in time as replays store it: the number of .NET ticks (100 nanoseconds each) since 0001-01-01 00:00 UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct TimeStamp {
+    ticks: u64,
+}
+
+impl TimeStamp {
+    pub fn from_ticks(ticks: u64) -> Self {
+        Self { ticks }
+    }
+
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// The time stamp of the given number of milliseconds since the Unix epoch, or `None` if it is before 0001-01-01.
+    pub fn from_unix_millis(millis: i64) -> Option<Self> {
+        let ticks = (millis as i128 * TICKS_PER_MILLISECOND as i128) + UNIX_EPOCH_TICKS as i128;
+        u64::try_from(ticks).ok().map(Self::from_ticks)
+    }
+
+    /// The number of milliseconds since the Unix epoch, which is negative for times before 1970.
+    pub fn unix_millis(&self) -> i64 {
+        (self.ticks as i128 - UNIX_EPOCH_TICKS as i128).div_euclid(TICKS_PER_MILLISECOND as i128)
+            as i64
+    }
+
+    /// The time stamp of the given date and time, or `None` if it is before 0001-01-01.
+    pub fn from_date_time(date_time: DateTime<Utc>) -> Option<Self> {
+        let since_epoch = date_time - DateTime::UNIX_EPOCH;
+        let ticks = since_epoch.num_microseconds()? as i128 * 10 + UNIX_EPOCH_TICKS as i128;
+        u64::try_from(ticks).ok().map(Self::from_ticks)
+    }
+
+    /// The date and time in UTC, to the microsecond.
+    pub fn date_time(&self) -> DateTime<Utc> {
+        let micros = (self.ticks as i128 - UNIX_EPOCH_TICKS as i128).div_euclid(10) as i64;
+        DateTime::UNIX_EPOCH + TimeDelta::microseconds(micros)
+    }
+
+    /// The date in UTC.
+    pub fn date(&self) -> NaiveDate {
+        self.date_time().date_naive()
+    }
+
+    /// Formats the date like the names osu! gives replay files, e.g. `2021-10-20`.
+    pub fn format_date(&self) -> String {
+        self.date().format("%Y-%m-%d").to_string()
+    }
+
+    /// The time stamp at midnight (in UTC) of the same day, which removes the time of day.
+    pub fn start_of_day(&self) -> Self {
+        Self::from_ticks(self.ticks - self.ticks % TICKS_PER_DAY)
+    }
+
+    /// Whether the time stamp is on or between the given dates (in UTC).
+    pub fn is_between(&self, first: NaiveDate, last: NaiveDate) -> bool {
+        (first..=last).contains(&self.date())
+    }
+}
+
+impl From<u64> for TimeStamp {
+    fn from(ticks: u64) -> Self {
+        Self::from_ticks(ticks)
+    }
+}
+
+/// Formats the time stamp as date and time in UTC, e.g. `2021-10-20 21:37:11`.
+impl Display for TimeStamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.date_time().format("%Y-%m-%d %H:%M:%S"))
+    }
+}
+
+/// Reads every replay in the given directory which was played on or between the given dates (in UTC),
+/// ordered from the oldest to the newest play. Files which are not replays, can't be read or are corrupt are skipped.
+pub fn read_replays_played_between(
+    directory: impl AsRef<Path>,
+    first: NaiveDate,
+    last: NaiveDate,
+) -> std::io::Result<Vec<(PathBuf, Replay)>> {
+    let mut replays = read_replays(directory)?
+        .filter(|(_, replay)| replay.played_at().is_between(first, last))
+        .collect::<Vec<_>>();
+    replays.sort_by_key(|(_, replay)| replay.played_at());
+    Ok(replays)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_data;
+
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn time_stamps_of_sample_replays_match_file_names() {
+        for (path, replay) in test_data::replays() {
+            let name = path.file_name().unwrap().to_str().unwrap();
+            let date = replay.played_at().format_date();
+            assert!(name.contains(&format!("({date})")), "{name}");
+        }
+    }
+
+    #[test]
+    fn conversions_round_trip() {
+        let time_stamp = TimeStamp::from_unix_millis(1_634_760_000_123).unwrap();
+        assert_eq!(time_stamp.unix_millis(), 1_634_760_000_123);
+        assert_eq!(
+            TimeStamp::from_date_time(time_stamp.date_time()),
+            Some(time_stamp)
+        );
+        assert_eq!(time_stamp.to_string(), "2021-10-20 20:00:00");
+        assert_eq!(time_stamp.start_of_day().to_string(), "2021-10-20 00:00:00");
+        assert_eq!(TimeStamp::from_unix_millis(i64::MIN), None);
+    }
+
+    #[test]
+    fn read_replays_played_between_skips_bad_files() {
+        let replays = test_data::replays();
+        let directory = test_data::temp_directory("played-between");
+        test_data::write_with_bad_files(
+            &directory,
+            &[("yuyu.osr", &replays[0].1), ("tiferet.osr", &replays[1].1)],
+        );
+
+        let read = |first, last| {
+            read_replays_played_between(&directory, first, last)
+                .unwrap()
+                .into_iter()
+                .map(|(path, _)| path.file_name().unwrap().to_owned())
+                .collect::<Vec<_>>()
+        };
+        let all = read(date(2021, 1, 1), date(2021, 12, 31));
+        let october = read(date(2021, 10, 1), date(2021, 10, 31));
+        std::fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(all, ["yuyu.osr", "tiferet.osr"]);
+        assert_eq!(october, ["tiferet.osr"]);
+    }
+}